@@ -1,26 +1,66 @@
+mod token;
+
 use std::{
+    collections::{HashMap, HashSet, VecDeque},
     io,
+    sync::Arc,
     task::{Context, Poll},
+    time::Duration,
 };
 
-use vela_core::authenticate::AuthError;
-use vela_protobuf::connect::Info;
-use vela_request::{Config, Request, client};
+use futures::{FutureExt, StreamExt, future::BoxFuture, stream::FuturesUnordered};
+use vela_protobuf::{
+    common::{Code, Metadata, Status},
+    connect::Info,
+};
+use vela_request::{Config, Request, Response, client};
 use volans::{
     core::{PeerId, Url},
     request::RequestId,
     swarm::{
         BehaviorEvent, ConnectionDenied, ConnectionId, DialOpts, NetworkBehavior,
         NetworkOutgoingBehavior, THandlerAction, THandlerEvent,
+        behavior::CloseConnection,
         error::{ConnectionError, DialError},
     },
 };
 
-use crate::PROTOCOL_NAME;
+pub use self::token::{RetryPolicy, Token, TokenProvider};
+use crate::{
+    AUTHENTICATE_SERVICE, EXPIRES_IN_METADATA, LAST_SEQ_METADATA, PROTOCOL_NAME, REFRESH_SERVICE,
+    REFRESH_TOKEN_METADATA, RESUME_SERVICE, RESUME_TOKEN_METADATA, TOKEN_METADATA,
+};
 
 pub struct Behavior {
     info: Info,
     inner: client::Behavior<Info, Info>,
+    provider: Option<Arc<dyn TokenProvider>>,
+    retry: RetryPolicy,
+    refresh_before: Duration,
+    /// 自动认证的连接
+    connections: HashMap<ConnectionId, Connection>,
+    fetching: FuturesUnordered<BoxFuture<'static, (ConnectionId, io::Result<Token>)>>,
+    timers: FuturesUnordered<BoxFuture<'static, (ConnectionId, Timer)>>,
+    pending_refresh: HashSet<RequestId>,
+    pending_resume: HashSet<RequestId>,
+    /// 下一个连接用于恢复会话的恢复令牌与消息序号
+    resume_next: Option<(String, u64)>,
+    pending_event: VecDeque<Event>,
+    pending_close: VecDeque<(PeerId, ConnectionId)>,
+}
+
+struct Connection {
+    peer_id: PeerId,
+    token: Option<Token>,
+    /// 连续失败的次数
+    attempts: u32,
+    /// 令牌更新后旧的刷新计时失效
+    generation: u64,
+}
+
+enum Timer {
+    Retry,
+    Refresh(u64),
 }
 
 impl Behavior {
@@ -28,14 +68,382 @@ impl Behavior {
         Self {
             info,
             inner: client::Behavior::new(config),
+            provider: None,
+            retry: RetryPolicy::default(),
+            refresh_before: Duration::from_secs(60),
+            connections: HashMap::new(),
+            fetching: FuturesUnordered::new(),
+            timers: FuturesUnordered::new(),
+            pending_refresh: HashSet::new(),
+            pending_resume: HashSet::new(),
+            resume_next: None,
+            pending_event: VecDeque::new(),
+            pending_close: VecDeque::new(),
         }
     }
 
+    /// 在每个新连接上自动认证
+    ///
+    /// 认证失败时按 [`RetryPolicy`] 重新获取令牌并重试，令牌带有刷新令牌时在过期前自动刷新。
+    /// 重连时恢复会话请使用 [`Behavior::resume_next_connection`]。
+    pub fn with_token_provider(mut self, provider: impl TokenProvider) -> Self {
+        self.provider = Some(Arc::new(provider));
+        self
+    }
+
+    /// 认证失败后的重试策略，默认为 [`RetryPolicy::default`]
+    ///
+    /// 重试在原连接上进行，服务端开启 `close_on_failure` 时连接已被关闭，需要重新拨号。
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// 在访问令牌过期前多久刷新，默认为 60 秒
+    pub fn with_refresh_before(mut self, refresh_before: Duration) -> Self {
+        self.refresh_before = refresh_before;
+        self
+    }
+
     pub fn send_authentication(&mut self, peer_id: PeerId, token: String) -> RequestId {
-        let mut request = Request::new("vela.connect.authenticate".to_string(), self.info.clone());
-        request.add_metadata("x-token".to_string(), token);
+        let mut request = Request::new(AUTHENTICATE_SERVICE.to_string(), self.info.clone());
+        request.add_metadata(TOKEN_METADATA.to_string(), token);
         self.inner.send_request(peer_id, PROTOCOL_NAME, request)
     }
+
+    /// 使用刷新令牌换取新的访问令牌，连接需已认证
+    pub fn send_refresh(&mut self, peer_id: PeerId, refresh_token: String) -> RequestId {
+        let mut request = Request::new(REFRESH_SERVICE.to_string(), self.info.clone());
+        request.add_metadata(REFRESH_TOKEN_METADATA.to_string(), refresh_token);
+        let request_id = self.inner.send_request(peer_id, PROTOCOL_NAME, request);
+        self.pending_refresh.insert(request_id);
+        request_id
+    }
+
+    /// 在新连接上恢复断开的会话
+    ///
+    /// `last_seq` 为最后收到的推送消息序号，服务端据此补发之后的消息。
+    /// 设置了令牌提供者时新连接会立即自动认证，恢复会因连接已认证而失败，
+    /// 此时应在拨号前调用 [`Behavior::resume_next_connection`]。
+    pub fn send_resume(
+        &mut self,
+        peer_id: PeerId,
+        resume_token: String,
+        last_seq: u64,
+    ) -> RequestId {
+        let mut request = Request::new(RESUME_SERVICE.to_string(), self.info.clone());
+        request.add_metadata(RESUME_TOKEN_METADATA.to_string(), resume_token);
+        request.add_metadata(LAST_SEQ_METADATA.to_string(), last_seq.to_string());
+        let request_id = self.inner.send_request(peer_id, PROTOCOL_NAME, request);
+        self.pending_resume.insert(request_id);
+        request_id
+    }
+
+    /// 下一个建立的连接先使用恢复令牌恢复会话，而不是自动认证
+    ///
+    /// 设置了令牌提供者时，恢复失败后再获取令牌认证。
+    pub fn resume_next_connection(&mut self, resume_token: String, last_seq: u64) {
+        self.resume_next = Some((resume_token, last_seq));
+    }
+
+    fn fetch_token(&mut self, connection_id: ConnectionId) {
+        if let Some(provider) = self.provider.clone() {
+            self.fetching
+                .push(async move { (connection_id, provider.token().await) }.boxed());
+        }
+    }
+
+    fn on_token(&mut self, connection_id: ConnectionId, result: io::Result<Token>) {
+        let Some(connection) = self.connections.get_mut(&connection_id) else {
+            return;
+        };
+        let peer_id = connection.peer_id;
+        match result {
+            Ok(token) => {
+                let access_token = token.access_token.clone();
+                connection.token = Some(token);
+                self.send_authentication(peer_id, access_token);
+            }
+            Err(error) => {
+                tracing::warn!("Failed to get token for {}: {}", peer_id, error);
+                self.pending_event.push_back(Event::AuthenticateFailure {
+                    peer_id,
+                    connection_id,
+                    error,
+                });
+                self.retry_or_close(peer_id, connection_id);
+            }
+        }
+    }
+
+    fn schedule(&mut self, connection_id: ConnectionId, delay: Duration, timer: Timer) {
+        self.timers.push(
+            async move {
+                futures_timer::Delay::new(delay).await;
+                (connection_id, timer)
+            }
+            .boxed(),
+        );
+    }
+
+    /// 自动认证的连接按重试策略重试，否则关闭连接
+    fn retry_or_close(&mut self, peer_id: PeerId, connection_id: ConnectionId) {
+        let backoff = match self.connections.get_mut(&connection_id) {
+            Some(connection) => {
+                connection.attempts += 1;
+                self.retry.backoff(connection.attempts)
+            }
+            None => None,
+        };
+        match backoff {
+            Some(backoff) => {
+                tracing::debug!("Retrying authentication to {} in {:?}", peer_id, backoff);
+                self.schedule(connection_id, backoff, Timer::Retry);
+            }
+            None => self.pending_close.push_back((peer_id, connection_id)),
+        }
+    }
+
+    fn schedule_refresh(&mut self, connection_id: ConnectionId) {
+        let expires_in = self
+            .connections
+            .get(&connection_id)
+            .and_then(|connection| connection.token.as_ref())
+            .filter(|token| token.refresh_token.is_some())
+            .and_then(|token| token.expires_in);
+        let Some(expires_in) = expires_in else {
+            return;
+        };
+        let Some(connection) = self.connections.get_mut(&connection_id) else {
+            return;
+        };
+        connection.generation += 1;
+        let generation = connection.generation;
+        let delay = expires_in
+            .saturating_sub(self.refresh_before)
+            .max(Duration::from_secs(1));
+        self.schedule(connection_id, delay, Timer::Refresh(generation));
+    }
+
+    fn on_timer(&mut self, connection_id: ConnectionId, timer: Timer) {
+        let Some(connection) = self.connections.get(&connection_id) else {
+            return;
+        };
+        match timer {
+            Timer::Retry => self.fetch_token(connection_id),
+            Timer::Refresh(generation) if generation == connection.generation => {
+                let peer_id = connection.peer_id;
+                if let Some(refresh_token) = connection
+                    .token
+                    .as_ref()
+                    .and_then(|token| token.refresh_token.clone())
+                {
+                    self.send_refresh(peer_id, refresh_token);
+                }
+            }
+            Timer::Refresh(_) => {}
+        }
+    }
+
+    fn on_authenticated(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        response: Response<Info>,
+    ) {
+        let resume_token = metadata_value(response.metadata(), RESUME_TOKEN_METADATA);
+        match response.into_payload() {
+            Ok(info) => {
+                if let Some(connection) = self.connections.get_mut(&connection_id) {
+                    connection.attempts = 0;
+                }
+                self.schedule_refresh(connection_id);
+                self.pending_event.push_back(Event::Authenticated {
+                    peer_id,
+                    connection_id,
+                    info,
+                    resume_token,
+                });
+            }
+            Err(status) => {
+                tracing::warn!("Authentication to {} failed: {}", peer_id, status);
+                let retryable = is_retryable(status.code_enum());
+                self.pending_event.push_back(Event::Unauthenticated {
+                    peer_id,
+                    connection_id,
+                    status,
+                });
+                if retryable {
+                    self.retry_or_close(peer_id, connection_id);
+                } else {
+                    self.pending_close.push_back((peer_id, connection_id));
+                }
+            }
+        }
+    }
+
+    fn on_refreshed(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        response: Response<Info>,
+    ) {
+        let metadata = |key: &str| metadata_value(response.metadata(), key);
+        let tokens = (
+            metadata(TOKEN_METADATA),
+            metadata(REFRESH_TOKEN_METADATA),
+            metadata(EXPIRES_IN_METADATA).and_then(|v| v.parse().ok()),
+        );
+        let event = match (response.payload(), tokens) {
+            (Ok(_), (Some(access_token), Some(refresh_token), Some(expires_in))) => {
+                let expires_in = Duration::from_secs(expires_in);
+                if let Some(connection) = self.connections.get_mut(&connection_id) {
+                    let token = Token::new(access_token.clone())
+                        .with_refresh_token(refresh_token.clone(), expires_in);
+                    if let Some(provider) = &self.provider {
+                        provider.on_refreshed(&token);
+                    }
+                    connection.token = Some(token);
+                    self.schedule_refresh(connection_id);
+                }
+                Event::Refreshed {
+                    peer_id,
+                    connection_id,
+                    access_token,
+                    refresh_token,
+                    expires_in,
+                }
+            }
+            (Ok(_), _) => Event::RefreshFailed {
+                peer_id,
+                connection_id,
+                status: Status::new(Code::Internal, "Missing tokens in response"),
+            },
+            (Err(status), _) => Event::RefreshFailed {
+                peer_id,
+                connection_id,
+                status: status.clone(),
+            },
+        };
+        if matches!(event, Event::RefreshFailed { .. }) {
+            self.on_refresh_failed(connection_id);
+        }
+        self.pending_event.push_back(event);
+    }
+
+    /// 刷新失败时重新获取令牌认证
+    fn on_refresh_failed(&mut self, connection_id: ConnectionId) {
+        if self.connections.contains_key(&connection_id) {
+            self.fetch_token(connection_id);
+        }
+    }
+
+    fn on_resumed(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        response: Response<Info>,
+    ) {
+        let resume_token = metadata_value(response.metadata(), RESUME_TOKEN_METADATA);
+        let event = match (response.into_payload(), resume_token) {
+            (Ok(info), Some(resume_token)) => Event::Resumed {
+                peer_id,
+                connection_id,
+                info,
+                resume_token,
+            },
+            (Ok(_), None) => Event::ResumeFailed {
+                peer_id,
+                connection_id,
+                status: Status::new(Code::Internal, "Missing resume token in response"),
+            },
+            (Err(status), _) => Event::ResumeFailed {
+                peer_id,
+                connection_id,
+                status,
+            },
+        };
+        if matches!(event, Event::ResumeFailed { .. }) {
+            self.on_resume_failed(connection_id);
+        }
+        self.pending_event.push_back(event);
+    }
+
+    /// 自动认证的连接恢复失败时改为获取令牌认证
+    fn on_resume_failed(&mut self, connection_id: ConnectionId) {
+        if self.connections.contains_key(&connection_id) {
+            self.fetch_token(connection_id);
+        }
+    }
+
+    fn on_request_event(&mut self, event: client::Event<Response<Info>>) {
+        match event {
+            client::Event::Response {
+                peer_id,
+                connection_id,
+                request_id,
+                response,
+            } => {
+                if self.pending_refresh.remove(&request_id) {
+                    self.on_refreshed(peer_id, connection_id, response);
+                } else if self.pending_resume.remove(&request_id) {
+                    self.on_resumed(peer_id, connection_id, response);
+                } else {
+                    self.on_authenticated(peer_id, connection_id, response);
+                }
+            }
+            client::Event::Failure {
+                peer_id,
+                connection_id,
+                request_id,
+                cause,
+            } => {
+                let status = Status::new(Code::Unavailable, cause.to_string());
+                if self.pending_refresh.remove(&request_id) {
+                    self.on_refresh_failed(connection_id);
+                    self.pending_event.push_back(Event::RefreshFailed {
+                        peer_id,
+                        connection_id,
+                        status,
+                    });
+                } else if self.pending_resume.remove(&request_id) {
+                    self.on_resume_failed(connection_id);
+                    self.pending_event.push_back(Event::ResumeFailed {
+                        peer_id,
+                        connection_id,
+                        status,
+                    });
+                } else {
+                    tracing::error!("Authentication request to {} failed: {:?}", peer_id, cause);
+                    self.pending_event.push_back(Event::AuthenticateFailure {
+                        peer_id,
+                        connection_id,
+                        error: cause.into(),
+                    });
+                    self.retry_or_close(peer_id, connection_id);
+                }
+            }
+        }
+    }
+}
+
+fn metadata_value(metadata: &[Metadata], key: &str) -> Option<String> {
+    metadata
+        .iter()
+        .find(|m| m.key == key)
+        .map(|m| m.value.clone())
+}
+
+/// 令牌无效或服务暂不可用时可以重试，版本不兼容等情况重试无效
+fn is_retryable(code: Code) -> bool {
+    !matches!(
+        code,
+        Code::InvalidArgument
+            | Code::PermissionDenied
+            | Code::FailedPrecondition
+            | Code::AlreadyExists
+            | Code::Unimplemented
+    )
 }
 
 impl NetworkBehavior for Behavior {
@@ -56,46 +464,29 @@ impl NetworkBehavior for Behavior {
         cx: &mut Context<'_>,
     ) -> Poll<BehaviorEvent<Self::Event, THandlerAction<Self>>> {
         loop {
+            // 重试计时结束后获取令牌，需在轮询 `fetching` 之前
+            while let Poll::Ready(Some((connection_id, timer))) = self.timers.poll_next_unpin(cx) {
+                self.on_timer(connection_id, timer);
+            }
+            while let Poll::Ready(Some((connection_id, result))) = self.fetching.poll_next_unpin(cx)
+            {
+                self.on_token(connection_id, result);
+            }
+            if let Some((peer_id, connection_id)) = self.pending_close.pop_front() {
+                return Poll::Ready(BehaviorEvent::CloseConnection {
+                    peer_id,
+                    connection: CloseConnection::One(connection_id),
+                });
+            }
+            if let Some(event) = self.pending_event.pop_front() {
+                return Poll::Ready(BehaviorEvent::Behavior(event));
+            }
+
             match self.inner.poll(cx) {
-                Poll::Ready(BehaviorEvent::Behavior(event)) => match event {
-                    client::Event::Failure {
-                        peer_id,
-                        connection_id,
-                        request_id: _,
-                        cause,
-                    } => {
-                        tracing::error!("身份验证错误 {}: {:?}", peer_id, cause);
-                        return Poll::Ready(BehaviorEvent::CloseConnection {
-                            peer_id: peer_id,
-                            connection: volans::swarm::behavior::CloseConnection::One(
-                                connection_id,
-                            ),
-                        });
-                    }
-                    client::Event::Response {
-                        peer_id,
-                        connection_id,
-                        request_id: _,
-                        response,
-                    } => match response.into_payload() {
-                        Ok(info) => {
-                            return Poll::Ready(BehaviorEvent::Behavior(Event::Authenticated {
-                                peer_id: peer_id,
-                                connection_id: connection_id,
-                                info: info,
-                            }));
-                        }
-                        Err(status) => {
-                            tracing::warn!("身份验证失败 {}: {:?}", peer_id, status);
-                            return Poll::Ready(BehaviorEvent::CloseConnection {
-                                peer_id: peer_id,
-                                connection: volans::swarm::behavior::CloseConnection::One(
-                                    connection_id,
-                                ),
-                            });
-                        }
-                    },
-                },
+                Poll::Ready(BehaviorEvent::Behavior(event)) => {
+                    self.on_request_event(event);
+                    continue;
+                }
                 Poll::Ready(BehaviorEvent::HandlerAction {
                     peer_id,
                     handler,
@@ -136,6 +527,23 @@ impl NetworkOutgoingBehavior for Behavior {
 
     fn on_connection_established(&mut self, id: ConnectionId, peer_id: PeerId, addr: &Url) {
         self.inner.on_connection_established(id, peer_id, addr);
+        if self.provider.is_some() {
+            self.connections.insert(
+                id,
+                Connection {
+                    peer_id,
+                    token: None,
+                    attempts: 0,
+                    generation: 0,
+                },
+            );
+        }
+        match self.resume_next.take() {
+            Some((resume_token, last_seq)) => {
+                self.send_resume(peer_id, resume_token, last_seq);
+            }
+            None => self.fetch_token(id),
+        }
     }
 
     fn on_connection_closed(
@@ -145,6 +553,7 @@ impl NetworkOutgoingBehavior for Behavior {
         addr: &Url,
         reason: Option<&ConnectionError>,
     ) {
+        self.connections.remove(&id);
         self.inner.on_connection_closed(id, peer_id, addr, reason);
     }
 
@@ -172,19 +581,63 @@ pub enum Event {
         peer_id: PeerId,
         /// 连接 ID。
         connection_id: ConnectionId,
-        /// 玩家 ID。
+        /// 服务端信息，`capabilities` 为协商后的功能。
         info: Info,
+        /// 恢复令牌，服务端未启用会话恢复时为 `None`。
+        resume_token: Option<String>,
     },
-    /// 认证失败。
+    /// 会话已在当前连接上恢复。
+    Resumed {
+        /// 对端节点 ID。
+        peer_id: PeerId,
+        /// 连接 ID。
+        connection_id: ConnectionId,
+        /// 服务端信息。
+        info: Info,
+        /// 新的恢复令牌，旧令牌已失效。
+        resume_token: String,
+    },
+    /// 会话恢复失败，需要重新认证，设置了令牌提供者时会自动认证。
+    ResumeFailed {
+        /// 对端节点 ID。
+        peer_id: PeerId,
+        /// 连接 ID。
+        connection_id: ConnectionId,
+        /// 失败状态。
+        status: Status,
+    },
+    /// 认证被服务端拒绝。
     Unauthenticated {
         /// 对端节点 ID。
         peer_id: PeerId,
         /// 连接 ID。
         connection_id: ConnectionId,
-        /// 认证失败的原因。
-        cause: AuthError,
+        /// 服务端返回的状态。
+        status: Status,
+    },
+    /// 访问令牌已刷新。
+    Refreshed {
+        /// 对端节点 ID。
+        peer_id: PeerId,
+        /// 连接 ID。
+        connection_id: ConnectionId,
+        /// 新的访问令牌。
+        access_token: String,
+        /// 新的刷新令牌，旧的刷新令牌仍在有效期内但不应再使用。
+        refresh_token: String,
+        /// 访问令牌有效期。
+        expires_in: Duration,
+    },
+    /// 刷新令牌失败，连接保持不变。
+    RefreshFailed {
+        /// 对端节点 ID。
+        peer_id: PeerId,
+        /// 连接 ID。
+        connection_id: ConnectionId,
+        /// 失败状态。
+        status: Status,
     },
-    /// 认证过程中发生错误。
+    /// 认证请求未能完成，如获取令牌失败或连接中断。
     AuthenticateFailure {
         /// 对端节点 ID。
         peer_id: PeerId,
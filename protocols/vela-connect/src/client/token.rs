@@ -0,0 +1,107 @@
+use std::{io, time::Duration};
+
+use vela_core::issuer::TokenPair;
+
+/// 客户端持有的令牌
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub access_token: String,
+    /// 用于在过期前刷新访问令牌
+    pub refresh_token: Option<String>,
+    /// 访问令牌有效期
+    pub expires_in: Option<Duration>,
+}
+
+impl Token {
+    pub fn new(access_token: impl Into<String>) -> Self {
+        Self {
+            access_token: access_token.into(),
+            refresh_token: None,
+            expires_in: None,
+        }
+    }
+
+    pub fn with_refresh_token(
+        mut self,
+        refresh_token: impl Into<String>,
+        expires_in: Duration,
+    ) -> Self {
+        self.refresh_token = Some(refresh_token.into());
+        self.expires_in = Some(expires_in);
+        self
+    }
+}
+
+impl From<TokenPair> for Token {
+    fn from(pair: TokenPair) -> Self {
+        Token::new(pair.access_token).with_refresh_token(pair.refresh_token, pair.expires_in)
+    }
+}
+
+/// 认证令牌来源
+///
+/// 每次建立连接或重试认证时调用 [`TokenProvider::token`]。
+#[async_trait::async_trait]
+pub trait TokenProvider: Send + Sync + 'static {
+    async fn token(&self) -> io::Result<Token>;
+
+    /// 令牌刷新成功，可在此保存新的令牌
+    fn on_refreshed(&self, _token: &Token) {}
+}
+
+/// 固定的令牌
+#[async_trait::async_trait]
+impl TokenProvider for Token {
+    async fn token(&self) -> io::Result<Token> {
+        Ok(self.clone())
+    }
+}
+
+/// 认证失败后的重试策略
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub(crate) max_retries: u32,
+    pub(crate) initial_backoff: Duration,
+    pub(crate) max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 最多重试次数，默认为 5，超过后关闭连接
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// 首次重试的等待时间，之后每次翻倍，默认为 500 毫秒
+    pub fn with_initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// 最长等待时间，默认为 30 秒
+    pub fn with_max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// 第 `attempt` 次重试前的等待时间，超过重试次数时返回 `None`
+    pub(crate) fn backoff(&self, attempt: u32) -> Option<Duration> {
+        if attempt == 0 || attempt > self.max_retries {
+            return None;
+        }
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt - 1));
+        Some(backoff.min(self.max_backoff))
+    }
+}
@@ -1,6 +1,37 @@
 pub mod client;
+pub mod presence;
 pub mod server;
 
 use volans::swarm::StreamProtocol;
 
 pub const PROTOCOL_NAME: StreamProtocol = StreamProtocol::new("/v1/connect");
+
+pub const AUTHENTICATE_SERVICE: &str = "vela.connect.authenticate";
+/// 使用刷新令牌换取新的访问令牌
+pub const REFRESH_SERVICE: &str = "vela.connect.refresh";
+
+/// 访问令牌
+pub const TOKEN_METADATA: &str = "x-token";
+/// 刷新令牌
+pub const REFRESH_TOKEN_METADATA: &str = "x-refresh-token";
+/// 访问令牌有效期（秒）
+pub const EXPIRES_IN_METADATA: &str = "x-expires-in";
+
+/// 断线后使用恢复令牌将会话重新关联到新连接
+pub const RESUME_SERVICE: &str = "vela.connect.resume";
+/// 恢复令牌
+pub const RESUME_TOKEN_METADATA: &str = "x-resume-token";
+/// 客户端最后收到的推送消息序号
+pub const LAST_SEQ_METADATA: &str = "x-last-seq";
+
+/// 握手中交换的功能
+pub mod capability {
+    /// 消息压缩
+    pub const COMPRESSION: &str = "compression";
+    /// 服务端推送
+    pub const PUSH: &str = "push";
+    /// 会话恢复，服务端启用恢复窗口时提供
+    pub const RESUME: &str = "resume";
+    /// 令牌刷新，服务端配置签发器时提供
+    pub const REFRESH: &str = "refresh";
+}
@@ -0,0 +1,341 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    pin::Pin,
+    task::{Context, Poll, Waker},
+    time::{Duration, Instant},
+};
+
+use futures::{FutureExt, Stream};
+use smallvec::SmallVec;
+use vela_core::ids::{PlayerId, SessionId};
+use volans::{ping, swarm::ConnectionId};
+
+use crate::server;
+
+/// 会话的在线状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Presence {
+    /// 会话已离线，不再跟踪
+    Offline,
+    /// 心跳超时、失败或连接已断开，仍在宽限期内
+    Away,
+    Online,
+}
+
+/// 在线状态的宽限期
+#[derive(Debug, Clone)]
+pub struct Config {
+    away_after: Duration,
+    offline_after: Duration,
+    check_interval: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            away_after: Duration::from_secs(30),
+            offline_after: Duration::from_secs(60),
+            check_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+impl Config {
+    /// 超过该时间未收到心跳时标记为 [`Presence::Away`]，默认为 30 秒
+    pub fn with_away_after(mut self, duration: Duration) -> Self {
+        self.away_after = duration;
+        self
+    }
+
+    /// 处于 [`Presence::Away`] 超过该时间后标记为 [`Presence::Offline`]，默认为 60 秒
+    pub fn with_offline_after(mut self, duration: Duration) -> Self {
+        self.offline_after = duration;
+        self
+    }
+
+    /// 检查宽限期的间隔，默认为 1 秒
+    pub fn with_check_interval(mut self, interval: Duration) -> Self {
+        self.check_interval = interval;
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// 会话的在线状态变化
+    ///
+    /// 同一玩家可能有多个会话，玩家整体的状态见 [`Tracker::presence`]。
+    Changed {
+        player_id: PlayerId,
+        session_id: SessionId,
+        previous: Presence,
+        presence: Presence,
+    },
+}
+
+struct Entry {
+    player_id: PlayerId,
+    /// 会话当前所在的连接，连接断开后为 `None`
+    connection_id: Option<ConnectionId>,
+    presence: Presence,
+    /// 进入当前状态的时间
+    since: Instant,
+    /// 最后一次收到心跳的时间
+    last_seen: Instant,
+    rtt: Option<Duration>,
+}
+
+/// 玩家在线状态跟踪
+///
+/// 由调用方传入连接服务端事件与心跳事件，
+/// 状态变化通过 [`Stream`] 产生 [`Event`]。
+pub struct Tracker {
+    config: Config,
+    sessions: HashMap<SessionId, Entry>,
+    connections: HashMap<ConnectionId, SessionId>,
+    players: HashMap<PlayerId, SmallVec<[SessionId; 1]>>,
+    check: futures_timer::Delay,
+    pending_event: VecDeque<Event>,
+    waker: Option<Waker>,
+}
+
+impl Default for Tracker {
+    fn default() -> Self {
+        Self::new(Config::default())
+    }
+}
+
+impl Tracker {
+    pub fn new(config: Config) -> Self {
+        Self {
+            check: futures_timer::Delay::new(config.check_interval),
+            config,
+            sessions: HashMap::new(),
+            connections: HashMap::new(),
+            players: HashMap::new(),
+            pending_event: VecDeque::new(),
+            waker: None,
+        }
+    }
+
+    /// 处理连接服务端事件
+    pub fn on_connect_event<TClaims>(&mut self, event: &server::Event<TClaims>) {
+        match event {
+            server::Event::Authenticated {
+                connection_id,
+                identity,
+                ..
+            } => {
+                self.attach(
+                    identity.session_id().clone(),
+                    identity.player_id().clone(),
+                    *connection_id,
+                );
+            }
+            server::Event::Resumed {
+                connection_id,
+                session_id,
+                player_id,
+                ..
+            } => {
+                self.attach(session_id.clone(), player_id.clone(), *connection_id);
+            }
+            server::Event::SessionDisconnected { binding, .. }
+                if self.connections.get(&binding.connection_id) == Some(&binding.session_id) =>
+            {
+                self.connections.remove(&binding.connection_id);
+                if let Some(entry) = self.sessions.get_mut(&binding.session_id) {
+                    entry.connection_id = None;
+                }
+                self.transition(&binding.session_id, Presence::Away);
+            }
+            server::Event::ResumeExpired { session_id, .. } => {
+                self.transition(session_id, Presence::Offline);
+            }
+            server::Event::SessionReplaced { session_id, .. } => {
+                // 新会话认证后会单独跟踪
+                self.transition(session_id, Presence::Offline);
+            }
+            _ => {}
+        }
+    }
+
+    /// 处理心跳事件，未认证连接上的心跳被忽略
+    ///
+    /// 返回收到心跳的会话。跟踪器不访问会话注册表，
+    /// 注册表带有过期时间时由调用方对返回的会话调用 [`SessionRegistry::touch`] 续期。
+    ///
+    /// [`SessionRegistry::touch`]: vela_core::session::SessionRegistry::touch
+    pub fn on_ping_event(&mut self, event: &ping::Event) -> Option<SessionId> {
+        let session_id = self.connections.get(&event.connection).cloned()?;
+        match &event.result {
+            Ok(rtt) => {
+                if let Some(entry) = self.sessions.get_mut(&session_id) {
+                    entry.last_seen = Instant::now();
+                    entry.rtt = Some(*rtt);
+                }
+                self.transition(&session_id, Presence::Online);
+                Some(session_id)
+            }
+            Err(failure) => {
+                tracing::debug!("Ping failed for session {}: {}", session_id, failure);
+                self.transition(&session_id, Presence::Away);
+                None
+            }
+        }
+    }
+
+    /// 玩家的在线状态，多个会话时取最好的状态
+    pub fn presence(&self, player_id: &PlayerId) -> Presence {
+        self.entries_of(player_id)
+            .map(|entry| entry.presence)
+            .max()
+            .unwrap_or(Presence::Offline)
+    }
+
+    pub fn session_presence(&self, session_id: &SessionId) -> Presence {
+        self.sessions
+            .get(session_id)
+            .map_or(Presence::Offline, |entry| entry.presence)
+    }
+
+    /// 玩家在线会话中最近一次心跳的最小往返时间
+    pub fn rtt(&self, player_id: &PlayerId) -> Option<Duration> {
+        self.entries_of(player_id)
+            .filter(|entry| entry.presence == Presence::Online)
+            .filter_map(|entry| entry.rtt)
+            .min()
+    }
+
+    /// 所有被跟踪的玩家
+    pub fn players(&self) -> impl Iterator<Item = &PlayerId> {
+        self.players.keys()
+    }
+
+    fn entries_of(&self, player_id: &PlayerId) -> impl Iterator<Item = &Entry> {
+        self.players
+            .get(player_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|session_id| self.sessions.get(session_id))
+    }
+
+    fn attach(&mut self, session_id: SessionId, player_id: PlayerId, connection_id: ConnectionId) {
+        // 玩家重新认证后不再跟踪已断开的旧会话
+        let detached: Vec<SessionId> = self
+            .players
+            .get(&player_id)
+            .into_iter()
+            .flatten()
+            .filter(|id| **id != session_id)
+            .filter(|id| self.sessions[*id].connection_id.is_none())
+            .cloned()
+            .collect();
+        for id in detached {
+            self.transition(&id, Presence::Offline);
+        }
+
+        let now = Instant::now();
+        let entry = self.sessions.entry(session_id.clone()).or_insert_with(|| {
+            let sessions = self.players.entry(player_id.clone()).or_default();
+            sessions.push(session_id.clone());
+            Entry {
+                player_id,
+                connection_id: None,
+                presence: Presence::Offline,
+                since: now,
+                last_seen: now,
+                rtt: None,
+            }
+        });
+        if let Some(old) = entry.connection_id.replace(connection_id) {
+            self.connections.remove(&old);
+        }
+        entry.last_seen = now;
+        self.connections.insert(connection_id, session_id.clone());
+        self.transition(&session_id, Presence::Online);
+    }
+
+    fn transition(&mut self, session_id: &SessionId, presence: Presence) {
+        let Some(entry) = self.sessions.get_mut(session_id) else {
+            return;
+        };
+        if entry.presence == presence {
+            return;
+        }
+        let previous = entry.presence;
+        entry.presence = presence;
+        entry.since = Instant::now();
+        let player_id = entry.player_id.clone();
+        if presence == Presence::Offline {
+            self.remove(session_id);
+        }
+        self.pending_event.push_back(Event::Changed {
+            player_id,
+            session_id: session_id.clone(),
+            previous,
+            presence,
+        });
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn remove(&mut self, session_id: &SessionId) {
+        let Some(entry) = self.sessions.remove(session_id) else {
+            return;
+        };
+        if let Some(connection_id) = entry.connection_id {
+            self.connections.remove(&connection_id);
+        }
+        if let Some(sessions) = self.players.get_mut(&entry.player_id) {
+            sessions.retain(|id| id != session_id);
+            if sessions.is_empty() {
+                self.players.remove(&entry.player_id);
+            }
+        }
+    }
+
+    /// 检查超过宽限期的会话
+    fn check_grace_periods(&mut self) {
+        let mut away = Vec::new();
+        let mut offline = Vec::new();
+        for (session_id, entry) in &self.sessions {
+            match entry.presence {
+                Presence::Online if entry.last_seen.elapsed() >= self.config.away_after => {
+                    away.push(session_id.clone());
+                }
+                Presence::Away if entry.since.elapsed() >= self.config.offline_after => {
+                    offline.push(session_id.clone());
+                }
+                _ => {}
+            }
+        }
+        for session_id in away {
+            self.transition(&session_id, Presence::Away);
+        }
+        for session_id in offline {
+            self.transition(&session_id, Presence::Offline);
+        }
+    }
+}
+
+impl Stream for Tracker {
+    type Item = Event;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            if let Some(event) = this.pending_event.pop_front() {
+                return Poll::Ready(Some(event));
+            }
+            if this.check.poll_unpin(cx).is_ready() {
+                this.check.reset(this.config.check_interval);
+                this.check_grace_periods();
+                continue;
+            }
+            this.waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+    }
+}
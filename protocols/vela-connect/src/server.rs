@@ -1,58 +1,1009 @@
+mod bindings;
+mod limits;
+mod negotiation;
+
 use std::{
-    collections::{HashMap, VecDeque},
-    io,
+    collections::{HashMap, HashSet, VecDeque},
+    fmt, io,
+    net::IpAddr,
+    sync::Arc,
     task::{Context, Poll},
-    time::Duration,
+    time::{Duration, Instant, SystemTime},
 };
 
-use futures::FutureExt;
+use futures::{FutureExt, StreamExt, future::BoxFuture, stream::FuturesUnordered};
 use futures_bounded::{Delay, FuturesMap};
+use semver::VersionReq;
 use vela_core::{
-    authenticate::{AuthError, Authenticator},
+    authenticate::{AuthError, Authenticator, Identity, RevocationList},
     ids::{PlayerId, SessionId},
+    issuer::JwtIssuer,
+    session::{LocalSessionRegistry, Session, SessionRegistry},
+};
+use vela_protobuf::{
+    common::{Code, Metadata, Status},
+    connect::Info,
 };
-use vela_protobuf::{common::Code, connect::Info};
 use vela_request::{Config, Request, RequestId, Responder, server};
 use volans::{
     core::{PeerId, Url},
     swarm::{
         BehaviorEvent, ConnectionDenied, ConnectionId, ListenerEvent, NetworkBehavior,
         NetworkIncomingBehavior, THandlerAction, THandlerEvent,
+        behavior::CloseConnection,
         error::{ConnectionError, ListenError},
     },
 };
 
-use crate::PROTOCOL_NAME;
+use self::bindings::Bindings;
+pub use self::bindings::{AuthenticatedConnections, Binding};
+use self::limits::{Attempts, ip_of};
+pub use self::limits::{Limits, RejectReason};
+use self::negotiation::Compatibility;
+use crate::{
+    EXPIRES_IN_METADATA, LAST_SEQ_METADATA, PROTOCOL_NAME, REFRESH_SERVICE, REFRESH_TOKEN_METADATA,
+    RESUME_SERVICE, RESUME_TOKEN_METADATA, TOKEN_METADATA, capability,
+};
+
+/// 同一玩家已有会话时的处理策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SessionPolicy {
+    /// 踢出旧会话
+    #[default]
+    KickOld,
+    /// 拒绝新的认证
+    RejectNew,
+    /// 允许同时存在多个会话
+    AllowMultiple,
+}
 
-pub struct Behavior<TAuthenticator> {
+pub struct Behavior<TAuthenticator, TRegistry = LocalSessionRegistry>
+where
+    TAuthenticator: Authenticator<String>,
+{
     info: Info,
+    compatibility: Compatibility,
     authenticator: TAuthenticator,
+    registry: Option<TRegistry>,
+    policy: SessionPolicy,
     inner: server::Behavior<Info, Info>,
-    pending_authentication: HashMap<RequestId, Authentication>,
-    authenticating: FuturesMap<RequestId, Result<(SessionId, PlayerId), AuthError>>,
-    pending_event: VecDeque<Event>,
+    pending_authentication: HashMap<RequestKey, Authentication>,
+    /// 正在验证的令牌，受 `verify_timeout` 限制
+    authenticating: FuturesMap<RequestKey, Result<Identity<TAuthenticator::Claims>, AuthError>>,
+    /// 验证通过后正在登记到注册表的会话
+    registering: FuturesUnordered<Registration<TAuthenticator::Claims>>,
+    issuer: Option<JwtIssuer>,
+    /// 本实例上已认证的连接与会话
+    bindings: Bindings,
+    removing: FuturesUnordered<BoxFuture<'static, ()>>,
+    resume_window: Option<Duration>,
+    /// 可恢复的会话
+    resumable: HashMap<SessionId, Resumable>,
+    resume_tokens: HashMap<String, SessionId>,
+    /// 断开的会话在恢复窗口结束时检查是否已恢复
+    detaching: FuturesUnordered<BoxFuture<'static, SessionId>>,
+    /// 恢复会话前检查是否已撤销
+    revocation: Option<Arc<dyn RevocationList + Send + Sync>>,
+    pending_resume: HashMap<RequestKey, Resume>,
+    /// 正在检查撤销状态的恢复请求，结果为会话是否已撤销
+    resuming: FuturesMap<RequestKey, bool>,
+    pending_refresh: HashMap<RequestKey, Refresh>,
+    /// 正在检查撤销状态的刷新请求
+    refreshing: FuturesMap<RequestKey, bool>,
+    limits: Limits,
+    peer_attempts: Attempts<PeerId>,
+    ip_attempts: Attempts<IpAddr>,
+    prune_attempts: futures_timer::Delay,
+    /// 已建立连接的对端 IP
+    connection_addrs: HashMap<ConnectionId, Option<IpAddr>>,
+    handshake_timeouts: FuturesUnordered<BoxFuture<'static, (PeerId, ConnectionId)>>,
+    /// 响应发送后需要关闭连接的请求
+    close_after_response: HashSet<RequestKey>,
+    pending_event: VecDeque<Event<TAuthenticator::Claims>>,
+    pending_close: VecDeque<(PeerId, ConnectionId)>,
+}
+
+struct Resumable {
+    player_id: PlayerId,
+    token: String,
+    /// 连接断开的时间，已关联连接时为 `None`
+    detached_at: Option<Instant>,
+    /// 认证的到期时间，到期后无法恢复
+    expires_at: Option<SystemTime>,
+}
+
+struct Resume {
+    peer_id: PeerId,
+    connection_id: ConnectionId,
+    session_id: SessionId,
+    /// 请求携带的恢复令牌
+    token: String,
+    last_seq: u64,
+    capabilities: Vec<String>,
+    responder: Responder<Info>,
 }
 
+struct Refresh {
+    peer_id: PeerId,
+    connection_id: ConnectionId,
+    session_id: SessionId,
+    /// 请求携带的刷新令牌
+    token: String,
+    capabilities: Vec<String>,
+    responder: Responder<Info>,
+}
+
+struct Authorized<TClaims> {
+    identity: Identity<TClaims>,
+    /// 被新会话取代的旧会话
+    displaced: Vec<Session>,
+}
+
+type Registration<TClaims> =
+    BoxFuture<'static, (RequestKey, Result<Authorized<TClaims>, AuthError>)>;
+
+/// 进行中请求的标识
+///
+/// volans 0.1 为所有请求分配相同的 `RequestId`，需要与连接一起区分不同连接上的请求。
+type RequestKey = (ConnectionId, RequestId);
+
 struct Authentication {
     peer_id: PeerId,
     connection_id: ConnectionId,
     info: Info,
+    capabilities: Vec<String>,
     responder: Responder<Info>,
 }
 
 impl<TAuthenticator> Behavior<TAuthenticator>
 where
-    TAuthenticator: Authenticator<String> + Clone + Send + 'static,
+    TAuthenticator:
+        Authenticator<String, Claims: fmt::Debug + Send + 'static> + Clone + Send + 'static,
 {
     pub fn new(info: Info, authenticator: TAuthenticator, config: Config) -> Self {
+        let limits = Limits::default();
         Self {
             info,
+            compatibility: Compatibility::default(),
             authenticator,
+            registry: None,
+            policy: SessionPolicy::default(),
             inner: server::Behavior::new(vec![PROTOCOL_NAME], config),
             pending_authentication: HashMap::new(),
-            authenticating: FuturesMap::new(|| Delay::futures_timer(Duration::from_secs(10)), 1000),
+            authenticating: authenticating(&limits),
+            registering: FuturesUnordered::new(),
+            issuer: None,
+            bindings: Bindings::default(),
+            removing: FuturesUnordered::new(),
+            resume_window: None,
+            resumable: HashMap::new(),
+            resume_tokens: HashMap::new(),
+            detaching: FuturesUnordered::new(),
+            revocation: None,
+            pending_resume: HashMap::new(),
+            resuming: authenticating(&limits),
+            pending_refresh: HashMap::new(),
+            refreshing: authenticating(&limits),
+            prune_attempts: futures_timer::Delay::new(limits.window),
+            limits,
+            peer_attempts: Attempts::new(),
+            ip_attempts: Attempts::new(),
+            connection_addrs: HashMap::new(),
+            handshake_timeouts: FuturesUnordered::new(),
+            close_after_response: HashSet::new(),
             pending_event: VecDeque::new(),
+            pending_close: VecDeque::new(),
+        }
+    }
+}
+
+impl<TAuthenticator, TRegistry> Behavior<TAuthenticator, TRegistry>
+where
+    TAuthenticator:
+        Authenticator<String, Claims: fmt::Debug + Send + 'static> + Clone + Send + 'static,
+    TRegistry: SessionRegistry + Clone + Send + 'static,
+{
+    /// 认证成功后将会话登记到注册表，并按 `policy` 处理同一玩家的其他会话
+    ///
+    /// 连接关闭时会话会从注册表中移除。
+    pub fn with_session_registry<R>(
+        self,
+        registry: R,
+        policy: SessionPolicy,
+    ) -> Behavior<TAuthenticator, R>
+    where
+        R: SessionRegistry + Clone + Send + 'static,
+    {
+        Behavior {
+            info: self.info,
+            compatibility: self.compatibility,
+            authenticator: self.authenticator,
+            registry: Some(registry),
+            policy,
+            inner: self.inner,
+            pending_authentication: self.pending_authentication,
+            authenticating: self.authenticating,
+            registering: self.registering,
+            issuer: self.issuer,
+            bindings: self.bindings,
+            removing: self.removing,
+            resume_window: self.resume_window,
+            resumable: self.resumable,
+            resume_tokens: self.resume_tokens,
+            detaching: self.detaching,
+            revocation: self.revocation,
+            pending_resume: self.pending_resume,
+            resuming: self.resuming,
+            pending_refresh: self.pending_refresh,
+            refreshing: self.refreshing,
+            limits: self.limits,
+            peer_attempts: self.peer_attempts,
+            ip_attempts: self.ip_attempts,
+            prune_attempts: self.prune_attempts,
+            connection_addrs: self.connection_addrs,
+            handshake_timeouts: self.handshake_timeouts,
+            close_after_response: self.close_after_response,
+            pending_event: self.pending_event,
+            pending_close: self.pending_close,
+        }
+    }
+
+    pub fn policy(&self) -> SessionPolicy {
+        self.policy
+    }
+
+    /// 启用 `vela.connect.refresh` 服务
+    ///
+    /// 已认证的连接可以用刷新令牌换取新的访问令牌，无需重新连接。
+    pub fn with_issuer(mut self, issuer: JwtIssuer) -> Self {
+        self.issuer = Some(issuer);
+        self.compatibility.offer(capability::REFRESH);
+        self
+    }
+
+    /// 启用 `vela.connect.resume` 服务
+    ///
+    /// 认证成功的响应中携带恢复令牌。连接断开后会话在 `window` 内保留，
+    /// 客户端在新连接上使用恢复令牌重新关联原会话，超时后会话被移除。
+    /// 收到 [`Event::Resumed`] 后可由推送服务补发断线期间的消息。
+    ///
+    /// 认证令牌到期后会话无法恢复，客户端需要重新认证。
+    pub fn with_resume_window(mut self, window: Duration) -> Self {
+        self.resume_window = Some(window);
+        self.compatibility.offer(capability::RESUME);
+        self
+    }
+
+    /// 恢复会话与刷新令牌前检查会话是否已撤销，已撤销的会话无法恢复或刷新
+    ///
+    /// 检查与认证共用 [`Limits`] 中的并发与超时限制。
+    pub fn with_revocation_list<L>(mut self, list: L) -> Self
+    where
+        L: RevocationList + Send + Sync + 'static,
+    {
+        self.revocation = Some(Arc::new(list));
+        self
+    }
+
+    /// 接受的客户端版本，如 `>=1.2.0, <2.0.0`，默认不检查
+    ///
+    /// 客户端版本无法解析或不满足要求时以 [`Code::FailedPrecondition`] 拒绝认证。
+    pub fn with_supported_versions(mut self, versions: VersionReq) -> Self {
+        self.compatibility.versions = Some(versions);
+        self
+    }
+
+    /// 服务端提供的功能，与客户端声明的功能取交集后作为协商结果
+    ///
+    /// 常用功能见 [`capability`]。
+    pub fn with_capabilities<I, S>(mut self, capabilities: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        for capability in capabilities {
+            self.compatibility.offer(capability.as_ref());
+        }
+        self
+    }
+
+    /// 客户端必须支持的功能，缺少时以 [`Code::FailedPrecondition`] 拒绝认证
+    pub fn with_required_capabilities<I, S>(mut self, capabilities: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        for capability in capabilities {
+            let capability = capability.into();
+            self.compatibility.offer(&capability);
+            self.compatibility.required.push(capability);
+        }
+        self
+    }
+
+    /// 响应中携带服务端信息与协商后的功能
+    fn info_with(&self, capabilities: Vec<String>) -> Info {
+        Info {
+            capabilities,
+            ..self.info.clone()
+        }
+    }
+
+    /// 连接上已认证的会话
+    pub fn session_of(&self, connection_id: ConnectionId) -> Option<&Binding> {
+        self.bindings.by_connection(connection_id)
+    }
+
+    /// 会话所在的连接
+    pub fn connection_of(&self, session_id: &SessionId) -> Option<&Binding> {
+        self.bindings.by_session(session_id)
+    }
+
+    /// 玩家在本实例上的所有连接
+    pub fn connections_of(&self, player_id: &PlayerId) -> impl Iterator<Item = &Binding> {
+        self.bindings.by_player(player_id)
+    }
+
+    /// 本实例上所有已认证的连接
+    pub fn bindings(&self) -> impl Iterator<Item = &Binding> {
+        self.bindings.iter()
+    }
+
+    pub fn num_bindings(&self) -> usize {
+        self.bindings.len()
+    }
+
+    /// 已认证连接的共享视图
+    ///
+    /// 可作为其他请求服务的守卫，如
+    /// `router::Behavior::new(..).with_guard(connect.authenticated_connections())`。
+    pub fn authenticated_connections(&self) -> AuthenticatedConnections {
+        self.bindings.shared()
+    }
+
+    /// 断开会话所在的连接，会话不可恢复
+    ///
+    /// 连接关闭后产生 [`Event::SessionDisconnected`]，会话不在本实例上时返回 `false`。
+    pub fn disconnect(&mut self, session_id: &SessionId) -> bool {
+        let Some(binding) = self.bindings.by_session(session_id) else {
+            return false;
+        };
+        self.pending_close
+            .push_back((binding.peer_id, binding.connection_id));
+        self.forget_resumable(session_id);
+        true
+    }
+
+    fn bind(&mut self, binding: Binding) {
+        // 同一连接上认证了其他会话
+        if let Some(old) = self.bindings.remove_connection(binding.connection_id)
+            && old.session_id != binding.session_id
+        {
+            self.forget_resumable(&old.session_id);
+            self.remove_session(old.session_id);
+        }
+        // 旧连接尚未断开时由新连接取代
+        if let Some(old) = self.bindings.insert(binding) {
+            self.pending_close
+                .push_back((old.peer_id, old.connection_id));
+        }
+    }
+
+    /// 认证次数、并发与超时限制，默认为 [`Limits::default`]
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.authenticating = authenticating(&limits);
+        self.resuming = authenticating(&limits);
+        self.prune_attempts = futures_timer::Delay::new(limits.window);
+        self.limits = limits;
+        self
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// 记录一次认证尝试，超过限制时返回拒绝原因
+    fn check_attempt(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+    ) -> Option<RejectReason> {
+        let limits = &self.limits;
+        if !self
+            .peer_attempts
+            .hit(peer_id, limits.max_attempts_per_peer, limits.window)
+        {
+            return Some(RejectReason::PeerRateLimited);
+        }
+        if let Some(Some(ip)) = self.connection_addrs.get(&connection_id)
+            && !self
+                .ip_attempts
+                .hit(*ip, limits.max_attempts_per_ip, limits.window)
+        {
+            return Some(RejectReason::IpRateLimited(*ip));
+        }
+        None
+    }
+
+    fn reject(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        request_id: RequestId,
+        responder: Responder<Info>,
+        reason: RejectReason,
+    ) {
+        tracing::warn!("Rejected authentication from {}: {}", peer_id, reason);
+        let _ = responder.err_response(Status::new(reason.code(), reason.to_string()));
+        self.close_after((connection_id, request_id));
+        self.pending_event.push_back(Event::Rejected {
+            peer_id,
+            connection_id,
+            reason,
+        });
+    }
+
+    /// 认证失败时在响应发送后关闭连接
+    fn close_after(&mut self, key: RequestKey) {
+        if self.limits.close_on_failure {
+            self.close_after_response.insert(key);
+        }
+    }
+
+    fn on_response_finished(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        request_id: RequestId,
+    ) {
+        if self
+            .close_after_response
+            .remove(&(connection_id, request_id))
+        {
+            self.pending_close.push_back((peer_id, connection_id));
+        }
+    }
+
+    fn on_handshake_timeout(&mut self, peer_id: PeerId, connection_id: ConnectionId) {
+        if !self.connection_addrs.contains_key(&connection_id)
+            || self.bindings.by_connection(connection_id).is_some()
+        {
+            return;
+        }
+        tracing::debug!(
+            "Connection {} from {} did not authenticate in time",
+            connection_id,
+            peer_id
+        );
+        self.pending_close.push_back((peer_id, connection_id));
+        self.pending_event.push_back(Event::Rejected {
+            peer_id,
+            connection_id,
+            reason: RejectReason::HandshakeTimeout,
+        });
+    }
+
+    /// 为会话签发新的恢复令牌，旧令牌失效
+    fn issue_resume_token(
+        &mut self,
+        session_id: &SessionId,
+        player_id: &PlayerId,
+        expires_at: Option<SystemTime>,
+    ) -> String {
+        let token = bs58::encode(rand::random::<[u8; 24]>()).into_string();
+        let resumable = Resumable {
+            player_id: player_id.clone(),
+            token: token.clone(),
+            detached_at: None,
+            expires_at,
+        };
+        if let Some(old) = self.resumable.insert(session_id.clone(), resumable) {
+            self.resume_tokens.remove(&old.token);
+        }
+        self.resume_tokens.insert(token.clone(), session_id.clone());
+        token
+    }
+
+    fn forget_resumable(&mut self, session_id: &SessionId) -> Option<Resumable> {
+        let resumable = self.resumable.remove(session_id)?;
+        self.resume_tokens.remove(&resumable.token);
+        Some(resumable)
+    }
+
+    fn remove_session(&mut self, session_id: SessionId) {
+        if let Some(mut registry) = self.registry.clone() {
+            self.removing.push(
+                async move {
+                    registry.remove(session_id).await;
+                }
+                .boxed(),
+            );
+        }
+    }
+
+    fn on_detach_elapsed(&mut self, session_id: SessionId) {
+        let Some(window) = self.resume_window else {
+            return;
+        };
+        let expired = self
+            .resumable
+            .get(&session_id)
+            .and_then(|resumable| resumable.detached_at)
+            .is_some_and(|detached_at| detached_at.elapsed() >= window);
+        if !expired {
+            return;
+        }
+        if let Some(resumable) = self.forget_resumable(&session_id) {
+            tracing::debug!("Session {} was not resumed in time", session_id);
+            self.remove_session(session_id.clone());
+            self.pending_event.push_back(Event::ResumeExpired {
+                player_id: resumable.player_id,
+                session_id,
+            });
+        }
+    }
+
+    fn on_resume(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        request_id: RequestId,
+        request: Request<Info>,
+        capabilities: Vec<String>,
+        responder: Responder<Info>,
+    ) {
+        let Some(window) = self.resume_window else {
+            let _ = responder.err_response(Code::Unimplemented.into());
+            return;
+        };
+        if self.bindings.by_connection(connection_id).is_some() {
+            let _ = responder.err_response(Status::new(
+                Code::FailedPrecondition,
+                "Connection already authenticated",
+            ));
+            return;
+        }
+        let Some(token) = request.get_metadata(RESUME_TOKEN_METADATA) else {
+            let _ =
+                responder.err_response(Status::new(Code::InvalidArgument, "Missing resume token"));
+            return;
+        };
+        let last_seq = match request.get_metadata(LAST_SEQ_METADATA) {
+            Some(value) => match value.parse::<u64>() {
+                Ok(last_seq) => last_seq,
+                Err(_) => {
+                    let _ = responder
+                        .err_response(Status::new(Code::InvalidArgument, "Invalid last seq"));
+                    return;
+                }
+            },
+            None => 0,
+        };
+        let now = SystemTime::now();
+        let session_id = self
+            .resume_tokens
+            .get(token)
+            .filter(|session_id| {
+                let resumable = &self.resumable[*session_id];
+                resumable
+                    .detached_at
+                    .is_none_or(|detached_at| detached_at.elapsed() < window)
+                    && resumable
+                        .expires_at
+                        .is_none_or(|expires_at| expires_at > now)
+            })
+            .cloned();
+        let Some(session_id) = session_id else {
+            self.fail_resume(
+                peer_id,
+                connection_id,
+                request_id,
+                responder,
+                AuthError::InvalidToken("Unknown or expired resume token".to_string()),
+            );
+            return;
+        };
+        let resume = Resume {
+            peer_id,
+            connection_id,
+            session_id,
+            token: token.clone(),
+            last_seq,
+            capabilities,
+            responder,
+        };
+        let Some(list) = self.revocation.clone() else {
+            self.resume(request_id, resume);
+            return;
+        };
+        let session_id = resume.session_id.clone();
+        let fut = async move { list.is_revoked(&session_id).await }.boxed();
+        if self
+            .resuming
+            .try_push((connection_id, request_id), fut)
+            .is_err()
+        {
+            self.reject(
+                peer_id,
+                connection_id,
+                request_id,
+                resume.responder,
+                RejectReason::Overloaded,
+            );
+            return;
+        }
+        // 检查期间令牌不能再次使用
+        self.resume_tokens.remove(&resume.token);
+        self.pending_resume
+            .insert((connection_id, request_id), resume);
+    }
+
+    fn on_resume_checked(&mut self, key: RequestKey, revoked: Result<bool, AuthError>) {
+        let Some(resume) = self.pending_resume.remove(&key) else {
+            return;
+        };
+        let (_, request_id) = key;
+        let current = self
+            .resumable
+            .get(&resume.session_id)
+            .is_some_and(|resumable| resumable.token == resume.token);
+        match revoked {
+            Ok(false) => self.resume(request_id, resume),
+            Ok(true) => {
+                tracing::debug!("Session {} has been revoked", resume.session_id);
+                if current {
+                    self.forget_resumable(&resume.session_id);
+                    if self.bindings.by_session(&resume.session_id).is_none() {
+                        self.remove_session(resume.session_id.clone());
+                    }
+                }
+                let cause = AuthError::InvalidToken(format!(
+                    "Session {} has been revoked",
+                    resume.session_id
+                ));
+                self.fail_resume(
+                    resume.peer_id,
+                    resume.connection_id,
+                    request_id,
+                    resume.responder,
+                    cause,
+                );
+            }
+            Err(cause) => {
+                // 检查超时，恢复令牌仍然有效
+                self.keep_resume_token(&resume);
+                self.fail_resume(
+                    resume.peer_id,
+                    resume.connection_id,
+                    request_id,
+                    resume.responder,
+                    cause,
+                );
+            }
+        }
+    }
+
+    /// 恢复未完成时保留仍有效的恢复令牌
+    fn keep_resume_token(&mut self, resume: &Resume) {
+        if self
+            .resumable
+            .get(&resume.session_id)
+            .is_some_and(|resumable| resumable.token == resume.token)
+        {
+            self.resume_tokens
+                .insert(resume.token.clone(), resume.session_id.clone());
+        }
+    }
+
+    fn resume(&mut self, request_id: RequestId, resume: Resume) {
+        // 连接在检查期间关闭
+        if !self.connection_addrs.contains_key(&resume.connection_id) {
+            self.keep_resume_token(&resume);
+            return;
+        }
+        let Resume {
+            peer_id,
+            connection_id,
+            session_id,
+            token,
+            last_seq,
+            capabilities,
+            mut responder,
+        } = resume;
+        // 检查期间会话可能已被其他连接恢复或已过期
+        let Some(resumable) = self
+            .resumable
+            .get(&session_id)
+            .filter(|resumable| resumable.token == token)
+        else {
+            self.fail_resume(
+                peer_id,
+                connection_id,
+                request_id,
+                responder,
+                AuthError::InvalidToken("Unknown or expired resume token".to_string()),
+            );
+            return;
+        };
+        let player_id = resumable.player_id.clone();
+        let expires_at = resumable.expires_at;
+        let token = self.issue_resume_token(&session_id, &player_id, expires_at);
+        self.bind(Binding {
+            peer_id,
+            connection_id,
+            session_id: session_id.clone(),
+            player_id: player_id.clone(),
+        });
+        if let Some(mut registry) = self.registry.clone() {
+            let session_id = session_id.clone();
+            self.removing.push(
+                async move {
+                    registry.touch(session_id).await;
+                }
+                .boxed(),
+            );
+        }
+        responder.add_metadata(Metadata {
+            key: RESUME_TOKEN_METADATA.to_string(),
+            value: token,
+        });
+        let _ = responder.ok_response(self.info_with(capabilities.clone()));
+        self.pending_event.push_back(Event::Resumed {
+            peer_id,
+            connection_id,
+            session_id,
+            player_id,
+            last_seq,
+            capabilities,
+        });
+    }
+
+    fn fail_resume(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        request_id: RequestId,
+        responder: Responder<Info>,
+        cause: AuthError,
+    ) {
+        let _ = responder.err_response(cause.status());
+        self.close_after((connection_id, request_id));
+        self.pending_event.push_back(Event::Unauthenticated {
+            peer_id,
+            connection_id,
+            cause,
+        });
+    }
+
+    fn on_refresh(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        request_id: RequestId,
+        request: Request<Info>,
+        capabilities: Vec<String>,
+        responder: Responder<Info>,
+    ) {
+        if self.issuer.is_none() {
+            let _ = responder.err_response(Code::Unimplemented.into());
+            return;
+        }
+        let Some(session_id) = self
+            .bindings
+            .by_connection(connection_id)
+            .map(|binding| binding.session_id.clone())
+        else {
+            let _ = responder.err_response(Code::Unauthenticated.into());
+            return;
+        };
+        let Some(token) = request.get_metadata(REFRESH_TOKEN_METADATA).cloned() else {
+            let _ =
+                responder.err_response(Status::new(Code::InvalidArgument, "Missing refresh token"));
+            return;
+        };
+        let refresh = Refresh {
+            peer_id,
+            connection_id,
+            session_id,
+            token,
+            capabilities,
+            responder,
+        };
+        let Some(list) = self.revocation.clone() else {
+            self.refresh(refresh);
+            return;
+        };
+        let session_id = refresh.session_id.clone();
+        let fut = async move { list.is_revoked(&session_id).await }.boxed();
+        if self
+            .refreshing
+            .try_push((connection_id, request_id), fut)
+            .is_err()
+        {
+            self.reject(
+                peer_id,
+                connection_id,
+                request_id,
+                refresh.responder,
+                RejectReason::Overloaded,
+            );
+            return;
+        }
+        self.pending_refresh
+            .insert((connection_id, request_id), refresh);
+    }
+
+    fn on_refresh_checked(&mut self, key: RequestKey, revoked: Result<bool, AuthError>) {
+        let Some(refresh) = self.pending_refresh.remove(&key) else {
+            return;
+        };
+        let cause = match revoked {
+            Ok(false) => {
+                self.refresh(refresh);
+                return;
+            }
+            Ok(true) => {
+                AuthError::InvalidToken(format!("Session {} has been revoked", refresh.session_id))
+            }
+            Err(cause) => cause,
+        };
+        tracing::warn!("Token refresh failed for {}: {:?}", refresh.peer_id, cause);
+        let _ = refresh.responder.err_response(cause.status());
+    }
+
+    /// 撤销检查通过后签发新令牌，旧的刷新令牌随之失效
+    fn refresh(&mut self, refresh: Refresh) {
+        let Refresh {
+            peer_id,
+            connection_id,
+            session_id,
+            token,
+            capabilities,
+            mut responder,
+        } = refresh;
+        let Some(issuer) = &self.issuer else {
+            return;
+        };
+        // 检查期间连接可能已关闭或重新认证为其他会话
+        if self
+            .bindings
+            .by_connection(connection_id)
+            .is_none_or(|binding| binding.session_id != session_id)
+        {
+            let _ = responder.err_response(Code::Unauthenticated.into());
+            return;
         }
+        let pair = match issuer.refresh(&token) {
+            Ok(pair) if pair.session_id == session_id => pair,
+            Ok(_) => {
+                let _ = responder.err_response(Status::new(
+                    Code::PermissionDenied,
+                    "Refresh token belongs to another session",
+                ));
+                return;
+            }
+            Err(cause) => {
+                tracing::warn!("Token refresh failed for {}: {:?}", peer_id, cause);
+                let _ = responder.err_response(cause.status());
+                return;
+            }
+        };
+        responder.add_metadata_from_iter([
+            Metadata {
+                key: TOKEN_METADATA.to_string(),
+                value: pair.access_token,
+            },
+            Metadata {
+                key: REFRESH_TOKEN_METADATA.to_string(),
+                value: pair.refresh_token,
+            },
+            Metadata {
+                key: EXPIRES_IN_METADATA.to_string(),
+                value: pair.expires_in.as_secs().to_string(),
+            },
+        ]);
+        let _ = responder.ok_response(self.info_with(capabilities));
+        self.pending_event.push_back(Event::Refreshed {
+            peer_id,
+            connection_id,
+            session_id: pair.session_id,
+        });
+    }
+
+    fn authenticate(
+        &self,
+        token: String,
+    ) -> BoxFuture<'static, Result<Identity<TAuthenticator::Claims>, AuthError>> {
+        let authenticator = self.authenticator.clone();
+        async move { authenticator.authenticate(token).await }.boxed()
+    }
+
+    /// 令牌验证完成，启用注册表时登记会话
+    ///
+    /// 登记不受 `verify_timeout` 限制，超时取消会使已登记的会话无人移除。
+    fn on_verified(
+        &mut self,
+        key: RequestKey,
+        result: Result<Identity<TAuthenticator::Claims>, AuthError>,
+    ) {
+        let identity = match result {
+            Ok(identity) => identity,
+            Err(cause) => {
+                self.on_authenticated(key, Err(cause));
+                return;
+            }
+        };
+        let Some(registry) = self.registry.clone() else {
+            self.on_authenticated(
+                key,
+                Ok(Authorized {
+                    identity,
+                    displaced: Vec::new(),
+                }),
+            );
+            return;
+        };
+        let Some(peer_id) = self
+            .pending_authentication
+            .get(&key)
+            .map(|authentication| authentication.peer_id)
+        else {
+            return;
+        };
+        let policy = self.policy;
+        let mut session = Session::new(identity.session_id().clone(), identity.player_id().clone())
+            .with_peer_id(peer_id);
+        if let Some(expires_at) = identity.expires_at() {
+            session = session.with_expires_at(expires_at);
+        }
+        self.registering.push(
+            async move {
+                let result =
+                    register(registry, policy, session)
+                        .await
+                        .map(|displaced| Authorized {
+                            identity,
+                            displaced,
+                        });
+                (key, result)
+            }
+            .boxed(),
+        );
+    }
+
+    fn on_displaced(
+        &mut self,
+        session: Session,
+        replaced_by: &SessionId,
+        current: Option<ConnectionId>,
+    ) {
+        // 同一会话重新认证，旧连接在绑定时关闭
+        if session.id() == replaced_by {
+            return;
+        }
+        // 同一连接重复认证
+        if self
+            .bindings
+            .by_session(session.id())
+            .is_some_and(|binding| Some(binding.connection_id) == current)
+        {
+            return;
+        }
+        self.forget_resumable(session.id());
+        let connection = self
+            .bindings
+            .remove_session(session.id())
+            .map(|binding| (binding.peer_id, binding.connection_id));
+        if let Some((peer_id, connection_id)) = connection {
+            self.pending_close.push_back((peer_id, connection_id));
+        }
+        self.pending_event.push_back(Event::SessionReplaced {
+            player_id: session.player_id().clone(),
+            session_id: session.id().clone(),
+            replaced_by: replaced_by.clone(),
+            connection,
+            reason: Code::Aborted,
+        });
     }
 
     fn on_authenticating(
@@ -61,35 +1012,34 @@ where
         connection_id: ConnectionId,
         request_id: RequestId,
         request: Request<Info>,
+        capabilities: Vec<String>,
         responder: Responder<Info>,
     ) {
-        let token = request.get_metadata("x-token").cloned();
+        let token = request.get_metadata(TOKEN_METADATA).cloned();
         if let Some(token) = token {
-            let authenticator = self.authenticator.clone();
-            let check_token = token.clone();
-            let fut = async move { authenticator.authenticate(check_token).await };
-            if self
-                .authenticating
-                .try_push(request_id, fut.boxed())
-                .is_err()
+            let fut = self.authenticate(token.clone());
+            if self.registering.len() >= self.limits.max_concurrent
+                || self
+                    .authenticating
+                    .try_push((connection_id, request_id), fut)
+                    .is_err()
             {
-                let _ = responder.err_response(Code::Internal.into());
-                self.pending_event.push_back(Event::Unauthenticated {
+                self.reject(
                     peer_id,
                     connection_id,
-                    cause: AuthError::Io(io::Error::new(
-                        io::ErrorKind::Other,
-                        "Authentication failed, task limit reached",
-                    )),
-                });
+                    request_id,
+                    responder,
+                    RejectReason::Overloaded,
+                );
             } else {
                 let info = request.into_payload();
                 self.pending_authentication.insert(
-                    request_id,
+                    (connection_id, request_id),
                     Authentication {
                         peer_id,
                         connection_id,
                         info: info.clone(),
+                        capabilities,
                         responder,
                     },
                 );
@@ -102,6 +1052,7 @@ where
             }
         } else {
             let _ = responder.err_response(Code::Unauthenticated.into());
+            self.close_after((connection_id, request_id));
             self.pending_event.push_back(Event::Unauthenticated {
                 peer_id,
                 connection_id,
@@ -112,36 +1063,62 @@ where
 
     fn on_authenticated(
         &mut self,
-        request_id: RequestId,
-        result: Result<(SessionId, PlayerId), AuthError>,
+        key: RequestKey,
+        result: Result<Authorized<TAuthenticator::Claims>, AuthError>,
     ) {
         if let Some(Authentication {
             peer_id,
             connection_id,
             info,
-            responder,
-        }) = self.pending_authentication.remove(&request_id)
+            capabilities,
+            mut responder,
+        }) = self
+            .pending_authentication
+            .remove(&key)
+            .filter(|authentication| {
+                self.connection_addrs
+                    .contains_key(&authentication.connection_id)
+            })
         {
             match result {
-                Ok((session_id, player_id)) => {
-                    let _ = responder.ok_response(self.info.clone());
+                Ok(Authorized {
+                    identity,
+                    displaced,
+                }) => {
+                    let session_id = identity.session_id().clone();
+                    for session in displaced {
+                        self.on_displaced(session, &session_id, Some(connection_id));
+                    }
+                    self.bind(Binding {
+                        peer_id,
+                        connection_id,
+                        session_id: session_id.clone(),
+                        player_id: identity.player_id().clone(),
+                    });
+                    if self.resume_window.is_some() {
+                        let token = self.issue_resume_token(
+                            &session_id,
+                            identity.player_id(),
+                            identity.expires_at(),
+                        );
+                        responder.add_metadata(Metadata {
+                            key: RESUME_TOKEN_METADATA.to_string(),
+                            value: token,
+                        });
+                    }
+                    let _ = responder.ok_response(self.info_with(capabilities.clone()));
                     self.pending_event.push_back(Event::Authenticated {
                         peer_id,
                         connection_id,
-                        player_id,
-                        session_id,
+                        identity,
                         info,
+                        capabilities,
                     });
                 }
                 Err(cause) => {
                     tracing::warn!("Authentication failed for {}: {:?}", peer_id, cause);
-                    let code = match &cause {
-                        AuthError::InvalidToken(_)
-                        | AuthError::ExpiredToken
-                        | AuthError::Unauthorized => Code::Unauthenticated,
-                        _ => Code::Internal,
-                    };
-                    let _ = responder.err_response(code.into());
+                    let _ = responder.err_response(cause.status());
+                    self.close_after(key);
                     self.pending_event.push_back(Event::Unauthenticated {
                         peer_id,
                         connection_id,
@@ -149,11 +1126,23 @@ where
                     });
                 }
             }
-        } else {
-            tracing::warn!(
-                "Authentication request {} not found in pending authentication",
-                request_id
+        } else if let Ok(Authorized {
+            identity,
+            displaced,
+        }) = result
+        {
+            // 连接在验证期间关闭，已登记的会话无人持有
+            tracing::debug!(
+                "Connection {} closed before authentication completed",
+                key.0
             );
+            let session_id = identity.session_id().clone();
+            for session in displaced {
+                self.on_displaced(session, &session_id, None);
+            }
+            if self.bindings.by_session(&session_id).is_none() {
+                self.remove_session(session_id);
+            }
         }
     }
 
@@ -166,7 +1155,46 @@ where
                 request,
                 responder,
             } => {
-                self.on_authenticating(peer_id, connection_id, request_id, request, responder);
+                let service = request.service();
+                if let Some(reason) = self.check_attempt(peer_id, connection_id) {
+                    self.reject(peer_id, connection_id, request_id, responder, reason);
+                    return;
+                }
+                let capabilities = match self.compatibility.negotiate(request.payload()) {
+                    Ok(capabilities) => capabilities,
+                    Err(reason) => {
+                        self.reject(peer_id, connection_id, request_id, responder, reason);
+                        return;
+                    }
+                };
+                if service == REFRESH_SERVICE {
+                    self.on_refresh(
+                        peer_id,
+                        connection_id,
+                        request_id,
+                        request,
+                        capabilities,
+                        responder,
+                    );
+                } else if service == RESUME_SERVICE {
+                    self.on_resume(
+                        peer_id,
+                        connection_id,
+                        request_id,
+                        request,
+                        capabilities,
+                        responder,
+                    );
+                } else {
+                    self.on_authenticating(
+                        peer_id,
+                        connection_id,
+                        request_id,
+                        request,
+                        capabilities,
+                        responder,
+                    );
+                }
             }
             server::Event::Failure {
                 peer_id,
@@ -174,6 +1202,7 @@ where
                 request_id,
                 cause,
             } => {
+                self.on_response_finished(peer_id, connection_id, request_id);
                 self.pending_event.push_back(Event::AuthenticateFailure {
                     peer_id,
                     connection_id,
@@ -192,16 +1221,24 @@ where
                     peer_id,
                     connection_id
                 );
+                self.on_response_finished(peer_id, connection_id, request_id);
             }
         }
     }
 }
 
-impl<TAuthenticator> NetworkBehavior for Behavior<TAuthenticator>
+fn authenticating<T>(limits: &Limits) -> FuturesMap<RequestKey, T> {
+    let timeout = limits.verify_timeout;
+    FuturesMap::new(move || Delay::futures_timer(timeout), limits.max_concurrent)
+}
+
+impl<TAuthenticator, TRegistry> NetworkBehavior for Behavior<TAuthenticator, TRegistry>
 where
-    TAuthenticator: Authenticator<String> + Clone + Send + 'static,
+    TAuthenticator:
+        Authenticator<String, Claims: fmt::Debug + Send + 'static> + Clone + Send + 'static,
+    TRegistry: SessionRegistry + Clone + Send + 'static,
 {
-    type Event = Event;
+    type Event = Event<TAuthenticator::Claims>;
     type ConnectionHandler = server::Handler<Info, Info>;
 
     fn on_connection_handler_event(
@@ -218,14 +1255,37 @@ where
         cx: &mut Context<'_>,
     ) -> Poll<BehaviorEvent<Self::Event, THandlerAction<Self>>> {
         loop {
-            match self.authenticating.poll_unpin(cx) {
-                Poll::Ready((request_id, Ok(result))) => {
-                    self.on_authenticated(request_id, result);
-                }
-                Poll::Ready((request_id, Err(_))) => {
-                    self.on_authenticated(request_id, Err(AuthError::Timeout));
-                }
-                Poll::Pending => {}
+            while let Poll::Ready((key, result)) = self.authenticating.poll_unpin(cx) {
+                self.on_verified(key, result.unwrap_or(Err(AuthError::Timeout)));
+            }
+            while let Poll::Ready(Some((key, result))) = self.registering.poll_next_unpin(cx) {
+                self.on_authenticated(key, result);
+            }
+            while let Poll::Ready((key, result)) = self.resuming.poll_unpin(cx) {
+                self.on_resume_checked(key, result.map_err(|_| AuthError::Timeout));
+            }
+            while let Poll::Ready((key, result)) = self.refreshing.poll_unpin(cx) {
+                self.on_refresh_checked(key, result.map_err(|_| AuthError::Timeout));
+            }
+            while let Poll::Ready(Some(())) = self.removing.poll_next_unpin(cx) {}
+            while let Poll::Ready(Some(session_id)) = self.detaching.poll_next_unpin(cx) {
+                self.on_detach_elapsed(session_id);
+            }
+            while let Poll::Ready(Some((peer_id, connection_id))) =
+                self.handshake_timeouts.poll_next_unpin(cx)
+            {
+                self.on_handshake_timeout(peer_id, connection_id);
+            }
+            if self.prune_attempts.poll_unpin(cx).is_ready() {
+                self.peer_attempts.prune(self.limits.window);
+                self.ip_attempts.prune(self.limits.window);
+                self.prune_attempts.reset(self.limits.window);
+            }
+            if let Some((peer_id, connection_id)) = self.pending_close.pop_front() {
+                return Poll::Ready(BehaviorEvent::CloseConnection {
+                    peer_id,
+                    connection: CloseConnection::One(connection_id),
+                });
             }
             if let Some(event) = self.pending_event.pop_front() {
                 return Poll::Ready(BehaviorEvent::Behavior(event));
@@ -264,9 +1324,11 @@ where
     }
 }
 
-impl<TAuthenticator> NetworkIncomingBehavior for Behavior<TAuthenticator>
+impl<TAuthenticator, TRegistry> NetworkIncomingBehavior for Behavior<TAuthenticator, TRegistry>
 where
-    TAuthenticator: Authenticator<String> + Clone + Send + 'static,
+    TAuthenticator:
+        Authenticator<String, Claims: fmt::Debug + Send + 'static> + Clone + Send + 'static,
+    TRegistry: SessionRegistry + Clone + Send + 'static,
 {
     /// 处理已建立的连接
     fn handle_established_connection(
@@ -288,6 +1350,16 @@ where
         local_addr: &Url,
         remote_addr: &Url,
     ) {
+        self.connection_addrs.insert(id, ip_of(remote_addr));
+        if let Some(timeout) = self.limits.handshake_timeout {
+            self.handshake_timeouts.push(
+                async move {
+                    futures_timer::Delay::new(timeout).await;
+                    (peer_id, id)
+                }
+                .boxed(),
+            );
+        }
         self.inner
             .on_connection_established(id, peer_id, local_addr, remote_addr);
     }
@@ -300,6 +1372,41 @@ where
         remote_addr: &Url,
         reason: Option<&ConnectionError>,
     ) {
+        self.connection_addrs.remove(&id);
+        self.pending_authentication
+            .retain(|_, authentication| authentication.connection_id != id);
+        let closed = self
+            .pending_resume
+            .extract_if(|_, resume| resume.connection_id == id)
+            .collect::<Vec<_>>();
+        for (_, resume) in closed {
+            self.keep_resume_token(&resume);
+        }
+        self.pending_refresh
+            .retain(|_, refresh| refresh.connection_id != id);
+        if let Some(binding) = self.bindings.remove_connection(id) {
+            let session_id = binding.session_id.clone();
+            let resumable = match (self.resume_window, self.resumable.get_mut(&session_id)) {
+                // 在恢复窗口内保留会话
+                (Some(window), Some(resumable)) => {
+                    resumable.detached_at = Some(Instant::now());
+                    self.detaching.push(
+                        async move {
+                            futures_timer::Delay::new(window).await;
+                            session_id
+                        }
+                        .boxed(),
+                    );
+                    true
+                }
+                _ => {
+                    self.remove_session(session_id);
+                    false
+                }
+            };
+            self.pending_event
+                .push_back(Event::SessionDisconnected { binding, resumable });
+        }
         self.inner
             .on_connection_closed(id, peer_id, local_addr, remote_addr, reason);
     }
@@ -324,7 +1431,7 @@ where
 }
 
 #[derive(Debug)]
-pub enum Event {
+pub enum Event<TClaims = ()> {
     Authenticating {
         peer_id: PeerId,
         connection_id: ConnectionId,
@@ -334,15 +1441,62 @@ pub enum Event {
     Authenticated {
         peer_id: PeerId,
         connection_id: ConnectionId,
-        player_id: PlayerId,
-        session_id: SessionId,
+        identity: Identity<TClaims>,
         info: Info,
+        /// 协商后双方都支持的功能
+        capabilities: Vec<String>,
     },
     Unauthenticated {
         peer_id: PeerId,
         connection_id: ConnectionId,
         cause: AuthError,
     },
+    /// 访问令牌已刷新
+    Refreshed {
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        session_id: SessionId,
+    },
+    /// 会话在新连接上恢复
+    ///
+    /// `last_seq` 为客户端最后收到的推送消息序号。
+    Resumed {
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        session_id: SessionId,
+        player_id: PlayerId,
+        last_seq: u64,
+        capabilities: Vec<String>,
+    },
+    /// 断开的会话未在恢复窗口内恢复，已从注册表中移除
+    ResumeExpired {
+        player_id: PlayerId,
+        session_id: SessionId,
+    },
+    /// 会话被同一玩家的新会话取代
+    ///
+    /// 旧会话位于本实例时 `connection` 为其所在连接，该连接会被关闭。
+    SessionReplaced {
+        player_id: PlayerId,
+        session_id: SessionId,
+        replaced_by: SessionId,
+        connection: Option<(PeerId, ConnectionId)>,
+        /// 通知旧会话客户端时使用的结果码
+        ///
+        /// 只有 [`SessionPolicy::KickOld`] 会取代其他会话，同一会话在新连接上重新认证不产生该事件，
+        /// 因此固定为 [`Code::Aborted`]，表示会话因同一玩家的并发登录而中止。
+        reason: Code,
+    },
+    /// 已认证的连接关闭
+    ///
+    /// 启用会话恢复时 `resumable` 表示会话仍可在恢复窗口内恢复。
+    SessionDisconnected { binding: Binding, resumable: bool },
+    /// 认证请求或连接被限制拒绝
+    Rejected {
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        reason: RejectReason,
+    },
     AuthenticateFailure {
         peer_id: PeerId,
         connection_id: ConnectionId,
@@ -350,3 +1504,113 @@ pub enum Event {
         error: io::Error,
     },
 }
+
+/// `RejectNew` 时检查玩家的其他会话与插入原子执行
+async fn insert<R>(registry: &mut R, policy: SessionPolicy, session: Session) -> Result<(), Session>
+where
+    R: SessionRegistry + Send,
+{
+    match policy {
+        SessionPolicy::RejectNew => registry.insert_if_absent(session).await,
+        _ => registry.insert(session).await,
+    }
+}
+
+async fn register<R>(
+    mut registry: R,
+    policy: SessionPolicy,
+    session: Session,
+) -> Result<Vec<Session>, AuthError>
+where
+    R: SessionRegistry + Send,
+{
+    let session_id = session.id().clone();
+    let player_id = session.player_id().clone();
+    let mut displaced = Vec::new();
+    if let Err(session) = insert(&mut registry, policy, session).await {
+        // 同一会话在新连接上重新认证，旧连接被取代
+        match registry.lookup(session_id.clone()).await {
+            Some(old) => {
+                registry.remove(session_id.clone()).await;
+                displaced.push(old);
+            }
+            None if policy == SessionPolicy::RejectNew => return Err(AuthError::SessionExists),
+            None => {}
+        }
+        if insert(&mut registry, policy, session).await.is_err() {
+            return Err(match policy {
+                SessionPolicy::RejectNew => AuthError::SessionExists,
+                _ => AuthError::Io(io::Error::other("Failed to register session")),
+            });
+        }
+    }
+    if policy == SessionPolicy::KickOld {
+        displaced.extend(registry.single_session(player_id, session_id).await);
+    }
+    Ok(displaced)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+
+    use super::*;
+
+    fn session(player_id: &PlayerId) -> Session {
+        Session::new(SessionId::generate(), player_id.clone())
+    }
+
+    #[test]
+    fn reject_new_rejects_other_sessions() {
+        let mut registry = LocalSessionRegistry::new();
+        let player_id = PlayerId::generate();
+        block_on(registry.insert(session(&player_id))).unwrap();
+
+        let result = block_on(register(
+            registry.clone(),
+            SessionPolicy::RejectNew,
+            session(&player_id),
+        ));
+        assert!(matches!(result, Err(AuthError::SessionExists)));
+        assert_eq!(registry.count(), 1);
+    }
+
+    #[test]
+    fn reject_new_allows_reauthenticating_the_same_session() {
+        let mut registry = LocalSessionRegistry::new();
+        let player_id = PlayerId::generate();
+        let existing = session(&player_id);
+        block_on(registry.insert(existing.clone())).unwrap();
+
+        let displaced = block_on(register(
+            registry.clone(),
+            SessionPolicy::RejectNew,
+            existing.clone(),
+        ))
+        .unwrap();
+        assert_eq!(displaced.len(), 1);
+        assert_eq!(displaced[0].id(), existing.id());
+        assert!(block_on(registry.lookup(existing.id().clone())).is_some());
+    }
+
+    #[test]
+    fn kick_old_displaces_other_sessions() {
+        let mut registry = LocalSessionRegistry::new();
+        let player_id = PlayerId::generate();
+        let old = session(&player_id);
+        block_on(registry.insert(old.clone())).unwrap();
+
+        let new = session(&player_id);
+        let displaced = block_on(register(
+            registry.clone(),
+            SessionPolicy::KickOld,
+            new.clone(),
+        ))
+        .unwrap();
+        assert_eq!(displaced.len(), 1);
+        assert_eq!(displaced[0].id(), old.id());
+        let sessions = block_on(registry.sessions_of(player_id));
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].id(), new.id());
+    }
+}
@@ -0,0 +1,133 @@
+use std::{collections::HashMap, sync::Arc};
+
+use parking_lot::RwLock;
+use smallvec::SmallVec;
+use vela_core::ids::{PlayerId, SessionId};
+use vela_protobuf::common::{Code, Status};
+use vela_request::{Guard, router::RequestContext};
+use volans::{core::PeerId, swarm::ConnectionId};
+
+/// 已认证连接与会话的绑定
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Binding {
+    pub peer_id: PeerId,
+    pub connection_id: ConnectionId,
+    pub session_id: SessionId,
+    pub player_id: PlayerId,
+}
+
+/// 已认证连接的共享视图
+///
+/// 由连接服务端维护，克隆后共享。作为 [`Guard`] 时拒绝尚未认证的连接上的请求。
+#[derive(Debug, Clone, Default)]
+pub struct AuthenticatedConnections {
+    connections: Arc<RwLock<HashMap<ConnectionId, Binding>>>,
+}
+
+impl AuthenticatedConnections {
+    pub fn contains(&self, connection_id: ConnectionId) -> bool {
+        self.connections.read().contains_key(&connection_id)
+    }
+
+    pub fn get(&self, connection_id: ConnectionId) -> Option<Binding> {
+        self.connections.read().get(&connection_id).cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.connections.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.connections.read().is_empty()
+    }
+}
+
+impl Guard for AuthenticatedConnections {
+    fn check(&self, context: &RequestContext) -> Result<(), Status> {
+        if self.contains(context.connection_id) {
+            Ok(())
+        } else {
+            Err(Status::new(
+                Code::Unauthenticated,
+                "Connection not authenticated",
+            ))
+        }
+    }
+}
+
+/// 连接与会话的绑定表
+///
+/// 每个连接最多绑定一个会话，每个会话最多位于一个连接上。
+#[derive(Default)]
+pub(crate) struct Bindings {
+    connections: HashMap<ConnectionId, Binding>,
+    sessions: HashMap<SessionId, ConnectionId>,
+    players: HashMap<PlayerId, SmallVec<[ConnectionId; 1]>>,
+    shared: AuthenticatedConnections,
+}
+
+impl Bindings {
+    /// 绑定会话，返回被取代的同一会话的旧绑定
+    pub(crate) fn insert(&mut self, binding: Binding) -> Option<Binding> {
+        let old = self.remove_session(&binding.session_id);
+        self.remove_connection(binding.connection_id);
+        self.sessions
+            .insert(binding.session_id.clone(), binding.connection_id);
+        self.players
+            .entry(binding.player_id.clone())
+            .or_default()
+            .push(binding.connection_id);
+        self.shared
+            .connections
+            .write()
+            .insert(binding.connection_id, binding.clone());
+        self.connections.insert(binding.connection_id, binding);
+        old
+    }
+
+    pub(crate) fn remove_connection(&mut self, connection_id: ConnectionId) -> Option<Binding> {
+        let binding = self.connections.remove(&connection_id)?;
+        self.shared.connections.write().remove(&connection_id);
+        self.sessions.remove(&binding.session_id);
+        if let Some(connections) = self.players.get_mut(&binding.player_id) {
+            connections.retain(|id| *id != connection_id);
+            if connections.is_empty() {
+                self.players.remove(&binding.player_id);
+            }
+        }
+        Some(binding)
+    }
+
+    pub(crate) fn remove_session(&mut self, session_id: &SessionId) -> Option<Binding> {
+        let connection_id = *self.sessions.get(session_id)?;
+        self.remove_connection(connection_id)
+    }
+
+    pub(crate) fn by_connection(&self, connection_id: ConnectionId) -> Option<&Binding> {
+        self.connections.get(&connection_id)
+    }
+
+    pub(crate) fn by_session(&self, session_id: &SessionId) -> Option<&Binding> {
+        self.connections.get(self.sessions.get(session_id)?)
+    }
+
+    pub(crate) fn by_player(&self, player_id: &PlayerId) -> impl Iterator<Item = &Binding> {
+        self.players
+            .get(player_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.connections.get(id))
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Binding> {
+        self.connections.values()
+    }
+
+    pub(crate) fn shared(&self) -> AuthenticatedConnections {
+        self.shared.clone()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.connections.len()
+    }
+}
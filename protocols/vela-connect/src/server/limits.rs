@@ -0,0 +1,153 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    net::IpAddr,
+    time::{Duration, Instant},
+};
+
+use vela_protobuf::common::Code;
+use volans::core::Url;
+
+/// 认证握手的限制
+#[derive(Debug, Clone)]
+pub struct Limits {
+    pub(crate) max_attempts_per_peer: u32,
+    pub(crate) max_attempts_per_ip: u32,
+    pub(crate) window: Duration,
+    pub(crate) max_concurrent: usize,
+    pub(crate) verify_timeout: Duration,
+    pub(crate) handshake_timeout: Option<Duration>,
+    pub(crate) close_on_failure: bool,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_attempts_per_peer: 10,
+            max_attempts_per_ip: 100,
+            window: Duration::from_secs(60),
+            max_concurrent: 1000,
+            verify_timeout: Duration::from_secs(10),
+            handshake_timeout: Some(Duration::from_secs(30)),
+            close_on_failure: false,
+        }
+    }
+}
+
+impl Limits {
+    /// 每个节点在 `window` 内的认证次数，默认为 10
+    pub fn with_max_attempts_per_peer(mut self, max: u32) -> Self {
+        self.max_attempts_per_peer = max;
+        self
+    }
+
+    /// 每个 IP 在 `window` 内的认证次数，默认为 100
+    pub fn with_max_attempts_per_ip(mut self, max: u32) -> Self {
+        self.max_attempts_per_ip = max;
+        self
+    }
+
+    /// 统计认证次数的时间窗口，默认为 60 秒
+    pub fn with_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// 同时进行的认证数量，默认为 1000
+    pub fn with_max_concurrent(mut self, max: usize) -> Self {
+        self.max_concurrent = max;
+        self
+    }
+
+    /// 单次验证令牌的超时时间，不包括登记会话，默认为 10 秒
+    pub fn with_verify_timeout(mut self, timeout: Duration) -> Self {
+        self.verify_timeout = timeout;
+        self
+    }
+
+    /// 连接建立后未完成认证时关闭连接，默认为 30 秒，`None` 表示不限制
+    pub fn with_handshake_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.handshake_timeout = timeout;
+        self
+    }
+
+    /// 认证失败后关闭连接，默认关闭
+    ///
+    /// 开启后客户端无法在同一连接上重试，未认证的连接仍由 `handshake_timeout` 关闭。
+    pub fn with_close_on_failure(mut self, close: bool) -> Self {
+        self.close_on_failure = close;
+        self
+    }
+}
+
+/// 认证被拒绝的原因
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum RejectReason {
+    #[error("Too many authentication attempts from peer")]
+    PeerRateLimited,
+    #[error("Too many authentication attempts from {0}")]
+    IpRateLimited(IpAddr),
+    #[error("Too many concurrent authentications")]
+    Overloaded,
+    #[error("Connection did not authenticate in time")]
+    HandshakeTimeout,
+    #[error("Unsupported client version {version:?}, requires {required}")]
+    UnsupportedVersion { version: String, required: String },
+    #[error("Missing required capabilities: {}", .0.join(", "))]
+    MissingCapabilities(Vec<String>),
+}
+
+impl RejectReason {
+    pub fn code(&self) -> Code {
+        match self {
+            RejectReason::PeerRateLimited | RejectReason::IpRateLimited(_) => {
+                Code::ResourceExhausted
+            }
+            RejectReason::Overloaded => Code::Unavailable,
+            RejectReason::HandshakeTimeout => Code::DeadlineExceeded,
+            RejectReason::UnsupportedVersion { .. } | RejectReason::MissingCapabilities(_) => {
+                Code::FailedPrecondition
+            }
+        }
+    }
+}
+
+/// 固定窗口的次数统计
+pub(crate) struct Attempts<K> {
+    counts: HashMap<K, (Instant, u32)>,
+}
+
+impl<K: Eq + Hash> Attempts<K> {
+    pub(crate) fn new() -> Self {
+        Self {
+            counts: HashMap::new(),
+        }
+    }
+
+    /// 记录一次尝试，超过 `max` 时返回 `false`
+    pub(crate) fn hit(&mut self, key: K, max: u32, window: Duration) -> bool {
+        let now = Instant::now();
+        let (start, count) = self.counts.entry(key).or_insert((now, 0));
+        if now.duration_since(*start) >= window {
+            *start = now;
+            *count = 0;
+        }
+        *count = count.saturating_add(1);
+        *count <= max
+    }
+
+    /// 移除已结束的窗口
+    pub(crate) fn prune(&mut self, window: Duration) {
+        let now = Instant::now();
+        self.counts
+            .retain(|_, (start, _)| now.duration_since(*start) < window);
+    }
+}
+
+pub(crate) fn ip_of(addr: &Url) -> Option<IpAddr> {
+    let host = addr.host_str()?;
+    host.trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse()
+        .ok()
+}
@@ -0,0 +1,127 @@
+use semver::{Version, VersionReq};
+use vela_protobuf::connect::Info;
+
+use super::RejectReason;
+
+/// 客户端版本与功能的兼容性要求
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Compatibility {
+    pub(crate) versions: Option<VersionReq>,
+    /// 服务端提供的功能
+    pub(crate) capabilities: Vec<String>,
+    /// 客户端必须支持的功能
+    pub(crate) required: Vec<String>,
+}
+
+impl Compatibility {
+    pub(crate) fn offer(&mut self, capability: &str) {
+        if !self.capabilities.iter().any(|c| c == capability) {
+            self.capabilities.push(capability.to_string());
+        }
+    }
+
+    /// 检查客户端信息，返回双方都支持的功能
+    pub(crate) fn negotiate(&self, client: &Info) -> Result<Vec<String>, RejectReason> {
+        if let Some(versions) = &self.versions {
+            let compatible =
+                Version::parse(&client.version).is_ok_and(|version| versions.matches(&version));
+            if !compatible {
+                return Err(RejectReason::UnsupportedVersion {
+                    version: client.version.clone(),
+                    required: versions.to_string(),
+                });
+            }
+        }
+        let missing: Vec<String> = self
+            .required
+            .iter()
+            .filter(|capability| !client.capabilities.contains(capability))
+            .cloned()
+            .collect();
+        if !missing.is_empty() {
+            return Err(RejectReason::MissingCapabilities(missing));
+        }
+        Ok(self
+            .capabilities
+            .iter()
+            .filter(|capability| client.capabilities.contains(capability))
+            .cloned()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vela_protobuf::common::Code;
+
+    use super::*;
+
+    fn client(version: &str, capabilities: &[&str]) -> Info {
+        Info {
+            name: "client".to_string(),
+            version: version.to_string(),
+            capabilities: capabilities.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+
+    fn compatibility() -> Compatibility {
+        let mut compatibility = Compatibility {
+            versions: Some(VersionReq::parse(">=1.2.0, <2.0.0").unwrap()),
+            required: vec!["resume".to_string()],
+            ..Default::default()
+        };
+        compatibility.offer("resume");
+        compatibility.offer("push");
+        compatibility.offer("push");
+        compatibility
+    }
+
+    #[test]
+    fn rejects_versions_out_of_range() {
+        for version in ["1.1.9", "2.0.0"] {
+            let reason = compatibility()
+                .negotiate(&client(version, &["resume"]))
+                .unwrap_err();
+            assert!(matches!(reason, RejectReason::UnsupportedVersion { .. }));
+            assert_eq!(reason.code(), Code::FailedPrecondition);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_versions() {
+        let reason = compatibility()
+            .negotiate(&client("latest", &["resume"]))
+            .unwrap_err();
+        assert!(matches!(
+            reason,
+            RejectReason::UnsupportedVersion { ref version, .. } if version == "latest"
+        ));
+        assert_eq!(reason.code(), Code::FailedPrecondition);
+    }
+
+    #[test]
+    fn rejects_missing_required_capabilities() {
+        let reason = compatibility()
+            .negotiate(&client("1.5.0", &["push"]))
+            .unwrap_err();
+        assert!(matches!(
+            reason,
+            RejectReason::MissingCapabilities(ref missing) if missing == &["resume"]
+        ));
+        assert_eq!(reason.code(), Code::FailedPrecondition);
+    }
+
+    #[test]
+    fn negotiates_common_capabilities() {
+        let capabilities = compatibility()
+            .negotiate(&client("1.5.0", &["resume", "compression"]))
+            .unwrap();
+        assert_eq!(capabilities, ["resume"]);
+
+        // 未设置要求时接受任意版本
+        let capabilities = Compatibility::default()
+            .negotiate(&client("latest", &["resume"]))
+            .unwrap();
+        assert!(capabilities.is_empty());
+    }
+}
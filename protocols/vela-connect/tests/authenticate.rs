@@ -0,0 +1,146 @@
+mod common;
+
+use std::{collections::VecDeque, io, sync::Mutex, time::Duration};
+
+use common::{Client, SlowAuthenticator, info, server_event, spawn_server};
+use vela_connect::{
+    client::{self, RetryPolicy, Token, TokenProvider},
+    server,
+};
+use vela_core::{authenticate::DevAuthenticator, session::LocalSessionRegistry};
+use vela_protobuf::common::Code;
+use volans::request;
+
+fn authenticator() -> DevAuthenticator {
+    DevAuthenticator::new().with_player("token", "py_1".parse().unwrap())
+}
+
+/// 依次返回给定的令牌
+struct Tokens(Mutex<VecDeque<&'static str>>);
+
+#[async_trait::async_trait]
+impl TokenProvider for Tokens {
+    async fn token(&self) -> io::Result<Token> {
+        let token = self.0.lock().unwrap().pop_front();
+        token
+            .map(Token::new)
+            .ok_or_else(|| io::Error::other("No more tokens"))
+    }
+}
+
+#[tokio::test]
+async fn failed_authentication_can_be_retried_on_the_same_connection() {
+    let (addr, _events) = spawn_server(server::Behavior::new(
+        info(),
+        authenticator(),
+        request::Config::default(),
+    ));
+    let behavior = client::Behavior::new(info(), request::Config::default())
+        .with_token_provider(Tokens(Mutex::new(["wrong", "token"].into())))
+        .with_retry_policy(RetryPolicy::default().with_initial_backoff(Duration::from_millis(10)));
+    let mut client = Client::with_behavior(behavior);
+    let (_, connection_id) = client.connect(&addr).await;
+
+    assert!(matches!(
+        client.next_event().await,
+        client::Event::Unauthenticated { .. }
+    ));
+    match client.next_event().await {
+        client::Event::Authenticated {
+            connection_id: authenticated,
+            ..
+        } => assert_eq!(authenticated, connection_id),
+        event => panic!("unexpected event: {event:?}"),
+    }
+}
+
+#[tokio::test]
+async fn verify_timeout_leaves_no_session() {
+    let registry = LocalSessionRegistry::new();
+    let authenticator = SlowAuthenticator {
+        inner: authenticator(),
+        delay: Duration::from_millis(500),
+    };
+    let behavior = server::Behavior::new(info(), authenticator, request::Config::default())
+        .with_session_registry(registry.clone(), server::SessionPolicy::KickOld)
+        .with_limits(server::Limits::default().with_verify_timeout(Duration::from_millis(100)));
+    let (addr, _events) = spawn_server(behavior);
+    let mut client = Client::new();
+    let (peer_id, _) = client.connect(&addr).await;
+
+    client
+        .behavior_mut()
+        .send_authentication(peer_id, "token".to_string());
+    match client.next_event().await {
+        client::Event::Unauthenticated { status, .. } => {
+            assert_eq!(status.code, Code::Unavailable as i32)
+        }
+        event => panic!("unexpected event: {event:?}"),
+    }
+    client.drive_for(Duration::from_millis(600)).await;
+    assert_eq!(registry.count(), 0);
+}
+
+#[tokio::test]
+async fn closing_during_verification_leaves_no_session() {
+    let registry = LocalSessionRegistry::new();
+    let authenticator = SlowAuthenticator {
+        inner: authenticator(),
+        delay: Duration::from_millis(300),
+    };
+    let behavior = server::Behavior::new(info(), authenticator, request::Config::default())
+        .with_session_registry(registry.clone(), server::SessionPolicy::KickOld);
+    let (addr, mut events) = spawn_server(behavior);
+    let mut client = Client::new();
+    let (peer_id, connection_id) = client.connect(&addr).await;
+
+    client
+        .behavior_mut()
+        .send_authentication(peer_id, "token".to_string());
+    client.drive_for(Duration::from_millis(100)).await;
+    server_event(&mut events, |event| {
+        matches!(event, server::Event::Authenticating { .. }).then_some(())
+    })
+    .await;
+    client.close(connection_id);
+    client.drive_for(Duration::from_millis(500)).await;
+
+    assert_eq!(registry.count(), 0);
+    while let Ok(Some(event)) = events.try_next() {
+        assert!(
+            !matches!(event, server::Event::Authenticated { .. }),
+            "unexpected event: {event:?}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn concurrent_authentications_all_complete() {
+    let registry = LocalSessionRegistry::new();
+    let authenticator = SlowAuthenticator {
+        inner: authenticator().with_player("other", "py_2".parse().unwrap()),
+        delay: Duration::from_millis(100),
+    };
+    let behavior = server::Behavior::new(info(), authenticator, request::Config::default())
+        .with_session_registry(registry.clone(), server::SessionPolicy::KickOld);
+    let (addr, _events) = spawn_server(behavior);
+    let mut first = Client::new();
+    let mut second = Client::new();
+    let (first_peer, _) = first.connect(&addr).await;
+    let (second_peer, _) = second.connect(&addr).await;
+
+    first
+        .behavior_mut()
+        .send_authentication(first_peer, "token".to_string());
+    second
+        .behavior_mut()
+        .send_authentication(second_peer, "other".to_string());
+    let (first_event, second_event) = tokio::join!(first.next_event(), second.next_event());
+    for event in [first_event, second_event] {
+        assert!(
+            matches!(event, client::Event::Authenticated { .. }),
+            "unexpected event: {event:?}"
+        );
+    }
+    assert_eq!(registry.count(), 2);
+}
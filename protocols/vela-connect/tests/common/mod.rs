@@ -0,0 +1,210 @@
+//! 基于本地 TCP 连接的服务端与客户端
+
+#![allow(dead_code)]
+
+use std::{fmt, pin::Pin, time::Duration};
+
+use futures::{StreamExt, channel::mpsc};
+use vela_connect::{client, server};
+use vela_core::{
+    authenticate::{AuthError, Authenticator, Identity},
+    session::SessionRegistry,
+};
+use vela_protobuf::connect::Info;
+use volans::{
+    Transport,
+    core::{PeerId, Url, muxing::StreamMuxerBox, transport::Boxed},
+    muxing, ping, plaintext, request,
+    swarm::{self, ConnectionId, DialOpts, NetworkOutgoingBehavior},
+    tcp,
+};
+
+/// 等待事件的最长时间
+pub const EVENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Default, Debug, Clone, Copy)]
+struct TokioExecutor;
+
+impl swarm::Executor for TokioExecutor {
+    fn exec(&self, future: Pin<Box<dyn Future<Output = ()> + Send>>) {
+        tokio::spawn(future);
+    }
+}
+
+fn transport() -> (PeerId, Boxed<(PeerId, StreamMuxerBox)>) {
+    let key: [u8; 32] = rand::random();
+    let signing_key = plaintext::ed25519::SigningKey::from_bytes(&key);
+    let transport = tcp::Config::new()
+        .upgrade()
+        .authenticate(plaintext::Config::new(signing_key.verifying_key()))
+        .multiplex(muxing::Config::new())
+        .boxed();
+    (PeerId::from_bytes(key), transport)
+}
+
+pub fn info() -> Info {
+    Info {
+        name: "Vela Test".to_string(),
+        version: "0.1.0".to_string(),
+        capabilities: Vec::new(),
+    }
+}
+
+/// 在后台运行服务端，返回监听地址与服务端事件
+pub fn spawn_server<A, R>(
+    behavior: server::Behavior<A, R>,
+) -> (Url, mpsc::UnboundedReceiver<server::Event<A::Claims>>)
+where
+    A: Authenticator<String, Claims: fmt::Debug + Send + 'static> + Clone + Send + 'static,
+    R: SessionRegistry + Clone + Send + 'static,
+{
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .unwrap()
+        .port();
+    // TCP 传输只读取主机与端口，非特殊协议名的 URL 不会解析 IPv4 地址
+    let addr = Url::parse(&format!("http://127.0.0.1:{port}")).unwrap();
+    let (peer_id, transport) = transport();
+    let mut swarm = swarm::server::Swarm::new(
+        transport,
+        behavior,
+        peer_id,
+        swarm::connection::PoolConfig::new(Box::new(TokioExecutor)),
+    );
+    swarm.listen_on(addr.clone()).unwrap();
+    let (sender, receiver) = mpsc::unbounded();
+    tokio::spawn(async move {
+        while let Some(event) = swarm.next().await {
+            if let swarm::server::SwarmEvent::Behavior(event) = event {
+                let _ = sender.unbounded_send(event);
+            }
+        }
+    });
+    (addr, receiver)
+}
+
+/// 与网关示例一致，由组合行为提供拨号地址
+#[derive(NetworkOutgoingBehavior)]
+struct ClientBehavior {
+    ping: ping::outbound::Behavior,
+    connect: client::Behavior,
+}
+
+pub struct Client {
+    swarm: swarm::client::Swarm<ClientBehavior>,
+}
+
+impl Client {
+    pub fn new() -> Self {
+        Self::with_behavior(client::Behavior::new(info(), request::Config::default()))
+    }
+
+    pub fn with_behavior(connect: client::Behavior) -> Self {
+        let (peer_id, transport) = transport();
+        let behavior = ClientBehavior {
+            ping: ping::outbound::Behavior::default(),
+            connect,
+        };
+        Self {
+            swarm: swarm::client::Swarm::new(
+                transport,
+                behavior,
+                peer_id,
+                swarm::connection::PoolConfig::new(Box::new(TokioExecutor)),
+            ),
+        }
+    }
+
+    pub fn behavior_mut(&mut self) -> &mut client::Behavior {
+        &mut self.swarm.behavior_mut().connect
+    }
+
+    /// 建立连接并返回服务端的节点 ID
+    pub async fn connect(&mut self, addr: &Url) -> (PeerId, ConnectionId) {
+        self.swarm
+            .dial(DialOpts::new(Some(addr.clone()), None))
+            .unwrap();
+        within(async {
+            loop {
+                if let Some(swarm::client::SwarmEvent::ConnectionEstablished {
+                    peer_id,
+                    connection_id,
+                    ..
+                }) = self.swarm.next().await
+                {
+                    return (peer_id, connection_id);
+                }
+            }
+        })
+        .await
+    }
+
+    pub fn close(&mut self, connection_id: ConnectionId) {
+        self.swarm.close_connection(connection_id);
+    }
+
+    /// 下一个客户端事件，其他事件被忽略
+    pub async fn next_event(&mut self) -> client::Event {
+        within(async {
+            loop {
+                if let Some(swarm::client::SwarmEvent::Behavior(ClientBehaviorEvent::Connect(
+                    event,
+                ))) = self.swarm.next().await
+                {
+                    return event;
+                }
+            }
+        })
+        .await
+    }
+
+    /// 在后台驱动客户端，`duration` 后结束
+    pub async fn drive_for(&mut self, duration: Duration) {
+        let _ = tokio::time::timeout(duration, async {
+            while self.swarm.next().await.is_some() {}
+        })
+        .await;
+    }
+}
+
+pub async fn within<T>(future: impl Future<Output = T>) -> T {
+    tokio::time::timeout(EVENT_TIMEOUT, future)
+        .await
+        .expect("timed out waiting for event")
+}
+
+/// 下一个满足条件的服务端事件
+pub async fn server_event<T, E>(
+    events: &mut mpsc::UnboundedReceiver<E>,
+    mut filter: impl FnMut(E) -> Option<T>,
+) -> T {
+    within(async {
+        loop {
+            let event = events.next().await.expect("server stopped");
+            if let Some(value) = filter(event) {
+                return value;
+            }
+        }
+    })
+    .await
+}
+
+/// 延迟 `delay` 后再验证令牌
+#[derive(Clone)]
+pub struct SlowAuthenticator<A> {
+    pub inner: A,
+    pub delay: Duration,
+}
+
+#[async_trait::async_trait]
+impl<A> Authenticator<String> for SlowAuthenticator<A>
+where
+    A: Authenticator<String> + Send + Sync,
+{
+    type Claims = A::Claims;
+
+    async fn authenticate(&self, token: String) -> Result<Identity<Self::Claims>, AuthError> {
+        tokio::time::sleep(self.delay).await;
+        self.inner.authenticate(token).await
+    }
+}
@@ -0,0 +1,195 @@
+mod common;
+
+use std::pin::Pin;
+
+use common::{EVENT_TIMEOUT, info, within};
+use futures::StreamExt;
+use vela_connect::{client, server};
+use vela_core::authenticate::DevAuthenticator;
+use vela_protobuf::common::{Code, Status};
+use vela_request::{Response, caller, router};
+use volans::{
+    Transport,
+    core::{PeerId, Url, muxing::StreamMuxerBox, transport::Boxed},
+    muxing, ping, plaintext, request,
+    swarm::{self, DialOpts, NetworkIncomingBehavior, NetworkOutgoingBehavior, StreamProtocol},
+    tcp,
+};
+
+const PROTOCOL: StreamProtocol = StreamProtocol::new("/v1/request");
+const SERVICE: &str = "test.echo";
+const TOKEN: &str = "token";
+
+#[derive(Default, Debug, Clone, Copy)]
+struct TokioExecutor;
+
+impl swarm::Executor for TokioExecutor {
+    fn exec(&self, future: Pin<Box<dyn Future<Output = ()> + Send>>) {
+        tokio::spawn(future);
+    }
+}
+
+fn build_transport() -> (PeerId, Boxed<(PeerId, StreamMuxerBox)>) {
+    let key: [u8; 32] = rand::random();
+    let signing_key = plaintext::ed25519::SigningKey::from_bytes(&key);
+    let transport = tcp::Config::new()
+        .upgrade()
+        .authenticate(plaintext::Config::new(signing_key.verifying_key()))
+        .multiplex(muxing::Config::new())
+        .boxed();
+    (PeerId::from_bytes(key), transport)
+}
+
+/// 业务请求与认证共用连接，由认证行为守卫路由
+#[derive(NetworkIncomingBehavior)]
+struct ServerBehavior {
+    ping: ping::inbound::Behavior,
+    connect: server::Behavior<DevAuthenticator>,
+    router: router::Behavior,
+}
+
+#[derive(NetworkOutgoingBehavior)]
+struct ClientBehavior {
+    ping: ping::outbound::Behavior,
+    connect: client::Behavior,
+    caller: caller::Behavior,
+}
+
+fn spawn_server() -> Url {
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .unwrap()
+        .port();
+    let addr = Url::parse(&format!("http://127.0.0.1:{port}")).unwrap();
+    let connect = server::Behavior::new(
+        info(),
+        DevAuthenticator::new().with_player(TOKEN, "py_1".parse().unwrap()),
+        request::Config::default(),
+    );
+    let router =
+        router::Router::new().route(SERVICE, |_, request: vela_request::Request<Status>| {
+            let status = request.into_payload();
+            async move { Ok::<_, Status>(status) }
+        });
+    let router = router::Behavior::new(router, [PROTOCOL], request::Config::default())
+        .with_guard(connect.authenticated_connections());
+    let (peer_id, transport) = build_transport();
+    let mut swarm = swarm::server::Swarm::new(
+        transport,
+        ServerBehavior {
+            ping: ping::inbound::Behavior::default(),
+            connect,
+            router,
+        },
+        peer_id,
+        swarm::connection::PoolConfig::new(Box::new(TokioExecutor)),
+    );
+    swarm.listen_on(addr.clone()).unwrap();
+    tokio::spawn(async move { while swarm.next().await.is_some() {} });
+    addr
+}
+
+struct Client {
+    swarm: swarm::client::Swarm<ClientBehavior>,
+    handle: caller::Handle,
+    server: PeerId,
+}
+
+impl Client {
+    async fn connect(addr: &Url) -> Self {
+        let (caller, handle) = caller::Behavior::new(PROTOCOL, request::Config::default());
+        let (peer_id, transport) = build_transport();
+        let mut swarm = swarm::client::Swarm::new(
+            transport,
+            ClientBehavior {
+                ping: ping::outbound::Behavior::default(),
+                connect: client::Behavior::new(info(), request::Config::default()),
+                caller,
+            },
+            peer_id,
+            swarm::connection::PoolConfig::new(Box::new(TokioExecutor)),
+        );
+        swarm.dial(DialOpts::new(Some(addr.clone()), None)).unwrap();
+        let server = within(async {
+            loop {
+                if let Some(swarm::client::SwarmEvent::ConnectionEstablished { peer_id, .. }) =
+                    swarm.next().await
+                {
+                    return peer_id;
+                }
+            }
+        })
+        .await;
+        Self {
+            swarm,
+            handle: handle.with_timeout(EVENT_TIMEOUT),
+            server,
+        }
+    }
+
+    async fn authenticate(&mut self) {
+        self.swarm
+            .behavior_mut()
+            .connect
+            .send_authentication(self.server, TOKEN.to_string());
+        within(async {
+            loop {
+                if let Some(swarm::client::SwarmEvent::Behavior(ClientBehaviorEvent::Connect(
+                    event,
+                ))) = self.swarm.next().await
+                {
+                    match event {
+                        client::Event::Authenticated { .. } => return,
+                        event => panic!("unexpected event: {event:?}"),
+                    }
+                }
+            }
+        })
+        .await
+    }
+
+    /// 驱动 swarm 直到请求完成
+    async fn call(&mut self, message: &str) -> Result<Status, Status> {
+        let call = self.handle.call(
+            self.server,
+            SERVICE,
+            Status::new(Code::Ok, message.to_string()),
+        );
+        let mut call = std::pin::pin!(call);
+        let response: Response<Status> = within(async {
+            loop {
+                tokio::select! {
+                    result = &mut call => return result.unwrap(),
+                    _ = self.swarm.next() => {}
+                }
+            }
+        })
+        .await;
+        response.into_payload()
+    }
+}
+
+#[tokio::test]
+async fn requests_before_authentication_are_rejected() {
+    let addr = spawn_server();
+    let mut client = Client::connect(&addr).await;
+
+    let status = client.call("hello").await.unwrap_err();
+    assert_eq!(status.code_enum(), Code::Unauthenticated);
+
+    client.authenticate().await;
+    let echoed = client.call("hello").await.unwrap();
+    assert_eq!(echoed.message, "hello");
+}
+
+#[tokio::test]
+async fn authentication_only_admits_its_own_connection() {
+    let addr = spawn_server();
+    let mut authenticated = Client::connect(&addr).await;
+    authenticated.authenticate().await;
+    let mut anonymous = Client::connect(&addr).await;
+
+    assert!(authenticated.call("hello").await.is_ok());
+    let status = anonymous.call("hello").await.unwrap_err();
+    assert_eq!(status.code_enum(), Code::Unauthenticated);
+}
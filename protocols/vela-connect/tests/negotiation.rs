@@ -0,0 +1,83 @@
+mod common;
+
+use common::{Client, info, server_event, spawn_server};
+use semver::VersionReq;
+use vela_connect::{client, server};
+use vela_core::authenticate::DevAuthenticator;
+use vela_protobuf::{common::Code, connect::Info};
+use volans::request;
+
+fn behavior() -> server::Behavior<DevAuthenticator> {
+    server::Behavior::new(
+        info(),
+        DevAuthenticator::new().with_player("token", "py_1".parse().unwrap()),
+        request::Config::default(),
+    )
+    .with_supported_versions(VersionReq::parse(">=1.0.0, <2.0.0").unwrap())
+    .with_capabilities(["resume", "push"])
+    .with_required_capabilities(["resume"])
+}
+
+/// 以给定版本与功能连接并认证，返回客户端事件
+async fn authenticate(version: &str, capabilities: &[&str]) -> client::Event {
+    let (addr, _events) = spawn_server(behavior());
+    let info = Info {
+        version: version.to_string(),
+        capabilities: capabilities.iter().map(|c| c.to_string()).collect(),
+        ..info()
+    };
+    let mut client = Client::with_behavior(client::Behavior::new(info, request::Config::default()));
+    let (peer_id, _) = client.connect(&addr).await;
+    client
+        .behavior_mut()
+        .send_authentication(peer_id, "token".to_string());
+    client.next_event().await
+}
+
+fn rejected(event: client::Event) -> Code {
+    match event {
+        client::Event::Unauthenticated { status, .. } => status.code_enum(),
+        event => panic!("unexpected event: {event:?}"),
+    }
+}
+
+#[tokio::test]
+async fn incompatible_clients_are_rejected() {
+    for (version, capabilities) in [
+        ("2.0.0", &["resume"][..]),
+        ("not-a-version", &["resume"][..]),
+        ("1.0.0", &["push"][..]),
+    ] {
+        assert_eq!(
+            rejected(authenticate(version, capabilities).await),
+            Code::FailedPrecondition,
+            "version {version}, capabilities {capabilities:?}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn negotiated_capabilities_are_reported() {
+    let (addr, mut events) = spawn_server(behavior());
+    let info = Info {
+        version: "1.2.0".to_string(),
+        capabilities: vec!["resume".to_string(), "compression".to_string()],
+        ..info()
+    };
+    let mut client = Client::with_behavior(client::Behavior::new(info, request::Config::default()));
+    let (peer_id, _) = client.connect(&addr).await;
+    client
+        .behavior_mut()
+        .send_authentication(peer_id, "token".to_string());
+
+    match client.next_event().await {
+        client::Event::Authenticated { info, .. } => assert_eq!(info.capabilities, ["resume"]),
+        event => panic!("unexpected event: {event:?}"),
+    }
+    let capabilities = server_event(&mut events, |event| match event {
+        server::Event::Authenticated { capabilities, .. } => Some(capabilities),
+        _ => None,
+    })
+    .await;
+    assert_eq!(capabilities, ["resume"]);
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{Client, info, within};
+use futures::{FutureExt, StreamExt, channel::mpsc};
+use vela_connect::{
+    presence::{Event, Presence, Tracker},
+    server,
+};
+use vela_core::{authenticate::DevAuthenticator, ids::SessionId, session::LocalSessionRegistry};
+use volans::{core::Url, request, swarm::ConnectionId};
+
+fn behavior() -> server::Behavior<DevAuthenticator> {
+    let authenticator = DevAuthenticator::new().with_player("token", "py_1".parse().unwrap());
+    server::Behavior::new(info(), authenticator, request::Config::default())
+}
+
+/// 将服务端事件交给跟踪器，直到出现满足条件的事件
+async fn feed<T>(
+    tracker: &mut Tracker,
+    events: &mut mpsc::UnboundedReceiver<server::Event<()>>,
+    mut until: impl FnMut(&server::Event<()>) -> Option<T>,
+) -> T {
+    within(async {
+        loop {
+            let event = events.next().await.expect("server stopped");
+            tracker.on_connect_event(&event);
+            if let Some(value) = until(&event) {
+                return value;
+            }
+        }
+    })
+    .await
+}
+
+fn authenticated(event: &server::Event<()>) -> Option<SessionId> {
+    match event {
+        server::Event::Authenticated { identity, .. } => Some(identity.session_id().clone()),
+        _ => None,
+    }
+}
+
+async fn authenticate(addr: &Url) -> (Client, ConnectionId) {
+    let mut client = Client::new();
+    let (peer_id, connection_id) = client.connect(addr).await;
+    client
+        .behavior_mut()
+        .send_authentication(peer_id, "token".to_string());
+    client.next_event().await;
+    (client, connection_id)
+}
+
+/// 跟踪器中已产生的事件
+fn changes(tracker: &mut Tracker) -> Vec<Event> {
+    std::iter::from_fn(|| tracker.next().now_or_never().flatten()).collect()
+}
+
+fn went_offline(changes: &[Event], session_id: &SessionId) -> bool {
+    changes.iter().any(
+        |Event::Changed {
+             session_id: id,
+             presence,
+             ..
+         }| { id == session_id && *presence == Presence::Offline },
+    )
+}
+
+#[tokio::test]
+async fn replaced_sessions_go_offline() {
+    let behavior = behavior()
+        .with_session_registry(LocalSessionRegistry::new(), server::SessionPolicy::KickOld);
+    let (addr, mut events) = common::spawn_server(behavior);
+    let mut tracker = Tracker::default();
+
+    let _first = authenticate(&addr).await;
+    let old = feed(&mut tracker, &mut events, authenticated).await;
+    let _second = authenticate(&addr).await;
+    feed(&mut tracker, &mut events, authenticated).await;
+
+    assert!(went_offline(&changes(&mut tracker), &old));
+    assert_eq!(tracker.session_presence(&old), Presence::Offline);
+}
+
+#[tokio::test]
+async fn detached_sessions_go_offline_when_the_player_reauthenticates() {
+    let (addr, mut events) = common::spawn_server(behavior());
+    let mut tracker = Tracker::default();
+
+    let (mut first, connection_id) = authenticate(&addr).await;
+    let old = feed(&mut tracker, &mut events, authenticated).await;
+    first.close(connection_id);
+    first.drive_for(Duration::from_millis(100)).await;
+    feed(&mut tracker, &mut events, |event| {
+        matches!(event, server::Event::SessionDisconnected { .. }).then_some(())
+    })
+    .await;
+    assert_eq!(tracker.session_presence(&old), Presence::Away);
+
+    let _second = authenticate(&addr).await;
+    feed(&mut tracker, &mut events, authenticated).await;
+
+    assert!(went_offline(&changes(&mut tracker), &old));
+    assert_eq!(tracker.session_presence(&old), Presence::Offline);
+}
@@ -0,0 +1,103 @@
+mod common;
+
+use common::{Client, info, spawn_server};
+use vela_connect::{client, server};
+use vela_core::{
+    authenticate::{DenyList, JwtAuthenticator},
+    issuer::{JwtIssuer, TokenPair},
+    jwt,
+};
+use vela_protobuf::common::Code;
+use volans::{
+    core::{PeerId, Url},
+    request,
+};
+
+const SECRET: &[u8] = b"secret";
+
+fn behavior() -> server::Behavior<JwtAuthenticator> {
+    server::Behavior::new(
+        info(),
+        JwtAuthenticator::new(jwt::DecodingKey::from_secret(SECRET)),
+        request::Config::default(),
+    )
+    .with_issuer(JwtIssuer::from_secret(SECRET))
+}
+
+/// 认证新签发的令牌，返回客户端、服务端节点与令牌
+async fn authenticated(addr: &Url) -> (Client, PeerId, TokenPair) {
+    let pair = JwtIssuer::from_secret(SECRET)
+        .issue("py_1".parse().unwrap())
+        .unwrap();
+    let mut client = Client::new();
+    let (peer_id, _) = client.connect(addr).await;
+    client
+        .behavior_mut()
+        .send_authentication(peer_id, pair.access_token.clone());
+    match client.next_event().await {
+        client::Event::Authenticated { .. } => {}
+        event => panic!("unexpected event: {event:?}"),
+    }
+    (client, peer_id, pair)
+}
+
+fn refresh_failed(event: client::Event) -> Code {
+    match event {
+        client::Event::RefreshFailed { status, .. } => status.code_enum(),
+        event => panic!("unexpected event: {event:?}"),
+    }
+}
+
+#[tokio::test]
+async fn refresh_token_cannot_be_reused() {
+    let (addr, _events) = spawn_server(behavior());
+    let (mut client, peer_id, pair) = authenticated(&addr).await;
+
+    client
+        .behavior_mut()
+        .send_refresh(peer_id, pair.refresh_token.clone());
+    let refresh_token = match client.next_event().await {
+        client::Event::Refreshed { refresh_token, .. } => refresh_token,
+        event => panic!("unexpected event: {event:?}"),
+    };
+    assert_ne!(refresh_token, pair.refresh_token);
+
+    client
+        .behavior_mut()
+        .send_refresh(peer_id, pair.refresh_token);
+    assert_eq!(
+        refresh_failed(client.next_event().await),
+        Code::Unauthenticated
+    );
+}
+
+#[tokio::test]
+async fn refresh_is_rejected_for_revoked_sessions() {
+    let deny_list = DenyList::new();
+    let (addr, _events) = spawn_server(behavior().with_revocation_list(deny_list.clone()));
+    let (mut client, peer_id, pair) = authenticated(&addr).await;
+
+    deny_list.revoke(pair.session_id);
+    client
+        .behavior_mut()
+        .send_refresh(peer_id, pair.refresh_token);
+    assert_eq!(
+        refresh_failed(client.next_event().await),
+        Code::Unauthenticated
+    );
+}
+
+#[tokio::test]
+async fn refresh_counts_towards_attempt_limits() {
+    let behavior = behavior().with_limits(server::Limits::default().with_max_attempts_per_peer(1));
+    let (addr, _events) = spawn_server(behavior);
+    let (mut client, peer_id, pair) = authenticated(&addr).await;
+
+    client
+        .behavior_mut()
+        .send_refresh(peer_id, pair.refresh_token);
+    assert_eq!(
+        refresh_failed(client.next_event().await),
+        Code::ResourceExhausted
+    );
+}
@@ -0,0 +1,176 @@
+mod common;
+
+use std::{
+    io,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use common::{Client, info, spawn_server};
+use vela_connect::{
+    client::{self, Token, TokenProvider},
+    server,
+};
+use vela_core::{
+    authenticate::{DenyList, JwtAuthenticator},
+    issuer::JwtIssuer,
+    jwt,
+};
+use vela_protobuf::common::Code;
+use volans::{core::Url, request};
+
+const SECRET: &[u8] = b"secret";
+
+fn behavior() -> server::Behavior<JwtAuthenticator> {
+    server::Behavior::new(
+        info(),
+        JwtAuthenticator::new(jwt::DecodingKey::from_secret(SECRET)),
+        request::Config::default(),
+    )
+    .with_resume_window(Duration::from_secs(30))
+}
+
+/// 每次返回同一个令牌
+struct Fixed(String);
+
+#[async_trait::async_trait]
+impl TokenProvider for Fixed {
+    async fn token(&self) -> io::Result<Token> {
+        Ok(Token::new(self.0.clone()))
+    }
+}
+
+/// 认证并返回恢复令牌
+async fn authenticate(addr: &Url, token: String) -> String {
+    let mut client = Client::new();
+    let (peer_id, _) = client.connect(addr).await;
+    client.behavior_mut().send_authentication(peer_id, token);
+    match client.next_event().await {
+        client::Event::Authenticated {
+            resume_token: Some(resume_token),
+            ..
+        } => resume_token,
+        event => panic!("unexpected event: {event:?}"),
+    }
+}
+
+/// 在新的客户端上恢复会话
+async fn resume(addr: &Url, resume_token: String) -> client::Event {
+    let mut client = Client::new();
+    let (peer_id, _) = client.connect(addr).await;
+    client.behavior_mut().send_resume(peer_id, resume_token, 0);
+    client.next_event().await
+}
+
+#[tokio::test]
+async fn resume_issues_a_new_token() {
+    let (addr, _events) = spawn_server(behavior());
+    let issuer = JwtIssuer::from_secret(SECRET);
+    let token = issuer.issue("py_1".parse().unwrap()).unwrap().access_token;
+    let resume_token = authenticate(&addr, token).await;
+
+    let resume_token = match resume(&addr, resume_token).await {
+        client::Event::Resumed { resume_token, .. } => resume_token,
+        event => panic!("unexpected event: {event:?}"),
+    };
+    assert!(matches!(
+        resume(&addr, resume_token).await,
+        client::Event::Resumed { .. }
+    ));
+}
+
+#[tokio::test]
+async fn resume_is_rejected_after_the_token_expires() {
+    let (addr, _events) = spawn_server(behavior());
+    let issuer = JwtIssuer::from_secret(SECRET);
+    // 在默认 60 秒的时钟偏差内仍可认证，但已过期
+    let mut claims = issuer.claims("ss_expired".parse().unwrap(), "py_1".parse().unwrap());
+    claims.exp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        - 5;
+    let resume_token = authenticate(&addr, issuer.encode(&claims).unwrap()).await;
+
+    match resume(&addr, resume_token).await {
+        client::Event::ResumeFailed { status, .. } => {
+            assert_eq!(status.code, Code::Unauthenticated as i32)
+        }
+        event => panic!("unexpected event: {event:?}"),
+    }
+}
+
+#[tokio::test]
+async fn resume_is_rejected_for_revoked_sessions() {
+    let deny_list = DenyList::new();
+    let (addr, _events) = spawn_server(behavior().with_revocation_list(deny_list.clone()));
+    let issuer = JwtIssuer::from_secret(SECRET);
+    let pair = issuer.issue("py_1".parse().unwrap()).unwrap();
+    let resume_token = authenticate(&addr, pair.access_token).await;
+
+    deny_list.revoke(pair.session_id);
+    match resume(&addr, resume_token.clone()).await {
+        client::Event::ResumeFailed { status, .. } => {
+            assert_eq!(status.code, Code::Unauthenticated as i32)
+        }
+        event => panic!("unexpected event: {event:?}"),
+    }
+    // 已撤销的会话不再可恢复
+    assert!(matches!(
+        resume(&addr, resume_token).await,
+        client::Event::ResumeFailed { .. }
+    ));
+}
+
+#[tokio::test]
+async fn token_provider_resumes_before_authenticating() {
+    let (addr, _events) = spawn_server(behavior());
+    let issuer = JwtIssuer::from_secret(SECRET);
+    let token = issuer.issue("py_1".parse().unwrap()).unwrap().access_token;
+    let behavior =
+        client::Behavior::new(info(), request::Config::default()).with_token_provider(Fixed(token));
+    let mut client = Client::with_behavior(behavior);
+    let (_, connection_id) = client.connect(&addr).await;
+    let resume_token = match client.next_event().await {
+        client::Event::Authenticated {
+            resume_token: Some(resume_token),
+            ..
+        } => resume_token,
+        event => panic!("unexpected event: {event:?}"),
+    };
+    client.close(connection_id);
+    client.drive_for(Duration::from_millis(100)).await;
+
+    client
+        .behavior_mut()
+        .resume_next_connection(resume_token, 0);
+    client.connect(&addr).await;
+    // 不会先自动认证，否则恢复会因连接已认证而失败
+    let event = client.next_event().await;
+    assert!(
+        matches!(event, client::Event::Resumed { .. }),
+        "unexpected event: {event:?}"
+    );
+}
+
+#[tokio::test]
+async fn token_provider_authenticates_after_failed_resume() {
+    let (addr, _events) = spawn_server(behavior());
+    let issuer = JwtIssuer::from_secret(SECRET);
+    let token = issuer.issue("py_1".parse().unwrap()).unwrap().access_token;
+    let behavior =
+        client::Behavior::new(info(), request::Config::default()).with_token_provider(Fixed(token));
+    let mut client = Client::with_behavior(behavior);
+
+    client
+        .behavior_mut()
+        .resume_next_connection("unknown".to_string(), 0);
+    client.connect(&addr).await;
+    assert!(matches!(
+        client.next_event().await,
+        client::Event::ResumeFailed { .. }
+    ));
+    assert!(matches!(
+        client.next_event().await,
+        client::Event::Authenticated { .. }
+    ));
+}
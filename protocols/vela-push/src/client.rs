@@ -0,0 +1,317 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt, io, mem,
+    task::{Context, Poll},
+};
+
+use vela_protobuf::{FrameError, Framed};
+use volans::{
+    core::{PeerId, Url, upgrade::ReadyUpgrade},
+    swarm::{
+        BehaviorEvent, ConnectionDenied, ConnectionHandler, ConnectionHandlerEvent, ConnectionId,
+        NetworkBehavior, NetworkOutgoingBehavior, OutboundStreamHandler, OutboundUpgradeSend,
+        StreamProtocol, StreamUpgradeError, SubstreamProtocol, THandlerAction, THandlerEvent,
+        behavior::NotifyHandler, error::ConnectionError,
+    },
+};
+
+use crate::{HandlerEvent, PROTOCOL_NAME, PushStream, enqueue, poll_stream};
+
+enum OutboundState<TIncoming, TOutgoing>
+where
+    TIncoming: prost::Message + Default + fmt::Debug + Send + 'static,
+    TOutgoing: prost::Message + Default + fmt::Debug + Send + 'static,
+{
+    /// 等待打开子流
+    Idle,
+    Opening,
+    Open(Box<PushStream<TOutgoing, TIncoming>>),
+    Closed,
+}
+
+pub struct Handler<TIncoming, TOutgoing>
+where
+    TIncoming: prost::Message + Default + fmt::Debug + Send + 'static,
+    TOutgoing: prost::Message + Default + fmt::Debug + Send + 'static,
+{
+    outbound: OutboundState<TIncoming, TOutgoing>,
+    pending_message: VecDeque<TIncoming>,
+    pending_event: VecDeque<HandlerEvent<TOutgoing>>,
+}
+
+impl<TIncoming, TOutgoing> Handler<TIncoming, TOutgoing>
+where
+    TIncoming: prost::Message + Default + fmt::Debug + Send + 'static,
+    TOutgoing: prost::Message + Default + fmt::Debug + Send + 'static,
+{
+    pub fn new() -> Self {
+        Self {
+            outbound: OutboundState::Idle,
+            pending_message: VecDeque::new(),
+            pending_event: VecDeque::new(),
+        }
+    }
+}
+
+impl<TIncoming, TOutgoing> Default for Handler<TIncoming, TOutgoing>
+where
+    TIncoming: prost::Message + Default + fmt::Debug + Send + 'static,
+    TOutgoing: prost::Message + Default + fmt::Debug + Send + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<TIncoming, TOutgoing> ConnectionHandler for Handler<TIncoming, TOutgoing>
+where
+    TIncoming: prost::Message + Default + fmt::Debug + Send + 'static,
+    TOutgoing: prost::Message + Default + fmt::Debug + Send + 'static,
+{
+    type Action = TIncoming;
+    type Event = HandlerEvent<TOutgoing>;
+
+    fn handle_action(&mut self, action: Self::Action) {
+        if !enqueue(&mut self.pending_message, action) {
+            self.pending_event.push_back(HandlerEvent::Dropped);
+        }
+    }
+
+    fn connection_keep_alive(&self) -> bool {
+        !matches!(self.outbound, OutboundState::Closed)
+    }
+
+    fn poll(&mut self, cx: &mut Context<'_>) -> Poll<ConnectionHandlerEvent<Self::Event>> {
+        if let Some(event) = self.pending_event.pop_front() {
+            return Poll::Ready(ConnectionHandlerEvent::Notify(event));
+        }
+        if let OutboundState::Open(stream) = &mut self.outbound {
+            match poll_stream(stream, &mut self.pending_message, cx) {
+                Poll::Ready(Ok(message)) => {
+                    return Poll::Ready(ConnectionHandlerEvent::Notify(HandlerEvent::Message(
+                        message,
+                    )));
+                }
+                Poll::Ready(Err(error)) => {
+                    self.outbound = OutboundState::Closed;
+                    let error = match error {
+                        FrameError::Closed => None,
+                        error => Some(error),
+                    };
+                    return Poll::Ready(ConnectionHandlerEvent::Notify(HandlerEvent::Closed(
+                        error,
+                    )));
+                }
+                Poll::Pending => {}
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl<TIncoming, TOutgoing> OutboundStreamHandler for Handler<TIncoming, TOutgoing>
+where
+    TIncoming: prost::Message + Default + fmt::Debug + Send + 'static,
+    TOutgoing: prost::Message + Default + fmt::Debug + Send + 'static,
+{
+    type OutboundUpgrade = ReadyUpgrade<StreamProtocol>;
+    type OutboundUserData = ();
+
+    fn on_fully_negotiated(
+        &mut self,
+        _user_data: Self::OutboundUserData,
+        stream: <Self::OutboundUpgrade as OutboundUpgradeSend>::Output,
+    ) {
+        self.outbound = OutboundState::Open(Box::new(Framed::new(stream)));
+        self.pending_event.push_back(HandlerEvent::Opened);
+    }
+
+    fn on_upgrade_error(
+        &mut self,
+        _user_data: Self::OutboundUserData,
+        error: StreamUpgradeError<<Self::OutboundUpgrade as OutboundUpgradeSend>::Error>,
+    ) {
+        self.outbound = OutboundState::Closed;
+        let error = match error {
+            StreamUpgradeError::Timeout => {
+                io::Error::new(io::ErrorKind::TimedOut, "Push stream negotiation timed out")
+            }
+            StreamUpgradeError::NegotiationFailed => {
+                io::Error::new(io::ErrorKind::Unsupported, "Push protocol not supported")
+            }
+            StreamUpgradeError::Io(e) => e,
+            StreamUpgradeError::Apply(e) => match e {},
+        };
+        self.pending_event
+            .push_back(HandlerEvent::Closed(Some(FrameError::Io(error))));
+    }
+
+    fn poll_outbound_request(
+        &mut self,
+        _cx: &mut Context<'_>,
+    ) -> Poll<SubstreamProtocol<Self::OutboundUpgrade, Self::OutboundUserData>> {
+        match mem::replace(&mut self.outbound, OutboundState::Opening) {
+            OutboundState::Idle => {
+                Poll::Ready(SubstreamProtocol::new(ReadyUpgrade::new(PROTOCOL_NAME), ()))
+            }
+            state => {
+                self.outbound = state;
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// 推送客户端
+///
+/// 每条连接建立后自动打开推送子流，接收服务端推送的消息。
+pub struct Behavior<TIncoming, TOutgoing> {
+    connections: HashMap<PeerId, Vec<ConnectionId>>,
+    pending_event: VecDeque<BehaviorEvent<Event<TOutgoing>, TIncoming>>,
+}
+
+impl<TIncoming, TOutgoing> Behavior<TIncoming, TOutgoing>
+where
+    TIncoming: prost::Message + Default + fmt::Debug + Send + 'static,
+    TOutgoing: prost::Message + Default + fmt::Debug + Send + 'static,
+{
+    pub fn new() -> Self {
+        Self {
+            connections: HashMap::new(),
+            pending_event: VecDeque::new(),
+        }
+    }
+
+    /// 向服务端发送消息，未连接时返回 false
+    ///
+    /// 连接的发送队列已满时消息被丢弃，并产生 [`Event::Dropped`]。
+    pub fn send(&mut self, peer_id: PeerId, message: TIncoming) -> bool {
+        if !self.connections.contains_key(&peer_id) {
+            return false;
+        }
+        self.pending_event.push_back(BehaviorEvent::HandlerAction {
+            peer_id,
+            handler: NotifyHandler::Any,
+            action: message,
+        });
+        true
+    }
+}
+
+impl<TIncoming, TOutgoing> Default for Behavior<TIncoming, TOutgoing>
+where
+    TIncoming: prost::Message + Default + fmt::Debug + Send + 'static,
+    TOutgoing: prost::Message + Default + fmt::Debug + Send + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<TIncoming, TOutgoing> NetworkBehavior for Behavior<TIncoming, TOutgoing>
+where
+    TIncoming: prost::Message + Default + fmt::Debug + Send + 'static,
+    TOutgoing: prost::Message + Default + fmt::Debug + Send + 'static,
+{
+    type Event = Event<TOutgoing>;
+    type ConnectionHandler = Handler<TIncoming, TOutgoing>;
+
+    fn on_connection_handler_event(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        event: THandlerEvent<Self>,
+    ) {
+        let event = match event {
+            HandlerEvent::Opened => Event::Opened {
+                peer_id,
+                connection_id: id,
+            },
+            HandlerEvent::Message(message) => Event::Message {
+                peer_id,
+                connection_id: id,
+                message,
+            },
+            HandlerEvent::Closed(error) => Event::Closed {
+                peer_id,
+                connection_id: id,
+                error,
+            },
+            HandlerEvent::Dropped => Event::Dropped {
+                peer_id,
+                connection_id: id,
+            },
+        };
+        self.pending_event.push_back(BehaviorEvent::Behavior(event));
+    }
+
+    fn poll(
+        &mut self,
+        _cx: &mut Context<'_>,
+    ) -> Poll<BehaviorEvent<Self::Event, THandlerAction<Self>>> {
+        if let Some(event) = self.pending_event.pop_front() {
+            return Poll::Ready(event);
+        }
+        Poll::Pending
+    }
+}
+
+impl<TIncoming, TOutgoing> NetworkOutgoingBehavior for Behavior<TIncoming, TOutgoing>
+where
+    TIncoming: prost::Message + Default + fmt::Debug + Send + 'static,
+    TOutgoing: prost::Message + Default + fmt::Debug + Send + 'static,
+{
+    fn handle_established_connection(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        _addr: &Url,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        Ok(Handler::new())
+    }
+
+    fn on_connection_established(&mut self, id: ConnectionId, peer_id: PeerId, _addr: &Url) {
+        self.connections.entry(peer_id).or_default().push(id);
+    }
+
+    fn on_connection_closed(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        _addr: &Url,
+        _reason: Option<&ConnectionError>,
+    ) {
+        if let Some(connections) = self.connections.get_mut(&peer_id) {
+            connections.retain(|c| *c != id);
+            if connections.is_empty() {
+                self.connections.remove(&peer_id);
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Event<TOutgoing> {
+    /// 推送子流已打开
+    Opened {
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+    },
+    /// 收到服务端推送的消息
+    Message {
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        message: TOutgoing,
+    },
+    /// 推送子流已关闭
+    Closed {
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        error: Option<FrameError>,
+    },
+    /// 连接的发送队列已满，一条消息被丢弃
+    Dropped {
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+    },
+}
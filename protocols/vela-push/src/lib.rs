@@ -0,0 +1,79 @@
+pub mod client;
+pub mod server;
+
+use std::{
+    collections::VecDeque,
+    task::{Context, Poll},
+};
+
+use futures::{SinkExt, StreamExt};
+use vela_protobuf::{FrameError, Framed};
+use volans::swarm::{StreamProtocol, Substream};
+
+pub const PROTOCOL_NAME: StreamProtocol = StreamProtocol::new("/v1/push");
+
+/// 子流未打开或写入受阻时，每个连接最多缓存的待发送消息数量
+pub const MAX_PENDING_MESSAGES: usize = 256;
+
+/// 推送子流，读取 `TInput` 并写入 `TOutput`
+pub(crate) type PushStream<TInput, TOutput> = Framed<TInput, TOutput, Substream>;
+
+/// 连接处理器事件
+#[derive(Debug)]
+pub enum HandlerEvent<T> {
+    /// 推送子流已建立
+    Opened,
+    /// 收到对端消息
+    Message(T),
+    /// 推送子流已关闭，`None` 表示对端正常关闭
+    Closed(Option<FrameError>),
+    /// 发送队列已满，一条待发送的消息被丢弃
+    Dropped,
+}
+
+/// 将消息加入发送队列，队列已满时丢弃消息并返回 false
+pub(crate) fn enqueue<T>(pending_message: &mut VecDeque<T>, message: T) -> bool {
+    if pending_message.len() >= MAX_PENDING_MESSAGES {
+        tracing::warn!(
+            "Push queue full ({} messages), dropping message",
+            MAX_PENDING_MESSAGES
+        );
+        return false;
+    }
+    pending_message.push_back(message);
+    true
+}
+
+/// 发送队列中的消息并读取下一条对端消息
+///
+/// 子流关闭或出错时返回 `Err`。
+pub(crate) fn poll_stream<TInput, TOutput>(
+    stream: &mut PushStream<TInput, TOutput>,
+    pending_message: &mut VecDeque<TOutput>,
+    cx: &mut Context<'_>,
+) -> Poll<Result<TInput, FrameError>>
+where
+    TInput: prost::Message + Default,
+    TOutput: prost::Message + Default,
+{
+    while !pending_message.is_empty() {
+        match stream.poll_ready_unpin(cx) {
+            Poll::Ready(Ok(())) => {
+                let message = pending_message.pop_front().expect("message should exist");
+                if let Err(e) = stream.start_send_unpin(message) {
+                    return Poll::Ready(Err(e));
+                }
+            }
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+            Poll::Pending => break,
+        }
+    }
+    if let Poll::Ready(Err(e)) = stream.poll_flush_unpin(cx) {
+        return Poll::Ready(Err(e));
+    }
+    match stream.poll_next_unpin(cx) {
+        Poll::Ready(Some(result)) => Poll::Ready(result),
+        Poll::Ready(None) => Poll::Ready(Err(FrameError::Closed)),
+        Poll::Pending => Poll::Pending,
+    }
+}
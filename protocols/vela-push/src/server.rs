@@ -0,0 +1,561 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use vela_core::ids::SessionId;
+use vela_protobuf::{FrameError, Framed};
+use volans::{
+    core::{PeerId, Url, upgrade::ReadyUpgrade},
+    swarm::{
+        BehaviorEvent, ConnectionDenied, ConnectionHandler, ConnectionHandlerEvent, ConnectionId,
+        InboundStreamHandler, InboundUpgradeSend, NetworkBehavior, NetworkIncomingBehavior,
+        StreamProtocol, SubstreamProtocol, THandlerAction, THandlerEvent, behavior::NotifyHandler,
+        error::ConnectionError,
+    },
+};
+
+use crate::{HandlerEvent, PROTOCOL_NAME, PushStream, enqueue, poll_stream};
+
+/// 会话绑定前每个连接最多缓存的客户端消息数量
+pub const MAX_UNBOUND_MESSAGES: usize = 32;
+
+pub struct Handler<TIncoming, TOutgoing>
+where
+    TIncoming: prost::Message + Default + fmt::Debug + Send + 'static,
+    TOutgoing: prost::Message + Default + fmt::Debug + Send + 'static,
+{
+    stream: Option<PushStream<TIncoming, TOutgoing>>,
+    pending_message: VecDeque<TOutgoing>,
+    pending_event: VecDeque<HandlerEvent<TIncoming>>,
+}
+
+impl<TIncoming, TOutgoing> Handler<TIncoming, TOutgoing>
+where
+    TIncoming: prost::Message + Default + fmt::Debug + Send + 'static,
+    TOutgoing: prost::Message + Default + fmt::Debug + Send + 'static,
+{
+    pub fn new() -> Self {
+        Self {
+            stream: None,
+            pending_message: VecDeque::new(),
+            pending_event: VecDeque::new(),
+        }
+    }
+}
+
+impl<TIncoming, TOutgoing> Default for Handler<TIncoming, TOutgoing>
+where
+    TIncoming: prost::Message + Default + fmt::Debug + Send + 'static,
+    TOutgoing: prost::Message + Default + fmt::Debug + Send + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<TIncoming, TOutgoing> ConnectionHandler for Handler<TIncoming, TOutgoing>
+where
+    TIncoming: prost::Message + Default + fmt::Debug + Send + 'static,
+    TOutgoing: prost::Message + Default + fmt::Debug + Send + 'static,
+{
+    type Action = TOutgoing;
+    type Event = HandlerEvent<TIncoming>;
+
+    fn handle_action(&mut self, action: Self::Action) {
+        if !enqueue(&mut self.pending_message, action) {
+            self.pending_event.push_back(HandlerEvent::Dropped);
+        }
+    }
+
+    fn connection_keep_alive(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    fn poll(&mut self, cx: &mut Context<'_>) -> Poll<ConnectionHandlerEvent<Self::Event>> {
+        if let Some(event) = self.pending_event.pop_front() {
+            return Poll::Ready(ConnectionHandlerEvent::Notify(event));
+        }
+        if let Some(stream) = self.stream.as_mut() {
+            match poll_stream(stream, &mut self.pending_message, cx) {
+                Poll::Ready(Ok(message)) => {
+                    return Poll::Ready(ConnectionHandlerEvent::Notify(HandlerEvent::Message(
+                        message,
+                    )));
+                }
+                Poll::Ready(Err(error)) => {
+                    self.stream = None;
+                    let error = match error {
+                        FrameError::Closed => None,
+                        error => Some(error),
+                    };
+                    return Poll::Ready(ConnectionHandlerEvent::Notify(HandlerEvent::Closed(
+                        error,
+                    )));
+                }
+                Poll::Pending => {}
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl<TIncoming, TOutgoing> InboundStreamHandler for Handler<TIncoming, TOutgoing>
+where
+    TIncoming: prost::Message + Default + fmt::Debug + Send + 'static,
+    TOutgoing: prost::Message + Default + fmt::Debug + Send + 'static,
+{
+    type InboundUpgrade = ReadyUpgrade<StreamProtocol>;
+    type InboundUserData = ();
+
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundUpgrade, Self::InboundUserData> {
+        SubstreamProtocol::new(ReadyUpgrade::new(PROTOCOL_NAME), ())
+    }
+
+    fn on_fully_negotiated(
+        &mut self,
+        _user_data: Self::InboundUserData,
+        stream: <Self::InboundUpgrade as InboundUpgradeSend>::Output,
+    ) {
+        if self.stream.is_some() {
+            tracing::debug!("Replacing existing push stream");
+        }
+        self.stream = Some(Framed::new(stream));
+        self.pending_event.push_back(HandlerEvent::Opened);
+    }
+
+    fn on_upgrade_error(
+        &mut self,
+        _user_data: Self::InboundUserData,
+        error: <Self::InboundUpgrade as InboundUpgradeSend>::Error,
+    ) {
+        match error {}
+    }
+}
+
+/// 会话已发送消息的缓存，用于断线重连后重放
+///
+/// 每个会话的消息从 1 开始按发送顺序编号，客户端按收到的消息数量记录最后的序号。
+struct ReplayBuffer<T> {
+    last_seq: u64,
+    messages: VecDeque<(u64, T)>,
+    /// 连接断开的时间，超过重放窗口后缓存被丢弃
+    detached_at: Option<Instant>,
+}
+
+impl<T: Clone> ReplayBuffer<T> {
+    fn new() -> Self {
+        Self {
+            last_seq: 0,
+            messages: VecDeque::new(),
+            detached_at: None,
+        }
+    }
+
+    fn push(&mut self, message: T, capacity: usize) {
+        self.last_seq += 1;
+        self.messages.push_back((self.last_seq, message));
+        while self.messages.len() > capacity {
+            self.messages.pop_front();
+        }
+    }
+
+    /// 序号大于 `last_seq` 的消息，缓存已不完整时返回 `None`
+    fn since(&self, last_seq: u64) -> Option<Vec<T>> {
+        if last_seq > self.last_seq {
+            return None;
+        }
+        let first = self
+            .messages
+            .front()
+            .map_or(self.last_seq + 1, |(seq, _)| *seq);
+        if last_seq + 1 < first {
+            return None;
+        }
+        Some(
+            self.messages
+                .iter()
+                .filter(|(seq, _)| *seq > last_seq)
+                .map(|(_, message)| message.clone())
+                .collect(),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ReplayConfig {
+    window: Duration,
+    capacity: usize,
+}
+
+/// 推送服务端
+///
+/// 客户端在每条连接上打开一条长连接子流，服务端将子流与已认证的会话绑定后，
+/// 即可通过 [`Behavior::send`] 按会话推送消息。
+///
+/// 启用 [`Behavior::with_replay`] 后，连接断开的会话在重放窗口内继续缓存消息，
+/// 客户端重连后通过 [`Behavior::resume`] 补发未收到的消息。
+///
+/// 推送服务不感知连接认证，会话绑定由调用方完成：收到连接服务的 `Authenticated`
+/// 事件后调用 [`Behavior::bind`]，`Resumed` 事件后调用 [`Behavior::resume`]。
+/// 绑定前收到的客户端消息最多缓存 [`MAX_UNBOUND_MESSAGES`] 条，绑定后再产生事件。
+///
+/// 子流未打开或写入受阻时每个连接最多缓存 [`MAX_PENDING_MESSAGES`] 条待推送消息，
+/// 超出的消息会被丢弃并产生 [`Event::Dropped`]。
+///
+/// [`MAX_PENDING_MESSAGES`]: crate::MAX_PENDING_MESSAGES
+pub struct Behavior<TIncoming, TOutgoing> {
+    sessions: HashMap<SessionId, (PeerId, ConnectionId)>,
+    connections: HashMap<ConnectionId, SessionId>,
+    unbound: HashMap<ConnectionId, Vec<TIncoming>>,
+    replay: Option<ReplayConfig>,
+    buffers: HashMap<SessionId, ReplayBuffer<TOutgoing>>,
+    pending_event: VecDeque<BehaviorEvent<Event<TIncoming>, TOutgoing>>,
+}
+
+impl<TIncoming, TOutgoing> Behavior<TIncoming, TOutgoing>
+where
+    TIncoming: prost::Message + Default + fmt::Debug + Send + 'static,
+    TOutgoing: prost::Message + Default + fmt::Debug + Clone + Send + 'static,
+{
+    pub fn new() -> Self {
+        Self {
+            sessions: HashMap::new(),
+            connections: HashMap::new(),
+            unbound: HashMap::new(),
+            replay: None,
+            buffers: HashMap::new(),
+            pending_event: VecDeque::new(),
+        }
+    }
+
+    /// 为每个会话缓存最近 `capacity` 条消息，连接断开后保留 `window`
+    pub fn with_replay(mut self, window: Duration, capacity: usize) -> Self {
+        self.replay = Some(ReplayConfig { window, capacity });
+        self
+    }
+
+    /// 将重连的会话绑定到新连接，并补发序号大于 `last_seq` 的消息
+    ///
+    /// 缓存已过期或不完整时返回 `None`，会话仍会绑定，客户端需要重新同步状态。
+    pub fn resume(
+        &mut self,
+        session_id: SessionId,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        last_seq: u64,
+    ) -> Option<usize> {
+        self.expire_buffers();
+        let messages = self
+            .buffers
+            .get(&session_id)
+            .and_then(|buffer| buffer.since(last_seq));
+        let Some(messages) = messages else {
+            // 无法补发，序号从头开始
+            self.bind(session_id, peer_id, connection_id);
+            return None;
+        };
+        self.attach(session_id, peer_id, connection_id);
+        let count = messages.len();
+        for message in messages {
+            self.pending_event.push_back(BehaviorEvent::HandlerAction {
+                peer_id,
+                handler: NotifyHandler::One(connection_id),
+                action: message,
+            });
+        }
+        Some(count)
+    }
+
+    /// 会话最后发送的消息序号
+    pub fn last_seq(&self, session_id: &SessionId) -> Option<u64> {
+        self.buffers.get(session_id).map(|buffer| buffer.last_seq)
+    }
+
+    fn expire_buffers(&mut self) {
+        if let Some(config) = self.replay {
+            self.buffers.retain(|_, buffer| {
+                buffer
+                    .detached_at
+                    .is_none_or(|detached_at| detached_at.elapsed() < config.window)
+            });
+        }
+    }
+
+    /// 将已认证的会话绑定到连接上，会话已绑定其他连接时会被替换
+    ///
+    /// 会话的消息序号从头开始，重连请使用 [`Behavior::resume`]。
+    pub fn bind(&mut self, session_id: SessionId, peer_id: PeerId, connection_id: ConnectionId) {
+        if self.replay.is_some() {
+            self.buffers.insert(session_id.clone(), ReplayBuffer::new());
+        }
+        self.attach(session_id, peer_id, connection_id);
+    }
+
+    fn attach(&mut self, session_id: SessionId, peer_id: PeerId, connection_id: ConnectionId) {
+        if let Some((_, old)) = self.sessions.remove(&session_id) {
+            self.connections.remove(&old);
+        }
+        if let Some(old) = self.connections.remove(&connection_id) {
+            self.sessions.remove(&old);
+        }
+        self.sessions
+            .insert(session_id.clone(), (peer_id, connection_id));
+        self.connections.insert(connection_id, session_id.clone());
+        if let Some(buffer) = self.buffers.get_mut(&session_id) {
+            buffer.detached_at = None;
+        }
+        for message in self.unbound.remove(&connection_id).unwrap_or_default() {
+            self.pending_event
+                .push_back(BehaviorEvent::Behavior(Event::Message {
+                    peer_id,
+                    connection_id,
+                    session_id: session_id.clone(),
+                    message,
+                }));
+        }
+    }
+
+    /// 解除会话绑定，并丢弃会话的重放缓存
+    pub fn unbind(&mut self, session_id: &SessionId) -> bool {
+        self.buffers.remove(session_id);
+        if let Some((_, connection_id)) = self.sessions.remove(session_id) {
+            self.connections.remove(&connection_id);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn is_bound(&self, session_id: &SessionId) -> bool {
+        self.sessions.contains_key(session_id)
+    }
+
+    /// 向会话推送消息，会话未绑定时返回 false
+    ///
+    /// 启用重放时，断开的会话在重放窗口内的消息会被缓存并返回 true。
+    /// 连接的发送队列已满时消息被丢弃，并产生 [`Event::Dropped`]。
+    pub fn send(&mut self, session_id: &SessionId, message: TOutgoing) -> bool {
+        if let Some(config) = self.replay {
+            self.expire_buffers();
+            match self.buffers.get_mut(session_id) {
+                Some(buffer) => buffer.push(message.clone(), config.capacity),
+                None if self.sessions.contains_key(session_id) => {
+                    let mut buffer = ReplayBuffer::new();
+                    buffer.push(message.clone(), config.capacity);
+                    self.buffers.insert(session_id.clone(), buffer);
+                }
+                None => return false,
+            }
+        }
+        if let Some((peer_id, connection_id)) = self.sessions.get(session_id) {
+            self.pending_event.push_back(BehaviorEvent::HandlerAction {
+                peer_id: *peer_id,
+                handler: NotifyHandler::One(*connection_id),
+                action: message,
+            });
+            true
+        } else {
+            self.replay.is_some()
+        }
+    }
+}
+
+impl<TIncoming, TOutgoing> Default for Behavior<TIncoming, TOutgoing>
+where
+    TIncoming: prost::Message + Default + fmt::Debug + Send + 'static,
+    TOutgoing: prost::Message + Default + fmt::Debug + Clone + Send + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<TIncoming, TOutgoing> NetworkBehavior for Behavior<TIncoming, TOutgoing>
+where
+    TIncoming: prost::Message + Default + fmt::Debug + Send + 'static,
+    TOutgoing: prost::Message + Default + fmt::Debug + Send + 'static,
+{
+    type Event = Event<TIncoming>;
+    type ConnectionHandler = Handler<TIncoming, TOutgoing>;
+
+    fn on_connection_handler_event(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        event: THandlerEvent<Self>,
+    ) {
+        let session_id = self.connections.get(&id).cloned();
+        let event = match event {
+            HandlerEvent::Opened => Event::Opened {
+                peer_id,
+                connection_id: id,
+                session_id,
+            },
+            HandlerEvent::Message(message) => match session_id {
+                Some(session_id) => Event::Message {
+                    peer_id,
+                    connection_id: id,
+                    session_id,
+                    message,
+                },
+                None => {
+                    let pending = self.unbound.entry(id).or_default();
+                    if pending.len() < MAX_UNBOUND_MESSAGES {
+                        pending.push(message);
+                    } else {
+                        tracing::warn!(
+                            "Dropping push message from unbound connection {} of {}",
+                            id,
+                            peer_id
+                        );
+                    }
+                    return;
+                }
+            },
+            HandlerEvent::Closed(error) => Event::Closed {
+                peer_id,
+                connection_id: id,
+                session_id,
+                error,
+            },
+            HandlerEvent::Dropped => Event::Dropped {
+                peer_id,
+                connection_id: id,
+                session_id,
+            },
+        };
+        self.pending_event.push_back(BehaviorEvent::Behavior(event));
+    }
+
+    fn poll(
+        &mut self,
+        _cx: &mut Context<'_>,
+    ) -> Poll<BehaviorEvent<Self::Event, THandlerAction<Self>>> {
+        if let Some(event) = self.pending_event.pop_front() {
+            return Poll::Ready(event);
+        }
+        Poll::Pending
+    }
+}
+
+impl<TIncoming, TOutgoing> NetworkIncomingBehavior for Behavior<TIncoming, TOutgoing>
+where
+    TIncoming: prost::Message + Default + fmt::Debug + Send + 'static,
+    TOutgoing: prost::Message + Default + fmt::Debug + Send + 'static,
+{
+    fn handle_established_connection(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        _local_addr: &Url,
+        _remote_addr: &Url,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        Ok(Handler::new())
+    }
+
+    fn on_connection_closed(
+        &mut self,
+        id: ConnectionId,
+        _peer_id: PeerId,
+        _local_addr: &Url,
+        _remote_addr: &Url,
+        _reason: Option<&ConnectionError>,
+    ) {
+        if let Some(session_id) = self.connections.remove(&id) {
+            self.sessions.remove(&session_id);
+            if let Some(buffer) = self.buffers.get_mut(&session_id) {
+                buffer.detached_at = Some(Instant::now());
+            }
+        }
+        self.unbound.remove(&id);
+    }
+}
+
+#[derive(Debug)]
+pub enum Event<TIncoming> {
+    /// 客户端打开了推送子流
+    Opened {
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        session_id: Option<SessionId>,
+    },
+    /// 收到已绑定会话的消息
+    Message {
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        session_id: SessionId,
+        message: TIncoming,
+    },
+    /// 推送子流已关闭
+    Closed {
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        session_id: Option<SessionId>,
+        error: Option<FrameError>,
+    },
+    /// 连接的发送队列已满，一条推送消息被丢弃
+    ///
+    /// 启用重放时消息仍在重放缓存中，可由客户端重连后补发。
+    Dropped {
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        session_id: Option<SessionId>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::task::noop_waker_ref;
+    use vela_protobuf::common::Status;
+
+    use super::*;
+    use crate::MAX_PENDING_MESSAGES;
+
+    fn poll_events(handler: &mut Handler<Status, Status>) -> Vec<HandlerEvent<Status>> {
+        let mut cx = Context::from_waker(noop_waker_ref());
+        std::iter::from_fn(|| match handler.poll(&mut cx) {
+            Poll::Ready(ConnectionHandlerEvent::Notify(event)) => Some(event),
+            _ => None,
+        })
+        .collect()
+    }
+
+    #[test]
+    fn full_queue_reports_dropped_messages() {
+        let mut handler = Handler::<Status, Status>::new();
+        for _ in 0..MAX_PENDING_MESSAGES {
+            handler.handle_action(Status::default());
+        }
+        assert!(poll_events(&mut handler).is_empty());
+
+        handler.handle_action(Status::default());
+        handler.handle_action(Status::default());
+
+        let events = poll_events(&mut handler);
+        assert_eq!(events.len(), 2);
+        assert!(
+            events
+                .iter()
+                .all(|event| matches!(event, HandlerEvent::Dropped))
+        );
+        assert_eq!(handler.pending_message.len(), MAX_PENDING_MESSAGES);
+    }
+
+    #[test]
+    fn replay_buffer_returns_messages_after_last_seq() {
+        let mut buffer = ReplayBuffer::new();
+        for seq in 1..=5u32 {
+            buffer.push(seq, 3);
+        }
+
+        assert_eq!(buffer.since(2), Some(vec![3, 4, 5]));
+        assert_eq!(buffer.since(4), Some(vec![5]));
+        assert_eq!(buffer.since(5), Some(Vec::new()));
+        // 已被淘汰或超出已发送范围
+        assert_eq!(buffer.since(1), None);
+        assert_eq!(buffer.since(6), None);
+    }
+}
@@ -0,0 +1,286 @@
+//! 基于本地 TCP 连接的推送服务端与客户端
+
+use std::{pin::Pin, time::Duration};
+
+use futures::StreamExt;
+use vela_core::ids::SessionId;
+use vela_push::{client, server};
+use volans::{
+    Transport,
+    core::{PeerId, Url, muxing::StreamMuxerBox, transport::Boxed},
+    muxing, ping, plaintext,
+    swarm::{self, ConnectionId, DialOpts, NetworkIncomingBehavior, NetworkOutgoingBehavior},
+    tcp,
+};
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct Note {
+    #[prost(string, tag = "1")]
+    text: String,
+}
+
+fn note(text: &str) -> Note {
+    Note {
+        text: text.to_string(),
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy)]
+struct TokioExecutor;
+
+impl swarm::Executor for TokioExecutor {
+    fn exec(&self, future: Pin<Box<dyn Future<Output = ()> + Send>>) {
+        tokio::spawn(future);
+    }
+}
+
+fn build_transport() -> (PeerId, Boxed<(PeerId, StreamMuxerBox)>) {
+    let key: [u8; 32] = rand::random();
+    let signing_key = plaintext::ed25519::SigningKey::from_bytes(&key);
+    let transport = tcp::Config::new()
+        .upgrade()
+        .authenticate(plaintext::Config::new(signing_key.verifying_key()))
+        .multiplex(muxing::Config::new())
+        .boxed();
+    (PeerId::from_bytes(key), transport)
+}
+
+/// 与网关示例一致，双方都挂载心跳协议
+#[derive(NetworkIncomingBehavior)]
+struct ServerBehavior {
+    ping: ping::inbound::Behavior,
+    push: server::Behavior<Note, Note>,
+}
+
+#[derive(NetworkOutgoingBehavior)]
+struct ClientBehavior {
+    ping: ping::outbound::Behavior,
+    push: client::Behavior<Note, Note>,
+}
+
+type ServerSwarm = swarm::server::Swarm<ServerBehavior>;
+type ClientSwarm = swarm::client::Swarm<ClientBehavior>;
+
+struct Net {
+    addr: Url,
+    server: ServerSwarm,
+    client: ClientSwarm,
+}
+
+impl Net {
+    fn new(behavior: server::Behavior<Note, Note>) -> Self {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .unwrap()
+            .port();
+        // TCP 传输只读取主机与端口，非特殊协议名的 URL 不会解析 IPv4 地址
+        let addr = Url::parse(&format!("http://127.0.0.1:{port}")).unwrap();
+        let (peer_id, transport) = build_transport();
+        let mut server = swarm::server::Swarm::new(
+            transport,
+            ServerBehavior {
+                ping: ping::inbound::Behavior::default(),
+                push: behavior,
+            },
+            peer_id,
+            swarm::connection::PoolConfig::new(Box::new(TokioExecutor)),
+        );
+        server.listen_on(addr.clone()).unwrap();
+        let (peer_id, transport) = build_transport();
+        let client = swarm::client::Swarm::new(
+            transport,
+            ClientBehavior {
+                ping: ping::outbound::Behavior::default(),
+                push: client::Behavior::new(),
+            },
+            peer_id,
+            swarm::connection::PoolConfig::new(Box::new(TokioExecutor)),
+        );
+        Self {
+            addr,
+            server,
+            client,
+        }
+    }
+
+    fn dial(&mut self) {
+        self.client
+            .dial(DialOpts::new(Some(self.addr.clone()), None))
+            .unwrap();
+    }
+
+    /// 同时驱动双方，直到服务端产生推送事件
+    async fn server_event(&mut self) -> server::Event<Note> {
+        within(async {
+            loop {
+                tokio::select! {
+                    Some(event) = self.server.next() => {
+                        if let swarm::server::SwarmEvent::Behavior(ServerBehaviorEvent::Push(event)) = event {
+                            return event;
+                        }
+                    }
+                    Some(_) = self.client.next() => {}
+                }
+            }
+        })
+        .await
+    }
+
+    /// 同时驱动双方，直到客户端产生推送事件
+    async fn client_event(&mut self) -> client::Event<Note> {
+        within(async {
+            loop {
+                tokio::select! {
+                    Some(_) = self.server.next() => {}
+                    Some(event) = self.client.next() => {
+                        if let swarm::client::SwarmEvent::Behavior(ClientBehaviorEvent::Push(
+                            event,
+                        )) = event
+                        {
+                            return event;
+                        }
+                    }
+                }
+            }
+        })
+        .await
+    }
+
+    /// 等待双方的推送子流打开，返回服务端看到的连接与客户端看到的服务端
+    async fn opened(&mut self) -> (PeerId, ConnectionId, PeerId) {
+        within(async {
+            let (mut server_side, mut client_side) = (None, None);
+            loop {
+                tokio::select! {
+                    Some(event) = self.server.next() => {
+                        if let swarm::server::SwarmEvent::Behavior(ServerBehaviorEvent::Push(
+                            server::Event::Opened {
+                                peer_id,
+                                connection_id,
+                                ..
+                            },
+                        )) = event
+                        {
+                            server_side = Some((peer_id, connection_id));
+                        }
+                    }
+                    Some(event) = self.client.next() => {
+                        if let swarm::client::SwarmEvent::Behavior(ClientBehaviorEvent::Push(
+                            client::Event::Opened { peer_id, .. },
+                        )) = event
+                        {
+                            client_side = Some(peer_id);
+                        }
+                    }
+                }
+                if let (Some((peer_id, connection_id)), Some(server_peer)) =
+                    (server_side, client_side)
+                {
+                    return (peer_id, connection_id, server_peer);
+                }
+            }
+        })
+        .await
+    }
+
+    /// 等待服务端的连接关闭
+    async fn closed(&mut self) {
+        within(async {
+            loop {
+                tokio::select! {
+                    Some(event) = self.server.next() => {
+                        if let swarm::server::SwarmEvent::ConnectionClosed { .. } = event {
+                            return;
+                        }
+                    }
+                    Some(_) = self.client.next() => {}
+                }
+            }
+        })
+        .await
+    }
+
+    async fn received(&mut self, count: usize) -> Vec<String> {
+        let mut texts = Vec::new();
+        while texts.len() < count {
+            if let client::Event::Message { message, .. } = self.client_event().await {
+                texts.push(message.text);
+            }
+        }
+        texts
+    }
+}
+
+async fn within<T>(future: impl Future<Output = T>) -> T {
+    tokio::time::timeout(Duration::from_secs(5), future)
+        .await
+        .expect("timed out waiting for event")
+}
+
+#[tokio::test]
+async fn messages_before_bind_are_delivered_after_bind() {
+    let mut net = Net::new(server::Behavior::new());
+    net.dial();
+    let (peer_id, connection_id, server_peer) = net.opened().await;
+
+    for text in ["first", "second"] {
+        assert!(net.client.behavior_mut().push.send(server_peer, note(text)));
+    }
+    // 未绑定的连接不产生消息事件
+    let idle = tokio::time::timeout(Duration::from_millis(200), net.server_event()).await;
+    assert!(idle.is_err(), "unexpected event: {idle:?}");
+
+    let session_id = SessionId::generate();
+    net.server
+        .behavior_mut()
+        .push
+        .bind(session_id.clone(), peer_id, connection_id);
+    for expected in ["first", "second"] {
+        match net.server_event().await {
+            server::Event::Message {
+                session_id: bound,
+                message,
+                ..
+            } => {
+                assert_eq!(bound, session_id);
+                assert_eq!(message.text, expected);
+            }
+            event => panic!("unexpected event: {event:?}"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn resume_replays_messages_sent_while_detached() {
+    let behavior = server::Behavior::new().with_replay(Duration::from_secs(30), 16);
+    let mut net = Net::new(behavior);
+    net.dial();
+    let (peer_id, connection_id, _) = net.opened().await;
+    let session_id = SessionId::generate();
+    net.server
+        .behavior_mut()
+        .push
+        .bind(session_id.clone(), peer_id, connection_id);
+    for text in ["1", "2"] {
+        assert!(net.server.behavior_mut().push.send(&session_id, note(text)));
+    }
+    assert_eq!(net.received(2).await, ["1", "2"]);
+
+    assert!(net.server.close_connection(connection_id));
+    net.closed().await;
+    // 断开期间的消息进入重放缓存
+    for text in ["3", "4"] {
+        assert!(net.server.behavior_mut().push.send(&session_id, note(text)));
+    }
+
+    net.dial();
+    let (peer_id, connection_id, _) = net.opened().await;
+    let replayed =
+        net.server
+            .behavior_mut()
+            .push
+            .resume(session_id.clone(), peer_id, connection_id, 2);
+    assert_eq!(replayed, Some(2));
+    assert_eq!(net.received(2).await, ["3", "4"]);
+    assert_eq!(net.server.behavior().push.last_seq(&session_id), Some(4));
+}
@@ -0,0 +1,460 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt, io,
+    pin::Pin,
+    task::{Context, Poll, ready},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use futures::{
+    Stream, StreamExt,
+    channel::{mpsc, oneshot},
+    future::{self, Either},
+};
+use futures_timer::Delay;
+use vela_protobuf::common;
+use volans::{
+    core::{PeerId, Url},
+    request::{Config, OutboundFailure, RequestId, client, codec::ProtobufCodec},
+    swarm::{
+        BehaviorEvent, ConnectionDenied, ConnectionId, DialOpts, NetworkBehavior,
+        NetworkOutgoingBehavior, StreamProtocol, THandlerAction, THandlerEvent,
+        error::{ConnectionError, DialError},
+    },
+};
+
+use crate::{Invoke, Request, Response};
+
+type RawCodec = ProtobufCodec<common::Request, common::Response>;
+
+pub type Handler = client::Handler<RawCodec>;
+
+type ResponseSender = oneshot::Sender<Result<common::Response, OutboundFailure>>;
+
+struct Command {
+    peer_id: PeerId,
+    request: common::Request,
+    sender: ResponseSender,
+}
+
+/// 请求句柄
+///
+/// 可克隆并在任意任务中使用，请求由 swarm 中的 [`Behavior`] 发送。
+/// 句柄本身不驱动 swarm，需要将 [`drive`] 或 [`Behavior::build`] 返回的 [`Driver`]
+/// 交给执行器运行，否则请求只会一直等待直到超时。
+#[derive(Debug, Clone)]
+pub struct Handle {
+    sender: mpsc::UnboundedSender<Command>,
+    timeout: Option<Duration>,
+}
+
+impl Handle {
+    /// 默认请求超时时间，未设置截止时间的请求将使用该值
+    ///
+    /// 截止时间既随请求发送给对端，也用于本地等待，
+    /// 到期未收到响应时返回 [`OutboundFailure::Timeout`]。
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// 调用服务并等待响应
+    pub async fn call<TRequest, TResponse>(
+        &self,
+        peer_id: PeerId,
+        service: impl Into<String>,
+        payload: TRequest,
+    ) -> Result<Response<TResponse>, OutboundFailure>
+    where
+        TRequest: prost::Message,
+        TResponse: prost::Message + Default,
+    {
+        self.send(peer_id, Request::new(service.into(), payload))
+            .await
+    }
+
+    /// 发送带元数据的请求并等待响应
+    pub async fn send<TRequest, TResponse>(
+        &self,
+        peer_id: PeerId,
+        mut request: Request<TRequest>,
+    ) -> Result<Response<TResponse>, OutboundFailure>
+    where
+        TRequest: prost::Message,
+        TResponse: prost::Message + Default,
+    {
+        if let Some(timeout) = self.timeout
+            && request.deadline().is_none()
+        {
+            request.set_timeout(timeout);
+        }
+        let remaining = request.remaining();
+        let (sender, receiver) = oneshot::channel();
+        self.sender
+            .unbounded_send(Command {
+                peer_id,
+                request: request.encode(),
+                sender,
+            })
+            .map_err(|_| closed())?;
+        // 对端忽略截止时间或丢弃请求时不会一直等待
+        let result = match remaining {
+            Some(remaining) => match future::select(receiver, Delay::new(remaining)).await {
+                Either::Left((result, _)) => result,
+                Either::Right(_) => return Err(OutboundFailure::Timeout),
+            },
+            None => receiver.await,
+        };
+        let response = result.map_err(|_| closed())??;
+        Response::decode(response).map_err(|e| OutboundFailure::Io(e.into()))
+    }
+}
+
+/// 在后台持续驱动 swarm，丢弃 swarm 产生的事件
+///
+/// 仅通过 [`Handle`] 发起请求时，将返回的 [`Driver`] 交给执行器运行，
+/// 如 `tokio::spawn(caller::drive(swarm))`。需要处理事件时请自行驱动 swarm。
+pub fn drive<S>(swarm: S) -> Driver<S>
+where
+    S: Stream + Unpin,
+    S::Item: fmt::Debug,
+{
+    Driver { swarm }
+}
+
+/// 驱动 swarm 的 future，swarm 结束时完成
+///
+/// 必须交给执行器运行，如 `tokio::spawn(driver)`，丢弃后句柄发起的请求不会再被发送。
+#[must_use = "requests made through the handle are only sent while the driver is polled, spawn it"]
+pub struct Driver<S> {
+    swarm: S,
+}
+
+impl<S> Future for Driver<S>
+where
+    S: Stream + Unpin,
+    S::Item: fmt::Debug,
+{
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = self.get_mut();
+        while let Some(event) = ready!(this.swarm.poll_next_unpin(cx)) {
+            tracing::trace!("Swarm event: {:?}", event);
+        }
+        Poll::Ready(())
+    }
+}
+
+fn closed() -> OutboundFailure {
+    OutboundFailure::Io(io::Error::new(
+        io::ErrorKind::BrokenPipe,
+        "Request behavior has been dropped",
+    ))
+}
+
+#[async_trait]
+impl Invoke for Handle {
+    async fn invoke<TRequest, TResponse>(
+        &self,
+        peer_id: PeerId,
+        request: Request<TRequest>,
+    ) -> Result<Response<TResponse>, OutboundFailure>
+    where
+        TRequest: prost::Message + Default + Send + 'static,
+        TResponse: prost::Message + Default + Send + 'static,
+    {
+        self.send(peer_id, request).await
+    }
+}
+
+/// 基于句柄的请求客户端
+///
+/// 通过 [`Handle`] 发起的请求在这里发送，响应交还给等待的调用方。
+///
+/// volans-request 0.1.1 为所有请求分配相同的 `RequestId`，同一连接上并发的请求
+/// 会被拒绝，响应也无法区分，因此每个节点同时只发送一个请求，其余请求按顺序排队，
+/// 响应按节点交还。节点的最后一个连接关闭时，未完成的请求以
+/// [`OutboundFailure::ConnectionClosed`] 失败。
+pub struct Behavior {
+    protocol: StreamProtocol,
+    inner: client::Behavior<RawCodec>,
+    receiver: mpsc::UnboundedReceiver<Command>,
+    in_flight: HashMap<PeerId, ResponseSender>,
+    queued: HashMap<PeerId, VecDeque<Command>>,
+    connections: HashMap<PeerId, usize>,
+    pending_event: VecDeque<Event>,
+}
+
+impl Behavior {
+    pub fn new(protocol: StreamProtocol, config: Config) -> (Self, Handle) {
+        let (sender, receiver) = mpsc::unbounded();
+        let behavior = Self {
+            protocol,
+            inner: client::Behavior::with_codec(RawCodec::new(), config),
+            receiver,
+            in_flight: HashMap::new(),
+            queued: HashMap::new(),
+            connections: HashMap::new(),
+            pending_event: VecDeque::new(),
+        };
+        (
+            behavior,
+            Handle {
+                sender,
+                timeout: None,
+            },
+        )
+    }
+
+    /// 创建客户端并交给 `swarm` 构造 swarm，返回句柄与驱动 swarm 的 [`Driver`]
+    ///
+    /// 返回的 [`Driver`] 必须交给执行器运行，如 `tokio::spawn(driver)`。
+    pub fn build<S, F>(protocol: StreamProtocol, config: Config, swarm: F) -> (Handle, Driver<S>)
+    where
+        F: FnOnce(Self) -> S,
+        S: Stream + Unpin,
+        S::Item: fmt::Debug,
+    {
+        let (behavior, handle) = Self::new(protocol, config);
+        (handle, drive(swarm(behavior)))
+    }
+
+    fn on_command(&mut self, command: Command) {
+        if command.sender.is_canceled() {
+            return;
+        }
+        if self.in_flight.contains_key(&command.peer_id) {
+            self.queued
+                .entry(command.peer_id)
+                .or_default()
+                .push_back(command);
+            return;
+        }
+        self.inner
+            .send_request(command.peer_id, self.protocol.clone(), command.request);
+        self.in_flight.insert(command.peer_id, command.sender);
+    }
+
+    fn on_response(
+        &mut self,
+        peer_id: PeerId,
+        request_id: RequestId,
+        result: Result<common::Response, OutboundFailure>,
+    ) {
+        let Some(sender) = self.in_flight.remove(&peer_id) else {
+            tracing::warn!("Response from {} without a pending request", peer_id);
+            return;
+        };
+        if sender.send(result).is_err() {
+            self.pending_event.push_back(Event::Canceled {
+                peer_id,
+                request_id,
+            });
+        }
+        self.send_queued(peer_id);
+    }
+
+    /// 连接关闭时底层不会报告其中未完成的请求，节点没有其他连接时主动使其失败
+    fn on_peer_disconnected(&mut self, peer_id: PeerId) {
+        if let Some(sender) = self.in_flight.remove(&peer_id) {
+            let _ = sender.send(Err(OutboundFailure::ConnectionClosed));
+        }
+        self.send_queued(peer_id);
+    }
+
+    /// 发送该节点排队中的下一个请求，跳过调用方已放弃的请求
+    fn send_queued(&mut self, peer_id: PeerId) {
+        let Some(queue) = self.queued.get_mut(&peer_id) else {
+            return;
+        };
+        while let Some(command) = queue.pop_front() {
+            if command.sender.is_canceled() {
+                continue;
+            }
+            self.inner
+                .send_request(peer_id, self.protocol.clone(), command.request);
+            self.in_flight.insert(peer_id, command.sender);
+            break;
+        }
+        if queue.is_empty() {
+            self.queued.remove(&peer_id);
+        }
+    }
+}
+
+impl NetworkBehavior for Behavior {
+    type Event = Event;
+    type ConnectionHandler = Handler;
+
+    fn on_connection_handler_event(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        event: THandlerEvent<Self>,
+    ) {
+        self.inner.on_connection_handler_event(id, peer_id, event);
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<BehaviorEvent<Self::Event, THandlerAction<Self>>> {
+        while let Poll::Ready(Some(command)) = self.receiver.poll_next_unpin(cx) {
+            self.on_command(command);
+        }
+        loop {
+            if let Some(event) = self.pending_event.pop_front() {
+                return Poll::Ready(BehaviorEvent::Behavior(event));
+            }
+
+            match self.inner.poll(cx) {
+                Poll::Ready(BehaviorEvent::Behavior(client::Event::Response {
+                    peer_id,
+                    request_id,
+                    response,
+                    ..
+                })) => {
+                    self.on_response(peer_id, request_id, Ok(response));
+                    continue;
+                }
+                Poll::Ready(BehaviorEvent::Behavior(client::Event::Failure {
+                    peer_id,
+                    request_id,
+                    cause,
+                    ..
+                })) => {
+                    self.on_response(peer_id, request_id, Err(cause));
+                    continue;
+                }
+                Poll::Ready(BehaviorEvent::HandlerAction {
+                    peer_id,
+                    handler,
+                    action,
+                }) => {
+                    return Poll::Ready(BehaviorEvent::HandlerAction {
+                        peer_id,
+                        handler,
+                        action,
+                    });
+                }
+                Poll::Ready(BehaviorEvent::CloseConnection {
+                    peer_id,
+                    connection,
+                }) => {
+                    return Poll::Ready(BehaviorEvent::CloseConnection {
+                        peer_id,
+                        connection,
+                    });
+                }
+                Poll::Pending => {}
+                _ => unreachable!("Unexpected event"),
+            }
+            return Poll::Pending;
+        }
+    }
+}
+
+impl NetworkOutgoingBehavior for Behavior {
+    fn handle_established_connection(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        addr: &Url,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        self.inner.handle_established_connection(id, peer_id, addr)
+    }
+
+    fn on_connection_established(&mut self, id: ConnectionId, peer_id: PeerId, addr: &Url) {
+        *self.connections.entry(peer_id).or_default() += 1;
+        self.inner.on_connection_established(id, peer_id, addr);
+    }
+
+    fn on_connection_closed(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        addr: &Url,
+        reason: Option<&ConnectionError>,
+    ) {
+        self.inner.on_connection_closed(id, peer_id, addr, reason);
+        if let Some(count) = self.connections.get_mut(&peer_id) {
+            *count -= 1;
+            if *count == 0 {
+                self.connections.remove(&peer_id);
+                self.on_peer_disconnected(peer_id);
+            }
+        }
+    }
+
+    fn on_dial_failure(
+        &mut self,
+        id: ConnectionId,
+        peer_id: Option<PeerId>,
+        addr: Option<&Url>,
+        error: &DialError,
+    ) {
+        self.inner.on_dial_failure(id, peer_id, addr, error);
+    }
+
+    fn poll_dial(&mut self, cx: &mut Context<'_>) -> Poll<DialOpts> {
+        self.inner.poll_dial(cx)
+    }
+}
+
+#[derive(Debug)]
+pub enum Event {
+    /// 调用方已放弃等待，响应被丢弃
+    Canceled {
+        peer_id: PeerId,
+        request_id: RequestId,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+
+    use super::*;
+
+    #[test]
+    fn call_times_out_when_no_response_arrives() {
+        // 不驱动 Behavior，请求永远不会得到响应
+        let (_behavior, handle) = Behavior::new(StreamProtocol::new("/v1/test"), Config::default());
+        let handle = handle.with_timeout(Duration::from_millis(20));
+
+        let result: Result<Response<common::Status>, _> = block_on(handle.call(
+            PeerId::from_bytes([0; 32]),
+            "test.service",
+            common::Status::default(),
+        ));
+
+        assert!(matches!(result, Err(OutboundFailure::Timeout)));
+    }
+
+    #[test]
+    fn call_fails_when_behavior_is_dropped() {
+        let (behavior, handle) = Behavior::new(StreamProtocol::new("/v1/test"), Config::default());
+        drop(behavior);
+
+        let result: Result<Response<common::Status>, _> = block_on(handle.call(
+            PeerId::from_bytes([0; 32]),
+            "test.service",
+            common::Status::default(),
+        ));
+
+        assert!(matches!(result, Err(OutboundFailure::Io(_))));
+    }
+
+    #[test]
+    fn driver_runs_until_swarm_ends() {
+        let (_handle, driver) = Behavior::build(
+            StreamProtocol::new("/v1/test"),
+            Config::default(),
+            |_behavior| futures::stream::iter([1, 2, 3]),
+        );
+
+        block_on(driver);
+    }
+}
@@ -0,0 +1,20 @@
+use vela_protobuf::common;
+
+use crate::router::RequestContext;
+
+/// 请求守卫
+///
+/// 请求交给处理器之前检查来源连接，返回错误时直接以该状态响应请求，
+/// 如拒绝尚未认证的连接。
+pub trait Guard: Send + Sync + 'static {
+    fn check(&self, context: &RequestContext) -> Result<(), common::Status>;
+}
+
+impl<F> Guard for F
+where
+    F: Fn(&RequestContext) -> Result<(), common::Status> + Send + Sync + 'static,
+{
+    fn check(&self, context: &RequestContext) -> Result<(), common::Status> {
+        self(context)
+    }
+}
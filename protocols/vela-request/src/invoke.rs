@@ -0,0 +1,29 @@
+use async_trait::async_trait;
+use vela_protobuf::common;
+use volans::core::PeerId;
+
+use crate::{OutboundFailure, Request, Response};
+
+/// 调用失败
+#[derive(Debug, thiserror::Error)]
+pub enum CallError {
+    #[error("Request failed: {0}")]
+    Failure(#[from] OutboundFailure),
+    #[error("Request returned status {0}")]
+    Status(common::Status),
+}
+
+/// 发起请求并等待响应
+///
+/// 由生成的客户端代码使用，`request.service()` 为目标服务名。
+#[async_trait]
+pub trait Invoke: Send + Sync {
+    async fn invoke<TRequest, TResponse>(
+        &self,
+        peer_id: PeerId,
+        request: Request<TRequest>,
+    ) -> Result<Response<TResponse>, OutboundFailure>
+    where
+        TRequest: prost::Message + Default + Send + 'static,
+        TResponse: prost::Message + Default + Send + 'static;
+}
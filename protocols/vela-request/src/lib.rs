@@ -1,19 +1,38 @@
+pub mod caller;
 pub mod client;
+mod guard;
+mod invoke;
+pub mod router;
 pub mod server;
 
-use std::io;
+use std::{
+    io,
+    time::{Duration, Instant},
+};
 
-use async_trait::async_trait;
 use futures::{AsyncRead, AsyncWrite};
 use vela_protobuf::common;
 use volans::{request, swarm::StreamProtocol};
 
-pub use volans::request::{Config, InboundFailure, OutboundFailure, RequestId};
+pub use async_trait::async_trait;
+pub use guard::Guard;
+pub use invoke::{CallError, Invoke};
+pub use volans::{
+    core::PeerId,
+    request::{Config, InboundFailure, OutboundFailure, RequestId},
+};
+
+/// 请求截止时间的元数据键，值为剩余的毫秒数
+pub const DEADLINE_METADATA: &str = "x-deadline";
+
+/// 服务端接受的最长剩余时间，更长的截止时间按此截断
+pub const MAX_DEADLINE: Duration = Duration::from_secs(24 * 60 * 60);
 
 #[derive(Debug)]
 pub struct Request<B> {
     service: String,
     metadata: Vec<common::Metadata>,
+    deadline: Option<Instant>,
     payload: B,
 }
 
@@ -22,10 +41,37 @@ impl<B> Request<B> {
         Self {
             service,
             metadata: Vec::new(),
+            deadline: None,
             payload,
         }
     }
 
+    /// 设置请求超时时间，发送时以 `x-deadline` 元数据传递给服务端
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.set_timeout(timeout);
+        self
+    }
+
+    /// 超时时间超出 [`Instant`] 的范围时视为没有截止时间
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.deadline = Instant::now().checked_add(timeout);
+    }
+
+    /// 设置截止时间，如继承上游请求的 [`Request::deadline`]
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// 距离截止时间的剩余时间，已超时返回零
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
     pub fn service(&self) -> &str {
         &self.service
     }
@@ -58,6 +104,47 @@ impl<B> Request<B> {
     }
 }
 
+impl<B: prost::Message> Request<B> {
+    /// 设置了截止时间时以其替换 `x-deadline` 元数据，否则保留已有的元数据
+    pub(crate) fn encode(mut self) -> common::Request {
+        if let Some(remaining) = self.remaining() {
+            self.metadata.retain(|m| m.key != DEADLINE_METADATA);
+            self.metadata.push(common::Metadata {
+                key: DEADLINE_METADATA.to_string(),
+                value: remaining.as_millis().to_string(),
+            });
+        }
+        common::Request {
+            service: self.service,
+            metadata: self.metadata,
+            payload: self.payload.encode_to_vec(),
+        }
+    }
+}
+
+impl<B: prost::Message + Default> Request<B> {
+    pub(crate) fn decode(request: common::Request) -> Result<Self, prost::DecodeError> {
+        Ok(Request {
+            deadline: parse_deadline(&request.metadata),
+            payload: B::decode(request.payload.as_slice())?,
+            service: request.service,
+            metadata: request.metadata,
+        })
+    }
+}
+
+/// 从元数据中解析截止时间，剩余时间最长为 [`MAX_DEADLINE`]
+pub(crate) fn parse_deadline(metadata: &[common::Metadata]) -> Option<Instant> {
+    let value = metadata.iter().find(|m| m.key == DEADLINE_METADATA)?;
+    match value.value.parse::<u64>() {
+        Ok(millis) => Instant::now().checked_add(Duration::from_millis(millis).min(MAX_DEADLINE)),
+        Err(_) => {
+            tracing::debug!("Invalid {} metadata: {}", DEADLINE_METADATA, value.value);
+            None
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Response<B> {
     metadata: Vec<common::Metadata>,
@@ -86,6 +173,40 @@ impl<B> Response<B> {
     }
 }
 
+impl<B: prost::Message> Response<B> {
+    pub(crate) fn encode(self) -> common::Response {
+        match self.payload {
+            Ok(payload) => common::Response {
+                status: Some(common::Status::default()),
+                metadata: self.metadata,
+                payload: payload.encode_to_vec(),
+            },
+            Err(status) => common::Response {
+                status: Some(status),
+                metadata: self.metadata,
+                payload: Vec::new(), // Empty payload for error responses
+            },
+        }
+    }
+}
+
+impl<B: prost::Message + Default> Response<B> {
+    pub(crate) fn decode(response: common::Response) -> Result<Self, prost::DecodeError> {
+        let status = response.status.unwrap_or_default();
+        if status.code == common::Code::Ok as i32 {
+            Ok(Response {
+                metadata: response.metadata,
+                payload: Ok(B::decode(response.payload.as_slice())?),
+            })
+        } else {
+            Ok(Response {
+                metadata: response.metadata,
+                payload: Err(status),
+            })
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Codec<TInput, TOutput> {
     inner: request::codec::ProtobufCodec<common::Request, common::Response>,
@@ -105,6 +226,16 @@ where
     }
 }
 
+impl<TInput, TOutput> Default for Codec<TInput, TOutput>
+where
+    TInput: prost::Message + Default + Send,
+    TOutput: prost::Message + Default + Send,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[async_trait]
 impl<TInput, TOutput> request::Codec for Codec<TInput, TOutput>
 where
@@ -124,11 +255,7 @@ where
         T: AsyncRead + Unpin + Send,
     {
         let common_request = self.inner.read_request(protocol, io).await?;
-        Ok(Request {
-            service: common_request.service,
-            metadata: common_request.metadata,
-            payload: TInput::decode(common_request.payload.as_slice())?,
-        })
+        Ok(Request::decode(common_request)?)
     }
     async fn read_response<T>(
         &mut self,
@@ -139,18 +266,7 @@ where
         T: AsyncRead + Unpin + Send,
     {
         let common_response = self.inner.read_response(protocol, io).await?;
-        let status = common_response.status.unwrap_or_default();
-        if status.code == common::Code::Ok as i32 {
-            return Ok(Response {
-                metadata: common_response.metadata,
-                payload: Ok(TOutput::decode(common_response.payload.as_slice())?),
-            });
-        } else {
-            return Ok(Response {
-                metadata: common_response.metadata,
-                payload: Err(status),
-            });
-        }
+        Ok(Response::decode(common_response)?)
     }
     async fn write_request<T>(
         &mut self,
@@ -161,12 +277,9 @@ where
     where
         T: AsyncWrite + Unpin + Send,
     {
-        let common_request = common::Request {
-            service: request.service,
-            metadata: request.metadata,
-            payload: request.payload.encode_to_vec(),
-        };
-        self.inner.write_request(protocol, io, common_request).await
+        self.inner
+            .write_request(protocol, io, request.encode())
+            .await
     }
     async fn write_response<T>(
         &mut self,
@@ -177,28 +290,9 @@ where
     where
         T: AsyncWrite + Unpin + Send,
     {
-        match response.payload {
-            Ok(payload) => {
-                let common_response = common::Response {
-                    status: Some(common::Status::default()),
-                    metadata: response.metadata,
-                    payload: payload.encode_to_vec(),
-                };
-                self.inner
-                    .write_response(protocol, io, common_response)
-                    .await
-            }
-            Err(status) => {
-                let common_response = common::Response {
-                    status: Some(status),
-                    metadata: response.metadata,
-                    payload: Vec::new(), // Empty payload for error responses
-                };
-                self.inner
-                    .write_response(protocol, io, common_response)
-                    .await
-            }
-        }
+        self.inner
+            .write_response(protocol, io, response.encode())
+            .await
     }
 }
 
@@ -257,3 +351,65 @@ impl<TResponse> Responder<TResponse> {
         self.send_response(Err(status))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deadlines(request: &common::Request) -> Vec<&str> {
+        request
+            .metadata
+            .iter()
+            .filter(|m| m.key == DEADLINE_METADATA)
+            .map(|m| m.value.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn encode_keeps_manual_deadline_metadata() {
+        let mut request = Request::new("test".to_string(), common::Status::default());
+        request.add_metadata(DEADLINE_METADATA.to_string(), "1500".to_string());
+
+        assert_eq!(deadlines(&request.encode()), ["1500"]);
+    }
+
+    #[test]
+    fn encode_replaces_deadline_metadata_when_deadline_is_set() {
+        let mut request = Request::new("test".to_string(), common::Status::default())
+            .with_timeout(Duration::from_secs(60));
+        request.add_metadata(DEADLINE_METADATA.to_string(), "1500".to_string());
+
+        let encoded = request.encode();
+        let values = deadlines(&encoded);
+        assert_eq!(values.len(), 1);
+        let millis: u64 = values[0].parse().unwrap();
+        assert!(millis > 59_000 && millis <= 60_000);
+    }
+
+    #[test]
+    fn decode_parses_deadline_metadata() {
+        let request = Request::new("test".to_string(), common::Status::default())
+            .with_timeout(Duration::from_secs(5));
+        let decoded = Request::<common::Status>::decode(request.encode()).unwrap();
+
+        let remaining = decoded.remaining().unwrap();
+        assert!(remaining > Duration::from_secs(4) && remaining <= Duration::from_secs(5));
+    }
+
+    #[test]
+    fn long_deadline_is_clamped() {
+        let metadata = [common::Metadata {
+            key: DEADLINE_METADATA.to_string(),
+            value: u64::MAX.to_string(),
+        }];
+        let deadline = parse_deadline(&metadata).unwrap();
+        assert!(deadline.saturating_duration_since(Instant::now()) <= MAX_DEADLINE);
+    }
+
+    #[test]
+    fn overflowing_timeout_sets_no_deadline() {
+        let request =
+            Request::new("test".to_string(), common::Status::default()).with_timeout(Duration::MAX);
+        assert_eq!(request.deadline(), None);
+    }
+}
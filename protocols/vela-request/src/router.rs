@@ -0,0 +1,517 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use futures::{
+    FutureExt,
+    future::{self, BoxFuture, Either},
+};
+use futures_bounded::FuturesMap;
+use futures_timer::Delay;
+use vela_protobuf::common::{self, Code};
+use volans::{
+    core::{PeerId, Url},
+    request::{self, Config, InboundFailure, RequestId, codec::ProtobufCodec, server},
+    swarm::{
+        BehaviorEvent, ConnectionDenied, ConnectionId, ListenerEvent, NetworkBehavior,
+        NetworkIncomingBehavior, StreamProtocol, THandlerAction, THandlerEvent,
+        error::{ConnectionError, ListenError},
+    },
+};
+
+use crate::{Guard, Request, Response, parse_deadline};
+
+type RawCodec = ProtobufCodec<common::Request, common::Response>;
+
+/// volans-request 0.1.1 为所有请求分配相同的 `RequestId`，按连接区分不同连接上的请求
+type RequestKey = (ConnectionId, RequestId);
+
+pub type Handler = server::Handler<RawCodec>;
+
+type Route = Arc<
+    dyn Fn(RequestContext, common::Request) -> BoxFuture<'static, common::Response> + Send + Sync,
+>;
+
+/// 处理请求的最长时间
+const HANDLER_TIMEOUT: Duration = Duration::from_secs(30);
+/// 同时处理的最大请求数量
+const MAX_CONCURRENT_REQUESTS: usize = 1000;
+
+/// 请求来源
+#[derive(Debug, Clone)]
+pub struct RequestContext {
+    pub peer_id: PeerId,
+    pub connection_id: ConnectionId,
+    pub request_id: RequestId,
+}
+
+/// 处理器返回值，可以直接返回结果，也可以返回带元数据的 [`Response`]
+pub trait IntoResponse<T> {
+    fn into_response(self) -> Response<T>;
+}
+
+impl<T> IntoResponse<T> for Result<T, common::Status> {
+    fn into_response(self) -> Response<T> {
+        Response::new(Vec::new(), self)
+    }
+}
+
+impl<T> IntoResponse<T> for Response<T> {
+    fn into_response(self) -> Response<T> {
+        self
+    }
+}
+
+/// 服务路由
+///
+/// 按 `Request::service` 将请求分发到对应的处理器，每个处理器有各自的请求与响应类型。
+#[derive(Clone, Default)]
+pub struct Router {
+    routes: HashMap<String, Route>,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 注册服务处理器，如 `vela.table.sit_down`，重复注册会覆盖之前的处理器
+    pub fn route<TRequest, TResponse, F, Fut, R>(
+        mut self,
+        service: impl Into<String>,
+        handler: F,
+    ) -> Self
+    where
+        TRequest: prost::Message + Default + Send + 'static,
+        TResponse: prost::Message + 'static,
+        F: Fn(RequestContext, Request<TRequest>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = R> + Send + 'static,
+        R: IntoResponse<TResponse>,
+    {
+        let route: Route = Arc::new(move |context, request: common::Request| {
+            let request = match Request::<TRequest>::decode(request) {
+                Ok(request) => request,
+                Err(e) => {
+                    let status = common::Status::new(
+                        Code::InvalidArgument,
+                        format!("Failed to decode request: {}", e),
+                    );
+                    return futures::future::ready(error_response(status)).boxed();
+                }
+            };
+            handler(context, request)
+                .map(|r| r.into_response().encode())
+                .boxed()
+        });
+        self.routes.insert(service.into(), route);
+        self
+    }
+
+    pub fn contains(&self, service: &str) -> bool {
+        self.routes.contains_key(service)
+    }
+
+    /// 已注册的服务名
+    pub fn services(&self) -> impl Iterator<Item = &str> {
+        self.routes.keys().map(String::as_str)
+    }
+
+    fn call(
+        &self,
+        context: RequestContext,
+        request: common::Request,
+    ) -> BoxFuture<'static, common::Response> {
+        let Some(route) = self.routes.get(&request.service) else {
+            let status = common::Status::new(
+                Code::Unimplemented,
+                format!("Service {} not found", request.service),
+            );
+            return futures::future::ready(error_response(status)).boxed();
+        };
+        // 按调用方截止时间取消处理器
+        let Some(deadline) = parse_deadline(&request.metadata) else {
+            return route(context, request);
+        };
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return futures::future::ready(deadline_exceeded()).boxed();
+        }
+        let fut = route(context, request);
+        async move {
+            match future::select(fut, Delay::new(remaining)).await {
+                Either::Left((response, _)) => response,
+                Either::Right(_) => deadline_exceeded(),
+            }
+        }
+        .boxed()
+    }
+}
+
+impl fmt::Debug for Router {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Router")
+            .field("services", &self.routes.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+fn deadline_exceeded() -> common::Response {
+    error_response(common::Status::new(
+        Code::DeadlineExceeded,
+        "Deadline exceeded".to_string(),
+    ))
+}
+
+/// 处理器超过服务端的处理时间限制，与调用方截止时间无关
+fn handler_timeout() -> common::Response {
+    error_response(common::Status::new(
+        Code::Unavailable,
+        "Handler timed out".to_string(),
+    ))
+}
+
+fn error_response(status: common::Status) -> common::Response {
+    common::Response {
+        status: Some(status),
+        metadata: Vec::new(),
+        payload: Vec::new(),
+    }
+}
+
+struct PendingResponse {
+    context: RequestContext,
+    service: String,
+    responder: request::Responder<common::Response>,
+}
+
+/// 路由服务端
+///
+/// 接收原始 `common::Request`，交由 [`Router`] 分发处理后返回响应。
+pub struct Behavior {
+    router: Router,
+    guard: Option<Arc<dyn Guard>>,
+    inner: server::Behavior<RawCodec>,
+    pending_response: HashMap<RequestKey, PendingResponse>,
+    handling: FuturesMap<RequestKey, common::Response>,
+    pending_event: VecDeque<Event>,
+}
+
+impl Behavior {
+    pub fn new<P>(router: Router, protocols: P, config: Config) -> Self
+    where
+        P: IntoIterator<Item = StreamProtocol>,
+    {
+        Self {
+            router,
+            guard: None,
+            inner: server::Behavior::with_codec(RawCodec::new(), protocols, config),
+            pending_response: HashMap::new(),
+            handling: FuturesMap::new(
+                || futures_bounded::Delay::futures_timer(HANDLER_TIMEOUT),
+                MAX_CONCURRENT_REQUESTS,
+            ),
+            pending_event: VecDeque::new(),
+        }
+    }
+
+    /// 设置处理器超时时间及最大并发数量，默认为 30 秒与 1000
+    ///
+    /// 处理器超时以 [`Code::Unavailable`] 响应，以区别于调用方截止时间到期时的
+    /// [`Code::DeadlineExceeded`]。
+    pub fn with_handler_limits(mut self, timeout: Duration, capacity: usize) -> Self {
+        self.handling = FuturesMap::new(
+            move || futures_bounded::Delay::futures_timer(timeout),
+            capacity,
+        );
+        self
+    }
+
+    /// 被守卫拒绝的请求直接以守卫返回的状态响应
+    pub fn with_guard(mut self, guard: impl Guard) -> Self {
+        self.guard = Some(Arc::new(guard));
+        self
+    }
+
+    pub fn router(&self) -> &Router {
+        &self.router
+    }
+
+    fn on_request(
+        &mut self,
+        context: RequestContext,
+        request: common::Request,
+        responder: request::Responder<common::Response>,
+    ) {
+        let key = (context.connection_id, context.request_id);
+        let service = request.service.clone();
+        if let Some(Err(status)) = self.guard.as_ref().map(|g| g.check(&context)) {
+            tracing::debug!(
+                "Request {} from {} rejected: {}",
+                service,
+                context.peer_id,
+                status
+            );
+            self.respond(
+                PendingResponse {
+                    context,
+                    service,
+                    responder,
+                },
+                error_response(status),
+            );
+            return;
+        }
+        let fut = self.router.call(context.clone(), request);
+        if self.handling.try_push(key, fut).is_err() {
+            tracing::warn!(
+                "Too many requests, rejecting {} from {}",
+                service,
+                context.peer_id
+            );
+            let status = common::Status::new(
+                Code::ResourceExhausted,
+                "Too many concurrent requests".to_string(),
+            );
+            self.respond(
+                PendingResponse {
+                    context,
+                    service,
+                    responder,
+                },
+                error_response(status),
+            );
+            return;
+        }
+        self.pending_response.insert(
+            key,
+            PendingResponse {
+                context,
+                service,
+                responder,
+            },
+        );
+    }
+
+    fn on_handled(&mut self, key: RequestKey, response: common::Response) {
+        if let Some(pending) = self.pending_response.remove(&key) {
+            self.respond(pending, response);
+        } else {
+            tracing::warn!("Request {} not found in pending response", key.1);
+        }
+    }
+
+    fn respond(&mut self, pending: PendingResponse, response: common::Response) {
+        let code = response
+            .status
+            .as_ref()
+            .map(common::Status::code_enum)
+            .unwrap_or(Code::Unknown);
+        let PendingResponse {
+            context,
+            service,
+            responder,
+        } = pending;
+        if responder.send_response(response).is_err() {
+            tracing::debug!(
+                "Response of {} to {} dropped, connection closed",
+                service,
+                context.peer_id
+            );
+        }
+        self.pending_event.push_back(Event::Handled {
+            peer_id: context.peer_id,
+            connection_id: context.connection_id,
+            request_id: context.request_id,
+            service,
+            code,
+        });
+    }
+
+    fn on_request_event(&mut self, event: server::Event<common::Request, common::Response>) {
+        match event {
+            server::Event::Request {
+                peer_id,
+                connection_id,
+                request_id,
+                request,
+                responder,
+            } => {
+                let context = RequestContext {
+                    peer_id,
+                    connection_id,
+                    request_id,
+                };
+                self.on_request(context, request, responder);
+            }
+            server::Event::Failure {
+                peer_id,
+                connection_id,
+                request_id,
+                cause,
+            } => {
+                self.pending_event.push_back(Event::Failure {
+                    peer_id,
+                    connection_id,
+                    request_id,
+                    cause,
+                });
+            }
+            server::Event::ResponseSent {
+                peer_id,
+                connection_id,
+                request_id,
+            } => {
+                self.pending_event.push_back(Event::ResponseSent {
+                    peer_id,
+                    connection_id,
+                    request_id,
+                });
+            }
+        }
+    }
+}
+
+impl NetworkBehavior for Behavior {
+    type Event = Event;
+    type ConnectionHandler = Handler;
+
+    fn on_connection_handler_event(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        event: THandlerEvent<Self>,
+    ) {
+        self.inner.on_connection_handler_event(id, peer_id, event);
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<BehaviorEvent<Self::Event, THandlerAction<Self>>> {
+        loop {
+            while let Poll::Ready((key, result)) = self.handling.poll_unpin(cx) {
+                let response = result.unwrap_or_else(|_| {
+                    tracing::warn!("Handler for request {} timed out", key.1);
+                    handler_timeout()
+                });
+                self.on_handled(key, response);
+            }
+            if let Some(event) = self.pending_event.pop_front() {
+                return Poll::Ready(BehaviorEvent::Behavior(event));
+            }
+
+            match self.inner.poll(cx) {
+                Poll::Ready(BehaviorEvent::Behavior(event)) => {
+                    self.on_request_event(event);
+                    continue;
+                }
+                Poll::Ready(BehaviorEvent::HandlerAction {
+                    peer_id,
+                    handler,
+                    action,
+                }) => {
+                    return Poll::Ready(BehaviorEvent::HandlerAction {
+                        peer_id,
+                        handler,
+                        action,
+                    });
+                }
+                Poll::Ready(BehaviorEvent::CloseConnection {
+                    peer_id,
+                    connection,
+                }) => {
+                    return Poll::Ready(BehaviorEvent::CloseConnection {
+                        peer_id,
+                        connection,
+                    });
+                }
+                Poll::Pending => {}
+                _ => unreachable!("Unexpected event"),
+            }
+            return Poll::Pending;
+        }
+    }
+}
+
+impl NetworkIncomingBehavior for Behavior {
+    /// 处理已建立的连接
+    fn handle_established_connection(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        local_addr: &Url,
+        remote_addr: &Url,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        self.inner
+            .handle_established_connection(id, peer_id, local_addr, remote_addr)
+    }
+
+    /// 连接处理器事件处理
+    fn on_connection_established(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        local_addr: &Url,
+        remote_addr: &Url,
+    ) {
+        self.inner
+            .on_connection_established(id, peer_id, local_addr, remote_addr);
+    }
+
+    fn on_connection_closed(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        local_addr: &Url,
+        remote_addr: &Url,
+        reason: Option<&ConnectionError>,
+    ) {
+        self.inner
+            .on_connection_closed(id, peer_id, local_addr, remote_addr, reason);
+    }
+
+    /// 监听失败事件处理
+    fn on_listen_failure(
+        &mut self,
+        id: ConnectionId,
+        peer_id: Option<PeerId>,
+        local_addr: &Url,
+        remote_addr: &Url,
+        error: &ListenError,
+    ) {
+        self.inner
+            .on_listen_failure(id, peer_id, local_addr, remote_addr, error);
+    }
+
+    /// 监听器事件处理
+    fn on_listener_event(&mut self, event: ListenerEvent<'_>) {
+        self.inner.on_listener_event(event);
+    }
+}
+
+#[derive(Debug)]
+pub enum Event {
+    /// 请求已处理，`code` 为响应状态码
+    Handled {
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        request_id: RequestId,
+        service: String,
+        code: Code,
+    },
+    Failure {
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        request_id: RequestId,
+        cause: InboundFailure,
+    },
+    ResponseSent {
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        request_id: RequestId,
+    },
+}
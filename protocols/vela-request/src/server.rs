@@ -1,5 +1,9 @@
-use std::task::{Context, Poll};
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+};
 
+use vela_protobuf::common::{Code, Status};
 use volans::{
     core::{PeerId, Url},
     request::{Config, InboundFailure, RequestId, server},
@@ -10,16 +14,23 @@ use volans::{
     },
 };
 
-use crate::{Codec, Request, Responder};
+use crate::{Codec, Guard, Request, Responder, router::RequestContext};
 
 pub type Handler<TRequest, TResponse> = server::Handler<Codec<TRequest, TResponse>>;
 
+/// 类型化的请求服务端
+///
+/// 到达时已超过截止时间的请求直接以 [`Code::DeadlineExceeded`] 响应，不产生事件。
+/// 其余请求的处理由调用方负责，服务端不会在截止时间到期时取消处理，
+/// 处理耗时较长时请检查 [`Request::remaining`]。需要自动取消时请使用
+/// [`router::Behavior`](crate::router::Behavior)。
 pub struct Behavior<TRequest, TResponse>
 where
     TRequest: prost::Message + Default + Send + Clone + 'static,
     TResponse: prost::Message + Default + Send + Clone + 'static,
 {
     inner: server::Behavior<Codec<TRequest, TResponse>>,
+    guard: Option<Arc<dyn Guard>>,
 }
 
 impl<TRequest, TResponse> Behavior<TRequest, TResponse>
@@ -34,8 +45,15 @@ where
         let codec = Codec::<TRequest, TResponse>::new();
         Self {
             inner: server::Behavior::with_codec(codec, protocols, config),
+            guard: None,
         }
     }
+
+    /// 被守卫拒绝的请求不会产生事件
+    pub fn with_guard(mut self, guard: impl Guard) -> Self {
+        self.guard = Some(Arc::new(guard));
+        self
+    }
 }
 
 impl<TRequest, TResponse> NetworkBehavior for Behavior<TRequest, TResponse>
@@ -59,8 +77,57 @@ where
         &mut self,
         cx: &mut Context<'_>,
     ) -> Poll<BehaviorEvent<Self::Event, THandlerAction<Self>>> {
-        self.inner.poll(cx).map(|e| {
-            e.map_event(|e| match e {
+        loop {
+            let event = match self.inner.poll(cx) {
+                Poll::Ready(BehaviorEvent::Behavior(server::Event::Request {
+                    peer_id,
+                    connection_id,
+                    request_id,
+                    request,
+                    responder,
+                })) => {
+                    let context = RequestContext {
+                        peer_id,
+                        connection_id,
+                        request_id,
+                    };
+                    if let Some(Err(status)) = self.guard.as_ref().map(|g| g.check(&context)) {
+                        tracing::debug!(
+                            "Request {} from {} rejected: {}",
+                            request.service(),
+                            peer_id,
+                            status
+                        );
+                        let _ = Responder::new(responder).err_response(status);
+                        continue;
+                    }
+                    if request
+                        .remaining()
+                        .is_some_and(|remaining| remaining.is_zero())
+                    {
+                        tracing::debug!(
+                            "Request {} from {} arrived after its deadline",
+                            request.service(),
+                            peer_id
+                        );
+                        let _ = Responder::new(responder).err_response(Status::new(
+                            Code::DeadlineExceeded,
+                            "Deadline exceeded".to_string(),
+                        ));
+                        continue;
+                    }
+                    BehaviorEvent::Behavior(server::Event::Request {
+                        peer_id,
+                        connection_id,
+                        request_id,
+                        request,
+                        responder,
+                    })
+                }
+                Poll::Ready(event) => event,
+                Poll::Pending => return Poll::Pending,
+            };
+            return Poll::Ready(event.map_event(|e| match e {
                 server::Event::Request {
                     peer_id,
                     connection_id,
@@ -94,8 +161,8 @@ where
                     connection_id,
                     request_id,
                 },
-            })
-        })
+            }));
+        }
     }
 }
 
@@ -0,0 +1,205 @@
+//! 经本地 TCP 连接调用路由服务端
+
+use std::{
+    pin::Pin,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use futures::StreamExt;
+use vela_protobuf::common::{Code, Status};
+use vela_request::{
+    Request,
+    caller::{self, Handle},
+    router::{self, RequestContext, Router},
+};
+use volans::{
+    Transport,
+    core::{PeerId, Url, muxing::StreamMuxerBox, transport::Boxed},
+    muxing, ping, plaintext,
+    request::Config,
+    swarm::{self, DialOpts, NetworkIncomingBehavior, NetworkOutgoingBehavior, StreamProtocol},
+    tcp,
+};
+
+const PROTOCOL: StreamProtocol = StreamProtocol::new("/v1/request");
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Default, Debug, Clone, Copy)]
+struct TokioExecutor;
+
+impl swarm::Executor for TokioExecutor {
+    fn exec(&self, future: Pin<Box<dyn Future<Output = ()> + Send>>) {
+        tokio::spawn(future);
+    }
+}
+
+fn build_transport() -> (PeerId, Boxed<(PeerId, StreamMuxerBox)>) {
+    let key: [u8; 32] = rand::random();
+    let signing_key = plaintext::ed25519::SigningKey::from_bytes(&key);
+    let transport = tcp::Config::new()
+        .upgrade()
+        .authenticate(plaintext::Config::new(signing_key.verifying_key()))
+        .multiplex(muxing::Config::new())
+        .boxed();
+    (PeerId::from_bytes(key), transport)
+}
+
+#[derive(NetworkIncomingBehavior)]
+struct ServerBehavior {
+    ping: ping::inbound::Behavior,
+    router: router::Behavior,
+}
+
+#[derive(NetworkOutgoingBehavior)]
+struct ClientBehavior {
+    ping: ping::outbound::Behavior,
+    caller: caller::Behavior,
+}
+
+/// 原样返回请求，`delay` 后响应
+fn echo(router: Router, delay: Duration) -> Router {
+    router.route("test.echo", move |_, request: Request<Status>| {
+        let status = request.into_payload();
+        async move {
+            tokio::time::sleep(delay).await;
+            Ok::<_, Status>(status)
+        }
+    })
+}
+
+fn spawn_server(router: router::Behavior) -> Url {
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .unwrap()
+        .port();
+    let addr = Url::parse(&format!("http://127.0.0.1:{port}")).unwrap();
+    let (peer_id, transport) = build_transport();
+    let mut server = swarm::server::Swarm::new(
+        transport,
+        ServerBehavior {
+            ping: ping::inbound::Behavior::default(),
+            router,
+        },
+        peer_id,
+        swarm::connection::PoolConfig::new(Box::new(TokioExecutor)),
+    );
+    server.listen_on(addr.clone()).unwrap();
+    tokio::spawn(async move { while server.next().await.is_some() {} });
+    addr
+}
+
+/// 建立连接并在后台驱动客户端，返回句柄与服务端节点 ID
+async fn connect(addr: &Url) -> (Handle, PeerId) {
+    let (caller, handle) = caller::Behavior::new(PROTOCOL, Config::default());
+    let (peer_id, transport) = build_transport();
+    let mut client = swarm::client::Swarm::new(
+        transport,
+        ClientBehavior {
+            ping: ping::outbound::Behavior::default(),
+            caller,
+        },
+        peer_id,
+        swarm::connection::PoolConfig::new(Box::new(TokioExecutor)),
+    );
+    client
+        .dial(DialOpts::new(Some(addr.clone()), None))
+        .unwrap();
+    let server = tokio::time::timeout(TIMEOUT, async {
+        loop {
+            if let Some(swarm::client::SwarmEvent::ConnectionEstablished { peer_id, .. }) =
+                client.next().await
+            {
+                return peer_id;
+            }
+        }
+    })
+    .await
+    .expect("connection timed out");
+    tokio::spawn(caller::drive(client));
+    (handle.with_timeout(TIMEOUT), server)
+}
+
+async fn call(
+    handle: &Handle,
+    server: PeerId,
+    service: &str,
+    message: &str,
+) -> Result<Status, Status> {
+    handle
+        .call(server, service, Status::new(Code::Ok, message.to_string()))
+        .await
+        .unwrap()
+        .into_payload()
+}
+
+fn server(router: Router) -> router::Behavior {
+    router::Behavior::new(router, [PROTOCOL], Config::default())
+}
+
+#[tokio::test]
+async fn unknown_service_is_unimplemented() {
+    let addr = spawn_server(server(echo(Router::new(), Duration::ZERO)));
+    let (handle, peer) = connect(&addr).await;
+
+    let status = call(&handle, peer, "test.missing", "hello")
+        .await
+        .unwrap_err();
+    assert_eq!(status.code_enum(), Code::Unimplemented);
+    assert_eq!(
+        call(&handle, peer, "test.echo", "hello")
+            .await
+            .unwrap()
+            .message,
+        "hello"
+    );
+}
+
+#[tokio::test]
+async fn handler_timeout_is_unavailable() {
+    let router = server(echo(Router::new(), Duration::from_secs(60)))
+        .with_handler_limits(Duration::from_millis(50), 10);
+    let addr = spawn_server(router);
+    let (handle, peer) = connect(&addr).await;
+
+    let status = call(&handle, peer, "test.echo", "hello").await.unwrap_err();
+    assert_eq!(status.code_enum(), Code::Unavailable);
+}
+
+#[tokio::test]
+async fn guard_rejection_skips_the_handler() {
+    let handled = Arc::new(AtomicUsize::new(0));
+    let counter = handled.clone();
+    let router = Router::new().route("test.echo", move |_, request: Request<Status>| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        let status = request.into_payload();
+        async move { Ok::<_, Status>(status) }
+    });
+    let router = server(router).with_guard(|_: &RequestContext| {
+        Err(Status::new(Code::PermissionDenied, "Denied".to_string()))
+    });
+    let addr = spawn_server(router);
+    let (handle, peer) = connect(&addr).await;
+
+    let status = call(&handle, peer, "test.echo", "hello").await.unwrap_err();
+    assert_eq!(status.code_enum(), Code::PermissionDenied);
+    assert_eq!(status.message, "Denied");
+    assert_eq!(handled.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn overlapping_requests_from_different_connections() {
+    let addr = spawn_server(server(echo(Router::new(), Duration::from_millis(100))));
+    let (first, peer) = connect(&addr).await;
+    let (second, _) = connect(&addr).await;
+
+    let (a, b) = futures::join!(
+        call(&first, peer, "test.echo", "first"),
+        call(&second, peer, "test.echo", "second"),
+    );
+    assert_eq!(a.unwrap().message, "first");
+    assert_eq!(b.unwrap().message, "second");
+}
@@ -0,0 +1,193 @@
+//! Vela 服务代码生成
+//!
+//! 为 `.proto` 中的 `service` 生成基于 `vela-request` 的服务端 trait 与客户端。
+//! 方法映射为 `Request::service` 字符串 `{package}.{method}`，如 `vela.table.sit_down`。
+//!
+//! 生成的代码依赖 `vela-request` 与 `vela-protobuf`。
+
+use std::{fmt::Write, io, path::Path};
+
+use prost_build::{Config, Method, Service};
+
+/// 生成服务代码的 [`prost_build::ServiceGenerator`]
+#[derive(Debug, Default, Clone)]
+pub struct ServiceGenerator;
+
+impl ServiceGenerator {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl prost_build::ServiceGenerator for ServiceGenerator {
+    fn generate(&mut self, service: Service, buf: &mut String) {
+        let methods: Vec<&Method> = service
+            .methods
+            .iter()
+            .filter(|method| {
+                let streaming = method.client_streaming || method.server_streaming;
+                if streaming {
+                    println!(
+                        "cargo:warning=Skipping streaming method {}.{}",
+                        service.proto_name, method.proto_name
+                    );
+                }
+                !streaming
+            })
+            .collect();
+
+        let module = to_snake(&service.name);
+        service.comments.append_with_indent(0, buf);
+        writeln!(buf, "pub mod {module} {{").unwrap();
+
+        // 服务名常量
+        for method in &methods {
+            writeln!(
+                buf,
+                "    pub const {}: &str = \"{}\";",
+                method.name.to_uppercase(),
+                service_name(&service.package, &method.name),
+            )
+            .unwrap();
+        }
+        buf.push('\n');
+
+        // 服务端 trait
+        writeln!(buf, "    #[::vela_request::async_trait]").unwrap();
+        writeln!(
+            buf,
+            "    pub trait {}: Send + Sync + 'static {{",
+            service.name
+        )
+        .unwrap();
+        for method in &methods {
+            method.comments.append_with_indent(2, buf);
+            writeln!(
+                buf,
+                "        async fn {}(&self, context: ::vela_request::router::RequestContext, request: ::vela_request::Request<{}>) -> ::core::result::Result<{}, ::vela_protobuf::common::Status>;",
+                method.name,
+                type_path(&method.input_type),
+                type_path(&method.output_type),
+            )
+            .unwrap();
+        }
+        buf.push_str("    }\n\n");
+
+        // 注册到路由
+        writeln!(buf, "    /// 将 `{}` 的所有方法注册到路由", service.name).unwrap();
+        writeln!(
+            buf,
+            "    pub fn route<S: {}>(router: ::vela_request::router::Router, service: ::std::sync::Arc<S>) -> ::vela_request::router::Router {{",
+            service.name
+        )
+        .unwrap();
+        buf.push_str("        router\n");
+        for method in &methods {
+            writeln!(
+                buf,
+                "            .route({}, {{ let service = service.clone(); move |context, request: ::vela_request::Request<{}>| {{ let service = service.clone(); async move {{ service.{}(context, request).await }} }} }})",
+                method.name.to_uppercase(),
+                type_path(&method.input_type),
+                method.name,
+            )
+            .unwrap();
+        }
+        buf.push_str("    }\n\n");
+
+        // 客户端
+        writeln!(buf, "    /// `{}` 客户端", service.name).unwrap();
+        writeln!(buf, "    #[derive(Debug, Clone)]").unwrap();
+        writeln!(buf, "    pub struct {}Client<T> {{", service.name).unwrap();
+        buf.push_str("        inner: T,\n");
+        buf.push_str("        peer_id: ::vela_request::PeerId,\n");
+        buf.push_str("    }\n\n");
+        writeln!(
+            buf,
+            "    impl<T: ::vela_request::Invoke> {}Client<T> {{",
+            service.name
+        )
+        .unwrap();
+        buf.push_str(
+            "        pub fn new(inner: T, peer_id: ::vela_request::PeerId) -> Self {\n            Self { inner, peer_id }\n        }\n\n",
+        );
+        buf.push_str("        pub fn peer_id(&self) -> ::vela_request::PeerId {\n            self.peer_id\n        }\n");
+        for method in &methods {
+            buf.push('\n');
+            method.comments.append_with_indent(2, buf);
+            writeln!(
+                buf,
+                "        pub async fn {}(&self, request: {}) -> ::core::result::Result<{}, ::vela_request::CallError> {{",
+                method.name,
+                type_path(&method.input_type),
+                type_path(&method.output_type),
+            )
+            .unwrap();
+            writeln!(
+                buf,
+                "            let request = ::vela_request::Request::new({}.to_string(), request);",
+                method.name.to_uppercase()
+            )
+            .unwrap();
+            buf.push_str(
+                "            let response = self.inner.invoke(self.peer_id, request).await?;\n",
+            );
+            buf.push_str(
+                "            response.into_payload().map_err(::vela_request::CallError::Status)\n",
+            );
+            buf.push_str("        }\n");
+        }
+        buf.push_str("    }\n");
+        buf.push_str("}\n");
+    }
+}
+
+/// 创建 prost 配置
+///
+/// 已设置服务代码生成，`vela.*` 包中的消息引用 `vela_protobuf` 中已生成的类型。
+pub fn configure() -> Config {
+    let mut config = Config::new();
+    config.service_generator(Box::new(ServiceGenerator::new()));
+    config.extern_path(".vela", "::vela_protobuf");
+    config
+}
+
+/// 使用 [`configure`] 编译 proto 文件
+pub fn compile_protos(
+    protos: &[impl AsRef<Path>],
+    includes: &[impl AsRef<Path>],
+) -> io::Result<()> {
+    configure().compile_protos(protos, includes)
+}
+
+/// 服务方法对应的 `Request::service`
+pub fn service_name(package: &str, method: &str) -> String {
+    if package.is_empty() {
+        method.to_string()
+    } else {
+        format!("{package}.{method}")
+    }
+}
+
+// 生成的代码位于服务模块内，相对路径需要指向上一级
+fn type_path(path: &str) -> String {
+    if path.starts_with("::") {
+        path.to_string()
+    } else {
+        format!("super::{path}")
+    }
+}
+
+fn to_snake(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len() + 4);
+    for (i, c) in name.char_indices() {
+        if c.is_ascii_uppercase() {
+            if i != 0 {
+                snake.push('_');
+            }
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
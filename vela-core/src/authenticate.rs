@@ -1,15 +1,132 @@
-use std::str::FromStr;
+mod cache;
+mod chain;
+mod dev;
+mod revocation;
+#[cfg(test)]
+mod testing;
 
-use serde::{Deserialize, Serialize};
+use std::{
+    marker::PhantomData,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::de::DeserializeOwned;
 
 use crate::{
     ids::{PlayerId, SessionId},
-    jwt,
+    issuer, jwks, jwt,
 };
 
+pub use cache::CachingAuthenticator;
+pub use chain::{ChainAuthenticator, Either};
+pub use dev::DevAuthenticator;
+pub use revocation::{DenyList, RevocationAuthenticator, RevocationList};
+
 #[async_trait::async_trait]
 pub trait Authenticator<T> {
-    async fn authenticate(&self, token: T) -> Result<(SessionId, PlayerId), AuthError>;
+    /// 自定义声明
+    type Claims;
+
+    async fn authenticate(&self, token: T) -> Result<Identity<Self::Claims>, AuthError>;
+}
+
+/// 认证通过的身份
+#[derive(Debug, Clone)]
+pub struct Identity<T = ()> {
+    session_id: SessionId,
+    player_id: PlayerId,
+    roles: Vec<String>,
+    scopes: Vec<String>,
+    expires_at: Option<SystemTime>,
+    claims: T,
+}
+
+impl<T> Identity<T> {
+    pub fn new(session_id: SessionId, player_id: PlayerId, claims: T) -> Self {
+        Self {
+            session_id,
+            player_id,
+            roles: Vec::new(),
+            scopes: Vec::new(),
+            expires_at: None,
+            claims,
+        }
+    }
+
+    pub fn with_roles(mut self, roles: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.roles = roles.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_scopes(mut self, scopes: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.scopes = scopes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_expires_at(mut self, expires_at: SystemTime) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+
+    pub fn session_id(&self) -> &SessionId {
+        &self.session_id
+    }
+
+    pub fn player_id(&self) -> &PlayerId {
+        &self.player_id
+    }
+
+    pub fn roles(&self) -> &[String] {
+        &self.roles
+    }
+
+    pub fn scopes(&self) -> &[String] {
+        &self.scopes
+    }
+
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+
+    pub fn expires_at(&self) -> Option<SystemTime> {
+        self.expires_at
+    }
+
+    /// 自定义声明
+    pub fn claims(&self) -> &T {
+        &self.claims
+    }
+
+    pub fn into_claims(self) -> T {
+        self.claims
+    }
+
+    pub fn map_claims<U>(self, f: impl FnOnce(T) -> U) -> Identity<U> {
+        Identity {
+            session_id: self.session_id,
+            player_id: self.player_id,
+            roles: self.roles,
+            scopes: self.scopes,
+            expires_at: self.expires_at,
+            claims: f(self.claims),
+        }
+    }
+}
+
+impl<T> From<jwt::Claims<T>> for Identity<jwt::Claims<T>> {
+    fn from(claims: jwt::Claims<T>) -> Self {
+        let roles = claims.roles.clone();
+        let scopes: Vec<String> = claims.scopes().map(str::to_string).collect();
+        let expires_at = UNIX_EPOCH + Duration::from_secs(claims.exp);
+        Identity::new(claims.jti.clone(), claims.sub.clone(), claims)
+            .with_roles(roles)
+            .with_scopes(scopes)
+            .with_expires_at(expires_at)
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -26,19 +143,74 @@ pub enum AuthError {
     Io(#[from] std::io::Error),
     #[error("Authentication service unavailable")]
     Timeout,
+    #[error("Player already has an active session")]
+    SessionExists,
 }
 
-#[derive(Clone)]
-pub struct JwtAuthenticator {
-    secret: jwt::DecodingKey,
+/// JWT 认证
+///
+/// `T` 为自定义声明，认证通过后可从 [`Identity::claims`] 取得完整的 [`jwt::Claims`]。
+/// 使用 [`jwks::Jwks`] 时按令牌头部的 `kid` 选择密钥，算法由密钥决定。
+/// 携带 `typ: "refresh"` 的刷新令牌总是被拒绝。
+pub struct JwtAuthenticator<T = ()> {
+    secret: Secret,
     validation: jwt::Validation,
+    _claims: PhantomData<fn() -> T>,
+}
+
+#[derive(Clone)]
+enum Secret {
+    Key(jwt::DecodingKey),
+    Jwks(jwks::Jwks),
+}
+
+impl<T> Clone for JwtAuthenticator<T> {
+    fn clone(&self) -> Self {
+        Self {
+            secret: self.secret.clone(),
+            validation: self.validation.clone(),
+            _claims: PhantomData,
+        }
+    }
 }
 
-impl JwtAuthenticator {
+impl<T> JwtAuthenticator<T> {
     pub fn new(secret: jwt::DecodingKey) -> Self {
         Self {
-            secret,
+            secret: Secret::Key(secret),
             validation: jwt::Validation::default(),
+            _claims: PhantomData,
+        }
+    }
+
+    /// 使用可刷新的密钥集
+    pub fn with_jwks(jwks: jwks::Jwks) -> Self {
+        Self {
+            secret: Secret::Jwks(jwks),
+            validation: jwt::Validation::default(),
+            _claims: PhantomData,
+        }
+    }
+
+    fn decode(&self, token: &str) -> jwt::errors::Result<jwt::Claims<T>>
+    where
+        T: DeserializeOwned,
+    {
+        match &self.secret {
+            Secret::Key(key) => jwt::decode(token, key, &self.validation).map(|data| data.claims),
+            Secret::Jwks(jwks) => {
+                let header = jwt::decode_header(token)?;
+                let kid = header.kid.ok_or_else(|| {
+                    jwt::errors::Error::from(jwt::errors::ErrorKind::InvalidToken)
+                })?;
+                let keys = jwks.keys();
+                let key = keys.get(&kid).ok_or_else(|| {
+                    jwt::errors::Error::from(jwt::errors::ErrorKind::InvalidKeyFormat)
+                })?;
+                let mut validation = self.validation.clone();
+                validation.algorithms = key.algorithms.clone();
+                jwt::decode(token, &key.key, &validation).map(|data| data.claims)
+            }
         }
     }
 
@@ -53,23 +225,119 @@ impl JwtAuthenticator {
 }
 
 #[async_trait::async_trait]
-impl Authenticator<String> for JwtAuthenticator {
-    async fn authenticate(&self, token: String) -> Result<(SessionId, PlayerId), AuthError> {
-        let claims = jwt::decode::<Claims<()>>(token.as_ref(), &self.secret, &self.validation)
-            .map_err(|e| AuthError::InvalidToken(e.to_string()))?
-            .claims;
-        Ok((claims.jti, claims.sub))
+impl<T> Authenticator<String> for JwtAuthenticator<T>
+where
+    T: DeserializeOwned + Send + Sync + 'static,
+{
+    type Claims = jwt::Claims<T>;
+
+    async fn authenticate(&self, token: String) -> Result<Identity<Self::Claims>, AuthError> {
+        let claims = self.decode(&token).map_err(|e| match e.kind() {
+            jwt::errors::ErrorKind::ExpiredSignature => AuthError::ExpiredToken,
+            _ => AuthError::InvalidToken(e.to_string()),
+        })?;
+        if claims.typ.as_deref() == Some(issuer::REFRESH_TOKEN_TYPE) {
+            return Err(AuthError::InvalidToken(
+                "Refresh token cannot be used for authentication".to_string(),
+            ));
+        }
+        Ok(claims.into())
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct Claims<T> {
-    pub jti: SessionId,           // Session ID
-    pub aud: Option<Vec<String>>, // 授权的范围
-    pub exp: u64,
-    pub iat: u64,
-    pub iss: Option<String>, // 颁发令牌的服务的名称
-    pub sub: PlayerId,       // 用户Id
-    #[serde(flatten)]
-    pub payload: T,
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    const SECRET: &[u8] = b"secret";
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct GameClaims {
+        level: u32,
+        region: String,
+    }
+
+    fn claims<T>(payload: T) -> jwt::Claims<T> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        jwt::Claims {
+            jti: SessionId::generate(),
+            aud: Vec::new(),
+            exp: now + 3600,
+            iat: now,
+            iss: None,
+            sub: PlayerId::generate(),
+            roles: vec!["admin".to_string()],
+            scope: Some("table.read table.write".to_string()),
+            typ: None,
+            payload,
+        }
+    }
+
+    fn encode<T: Serialize>(claims: &jwt::Claims<T>) -> String {
+        jwt::encode(
+            &jwt::Header::default(),
+            claims,
+            &jwt::EncodingKey::from_secret(SECRET),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn custom_claims_are_flattened() {
+        let claims = claims(GameClaims {
+            level: 7,
+            region: "eu".to_string(),
+        });
+
+        let value = serde_json::to_value(&claims).unwrap();
+        assert_eq!(value["level"], 7);
+        assert_eq!(value["region"], "eu");
+        assert!(value.get("payload").is_none());
+
+        let decoded: jwt::Claims<GameClaims> = serde_json::from_value(value).unwrap();
+        assert_eq!(decoded.payload, claims.payload);
+        assert_eq!(decoded.jti, claims.jti);
+    }
+
+    #[test]
+    fn identity_carries_custom_claims() {
+        let claims = claims(GameClaims {
+            level: 7,
+            region: "eu".to_string(),
+        });
+        let authenticator =
+            JwtAuthenticator::<GameClaims>::new(jwt::DecodingKey::from_secret(SECRET));
+
+        let identity = block_on(authenticator.authenticate(encode(&claims))).unwrap();
+        assert_eq!(identity.session_id(), &claims.jti);
+        assert_eq!(identity.player_id(), &claims.sub);
+        assert!(identity.has_role("admin"));
+        assert!(identity.has_scope("table.write"));
+        assert_eq!(
+            identity.expires_at(),
+            Some(UNIX_EPOCH + Duration::from_secs(claims.exp))
+        );
+        assert_eq!(identity.claims().payload, claims.payload);
+    }
+
+    #[test]
+    fn refresh_token_type_is_rejected() {
+        let mut claims = claims(());
+        claims.typ = Some(issuer::REFRESH_TOKEN_TYPE.to_string());
+        let authenticator = JwtAuthenticator::<()>::new(jwt::DecodingKey::from_secret(SECRET));
+
+        assert!(matches!(
+            block_on(authenticator.authenticate(encode(&claims))),
+            Err(AuthError::InvalidToken(_))
+        ));
+
+        claims.typ = None;
+        assert!(block_on(authenticator.authenticate(encode(&claims))).is_ok());
+    }
 }
@@ -0,0 +1,172 @@
+use std::{
+    num::NonZeroUsize,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use lru::LruCache;
+use parking_lot::Mutex;
+
+use super::{AuthError, Authenticator, Identity};
+
+/// 缓存认证结果
+///
+/// 令牌验证通过后缓存到身份过期，最长保留 `ttl`，认证失败不缓存。
+/// 克隆后共享同一份缓存。撤销检查应放在缓存之外，如
+/// `RevocationAuthenticator<CachingAuthenticator<_>, _>`。
+pub struct CachingAuthenticator<A: Authenticator<String>> {
+    inner: A,
+    ttl: Duration,
+    cache: Arc<Mutex<LruCache<String, Cached<A::Claims>>>>,
+}
+
+struct Cached<T> {
+    identity: Identity<T>,
+    expires_at: SystemTime,
+}
+
+impl<A: Authenticator<String>> CachingAuthenticator<A> {
+    /// 最多缓存 `capacity` 个令牌
+    pub fn new(inner: A, capacity: NonZeroUsize) -> Self {
+        Self {
+            inner,
+            ttl: Duration::from_secs(5 * 60),
+            cache: Arc::new(Mutex::new(LruCache::new(capacity))),
+        }
+    }
+
+    /// 缓存的最长保留时间，默认为 5 分钟，身份没有过期时间时以此为准
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn inner(&self) -> &A {
+        &self.inner
+    }
+
+    /// 移除缓存的令牌
+    pub fn invalidate(&self, token: &str) -> bool {
+        self.cache.lock().pop(token).is_some()
+    }
+
+    pub fn clear(&self) {
+        self.cache.lock().clear();
+    }
+}
+
+impl<A> Clone for CachingAuthenticator<A>
+where
+    A: Authenticator<String> + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            ttl: self.ttl,
+            cache: self.cache.clone(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<A> Authenticator<String> for CachingAuthenticator<A>
+where
+    A: Authenticator<String, Claims: Clone + Send> + Send + Sync,
+{
+    type Claims = A::Claims;
+
+    async fn authenticate(&self, token: String) -> Result<Identity<Self::Claims>, AuthError> {
+        let now = SystemTime::now();
+        {
+            let mut cache = self.cache.lock();
+            match cache.get(&token) {
+                Some(cached) if cached.expires_at > now => return Ok(cached.identity.clone()),
+                Some(_) => {
+                    cache.pop(&token);
+                }
+                None => {}
+            }
+        }
+        let identity = self.inner.authenticate(token.clone()).await?;
+        let expires_at = match identity.expires_at() {
+            Some(expires_at) => expires_at.min(now + self.ttl),
+            None => now + self.ttl,
+        };
+        self.cache.lock().put(
+            token,
+            Cached {
+                identity: identity.clone(),
+                expires_at,
+            },
+        );
+        Ok(identity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+
+    use super::*;
+    use crate::authenticate::testing::Counting;
+
+    fn token() -> String {
+        Counting::TOKEN.to_string()
+    }
+
+    fn cache(inner: Counting, ttl: Duration) -> CachingAuthenticator<Counting> {
+        CachingAuthenticator::new(inner, NonZeroUsize::new(8).unwrap()).with_ttl(ttl)
+    }
+
+    #[test]
+    fn cached_until_identity_expires() {
+        let inner = Counting::new().with_expires_in(Duration::from_millis(50));
+        let cache = cache(inner.clone(), Duration::from_secs(3600));
+
+        block_on(cache.authenticate(token())).unwrap();
+        block_on(cache.authenticate(token())).unwrap();
+        assert_eq!(inner.calls(), 1);
+
+        std::thread::sleep(Duration::from_millis(60));
+        block_on(cache.authenticate(token())).unwrap();
+        assert_eq!(inner.calls(), 2);
+    }
+
+    #[test]
+    fn cached_at_most_ttl() {
+        let inner = Counting::new().with_expires_in(Duration::from_secs(3600));
+        let cache = cache(inner.clone(), Duration::from_millis(50));
+
+        block_on(cache.authenticate(token())).unwrap();
+        block_on(cache.authenticate(token())).unwrap();
+        assert_eq!(inner.calls(), 1);
+
+        std::thread::sleep(Duration::from_millis(60));
+        block_on(cache.authenticate(token())).unwrap();
+        assert_eq!(inner.calls(), 2);
+    }
+
+    #[test]
+    fn failures_are_not_cached() {
+        let inner = Counting::new();
+        let cache = cache(inner.clone(), Duration::from_secs(3600));
+
+        assert!(block_on(cache.authenticate("invalid".to_string())).is_err());
+        assert!(block_on(cache.authenticate("invalid".to_string())).is_err());
+        assert_eq!(inner.calls(), 2);
+    }
+
+    #[test]
+    fn clones_share_the_cache() {
+        let inner = Counting::new();
+        let cache = cache(inner.clone(), Duration::from_secs(3600));
+
+        block_on(cache.clone().authenticate(token())).unwrap();
+        block_on(cache.authenticate(token())).unwrap();
+        assert_eq!(inner.calls(), 1);
+
+        assert!(cache.invalidate(Counting::TOKEN));
+        block_on(cache.authenticate(token())).unwrap();
+        assert_eq!(inner.calls(), 2);
+    }
+}
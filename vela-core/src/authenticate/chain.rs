@@ -0,0 +1,102 @@
+use super::{AuthError, Authenticator, Identity};
+
+/// 依次尝试两个认证器
+///
+/// 如先验证 JWT，失败后再按 API Key 验证。两者都失败时返回第一个认证器的错误。
+#[derive(Debug, Clone)]
+pub struct ChainAuthenticator<A, B> {
+    first: A,
+    second: B,
+}
+
+/// 链式认证的声明，标明由哪个认证器通过
+#[derive(Debug, Clone)]
+pub enum Either<A, B> {
+    First(A),
+    Second(B),
+}
+
+impl<A, B> ChainAuthenticator<A, B> {
+    pub fn new(first: A, second: B) -> Self {
+        Self { first, second }
+    }
+}
+
+#[async_trait::async_trait]
+impl<A, B> Authenticator<String> for ChainAuthenticator<A, B>
+where
+    A: Authenticator<String> + Send + Sync,
+    B: Authenticator<String> + Send + Sync,
+{
+    type Claims = Either<A::Claims, B::Claims>;
+
+    async fn authenticate(&self, token: String) -> Result<Identity<Self::Claims>, AuthError> {
+        let cause = match self.first.authenticate(token.clone()).await {
+            Ok(identity) => return Ok(identity.map_claims(Either::First)),
+            Err(cause) => cause,
+        };
+        match self.second.authenticate(token).await {
+            Ok(identity) => Ok(identity.map_claims(Either::Second)),
+            Err(_) => Err(cause),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+
+    use super::*;
+    use crate::{
+        authenticate::{DevAuthenticator, JwtAuthenticator, testing::Counting},
+        issuer::JwtIssuer,
+        jwt,
+    };
+
+    const SECRET: &[u8] = b"secret";
+    const API_KEY: &str = "api-key";
+
+    fn chain(api_keys: Counting) -> ChainAuthenticator<JwtAuthenticator, Counting> {
+        ChainAuthenticator::new(
+            JwtAuthenticator::new(jwt::DecodingKey::from_secret(SECRET)),
+            api_keys,
+        )
+    }
+
+    #[test]
+    fn jwt_is_tried_first() {
+        let api_keys = Counting::new();
+        let pair = JwtIssuer::from_secret(SECRET)
+            .issue("py_1".parse().unwrap())
+            .unwrap();
+
+        let identity = block_on(chain(api_keys.clone()).authenticate(pair.access_token)).unwrap();
+        assert!(matches!(identity.claims(), Either::First(_)));
+        assert_eq!(api_keys.calls(), 0);
+    }
+
+    #[test]
+    fn falls_back_after_jwt_failure() {
+        let api_keys = Counting::new();
+
+        let identity =
+            block_on(chain(api_keys.clone()).authenticate(Counting::TOKEN.to_string())).unwrap();
+        assert!(matches!(identity.claims(), Either::Second(())));
+        assert_eq!(identity.session_id(), api_keys.session_id());
+        assert_eq!(api_keys.calls(), 1);
+    }
+
+    #[test]
+    fn returns_the_first_error_when_both_fail() {
+        let chain = ChainAuthenticator::new(
+            JwtAuthenticator::<()>::new(jwt::DecodingKey::from_secret(SECRET)),
+            DevAuthenticator::new().with_player(API_KEY, "py_1".parse().unwrap()),
+        );
+
+        assert!(block_on(chain.authenticate(API_KEY.to_string())).is_ok());
+        assert!(matches!(
+            block_on(chain.authenticate("unknown".to_string())),
+            Err(AuthError::InvalidToken(_))
+        ));
+    }
+}
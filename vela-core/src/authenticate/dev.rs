@@ -0,0 +1,78 @@
+use std::{collections::HashMap, sync::Arc};
+
+use super::{AuthError, Authenticator, Identity};
+use crate::ids::{PlayerId, SessionId};
+
+/// 静态令牌认证，仅用于开发与测试
+///
+/// 每次认证都会生成新的会话。
+#[derive(Debug, Clone, Default)]
+pub struct DevAuthenticator {
+    tokens: Arc<HashMap<String, (PlayerId, Vec<String>)>>,
+}
+
+impl DevAuthenticator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 令牌 `token` 认证为玩家 `player_id`
+    pub fn with_player(self, token: impl Into<String>, player_id: PlayerId) -> Self {
+        self.with_roles(token, player_id, Vec::<String>::new())
+    }
+
+    pub fn with_roles(
+        mut self,
+        token: impl Into<String>,
+        player_id: PlayerId,
+        roles: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        Arc::make_mut(&mut self.tokens).insert(
+            token.into(),
+            (player_id, roles.into_iter().map(Into::into).collect()),
+        );
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl Authenticator<String> for DevAuthenticator {
+    type Claims = ();
+
+    async fn authenticate(&self, token: String) -> Result<Identity<Self::Claims>, AuthError> {
+        if token.is_empty() {
+            return Err(AuthError::EmptyToken);
+        }
+        let (player_id, roles) = self.tokens.get(&token).ok_or(AuthError::Unauthorized)?;
+        Ok(Identity::new(SessionId::generate(), player_id.clone(), ()).with_roles(roles.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+
+    use super::*;
+
+    #[test]
+    fn each_authentication_starts_a_new_session() {
+        let player_id: PlayerId = "py_1".parse().unwrap();
+        let authenticator =
+            DevAuthenticator::new().with_roles("token", player_id.clone(), ["admin"]);
+
+        let first = block_on(authenticator.authenticate("token".to_string())).unwrap();
+        let second = block_on(authenticator.authenticate("token".to_string())).unwrap();
+        assert_eq!(first.player_id(), &player_id);
+        assert!(first.has_role("admin"));
+        assert_ne!(first.session_id(), second.session_id());
+
+        assert!(matches!(
+            block_on(authenticator.authenticate(String::new())),
+            Err(AuthError::EmptyToken)
+        ));
+        assert!(matches!(
+            block_on(authenticator.authenticate("unknown".to_string())),
+            Err(AuthError::Unauthorized)
+        ));
+    }
+}
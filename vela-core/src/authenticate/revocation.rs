@@ -0,0 +1,150 @@
+use std::{collections::HashMap, sync::Arc, time::SystemTime};
+
+use parking_lot::RwLock;
+
+use super::{AuthError, Authenticator, Identity};
+use crate::ids::SessionId;
+
+/// 已撤销的 `jti`
+#[async_trait::async_trait]
+pub trait RevocationList {
+    async fn is_revoked(&self, session_id: &SessionId) -> bool;
+}
+
+/// 内存中的撤销列表
+///
+/// 克隆后共享同一份列表。
+#[derive(Debug, Clone, Default)]
+pub struct DenyList {
+    revoked: Arc<RwLock<HashMap<SessionId, Option<SystemTime>>>>,
+}
+
+impl DenyList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 永久撤销
+    pub fn revoke(&self, session_id: SessionId) {
+        self.revoked.write().insert(session_id, None);
+    }
+
+    /// 撤销到令牌过期为止，过期后由 [`DenyList::prune`] 清理
+    pub fn revoke_until(&self, session_id: SessionId, expires_at: SystemTime) {
+        self.revoked.write().insert(session_id, Some(expires_at));
+    }
+
+    pub fn restore(&self, session_id: &SessionId) -> bool {
+        self.revoked.write().remove(session_id).is_some()
+    }
+
+    pub fn contains(&self, session_id: &SessionId) -> bool {
+        self.revoked.read().contains_key(session_id)
+    }
+
+    /// 移除已过期的条目
+    pub fn prune(&self) -> usize {
+        let now = SystemTime::now();
+        let mut revoked = self.revoked.write();
+        let len = revoked.len();
+        revoked.retain(|_, expires_at| expires_at.is_none_or(|expires_at| expires_at > now));
+        len - revoked.len()
+    }
+}
+
+#[async_trait::async_trait]
+impl RevocationList for DenyList {
+    async fn is_revoked(&self, session_id: &SessionId) -> bool {
+        self.contains(session_id)
+    }
+}
+
+/// 拒绝已撤销的会话
+#[derive(Debug, Clone)]
+pub struct RevocationAuthenticator<A, L = DenyList> {
+    inner: A,
+    list: L,
+}
+
+impl<A, L> RevocationAuthenticator<A, L> {
+    pub fn new(inner: A, list: L) -> Self {
+        Self { inner, list }
+    }
+
+    pub fn list(&self) -> &L {
+        &self.list
+    }
+}
+
+#[async_trait::async_trait]
+impl<A, L> Authenticator<String> for RevocationAuthenticator<A, L>
+where
+    A: Authenticator<String, Claims: Send> + Send + Sync,
+    L: RevocationList + Send + Sync,
+{
+    type Claims = A::Claims;
+
+    async fn authenticate(&self, token: String) -> Result<Identity<Self::Claims>, AuthError> {
+        let identity = self.inner.authenticate(token).await?;
+        if self.list.is_revoked(identity.session_id()).await {
+            return Err(AuthError::InvalidToken(format!(
+                "Session {} has been revoked",
+                identity.session_id()
+            )));
+        }
+        Ok(identity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{num::NonZeroUsize, time::Duration};
+
+    use futures::executor::block_on;
+
+    use super::*;
+    use crate::authenticate::{CachingAuthenticator, testing::Counting};
+
+    #[test]
+    fn prune_removes_only_expired_entries() {
+        let list = DenyList::new();
+        let expired = SessionId::generate();
+        let pending = SessionId::generate();
+        let permanent = SessionId::generate();
+        list.revoke_until(expired.clone(), SystemTime::now() - Duration::from_secs(1));
+        list.revoke_until(
+            pending.clone(),
+            SystemTime::now() + Duration::from_secs(3600),
+        );
+        list.revoke(permanent.clone());
+
+        assert_eq!(list.prune(), 1);
+        assert!(!list.contains(&expired));
+        assert!(list.contains(&pending));
+        assert!(list.contains(&permanent));
+        assert_eq!(list.prune(), 0);
+    }
+
+    #[test]
+    fn revocation_applies_over_the_cache() {
+        let inner = Counting::new();
+        let list = DenyList::new();
+        let authenticator = RevocationAuthenticator::new(
+            CachingAuthenticator::new(inner.clone(), NonZeroUsize::new(8).unwrap()),
+            list.clone(),
+        );
+        let token = || Counting::TOKEN.to_string();
+
+        block_on(authenticator.authenticate(token())).unwrap();
+        list.revoke(inner.session_id().clone());
+        assert!(matches!(
+            block_on(authenticator.authenticate(token())),
+            Err(AuthError::InvalidToken(_))
+        ));
+
+        assert!(list.restore(inner.session_id()));
+        block_on(authenticator.authenticate(token())).unwrap();
+        // 撤销与恢复都没有绕过缓存
+        assert_eq!(inner.calls(), 1);
+    }
+}
@@ -0,0 +1,61 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, SystemTime},
+};
+
+use super::{AuthError, Authenticator, Identity};
+use crate::ids::{PlayerId, SessionId};
+
+/// 只接受令牌 [`Counting::TOKEN`] 的认证器，记录被调用的次数
+#[derive(Clone)]
+pub(crate) struct Counting {
+    session_id: SessionId,
+    expires_in: Option<Duration>,
+    calls: Arc<AtomicUsize>,
+}
+
+impl Counting {
+    pub(crate) const TOKEN: &str = "valid";
+
+    pub(crate) fn new() -> Self {
+        Self {
+            session_id: SessionId::generate(),
+            expires_in: None,
+            calls: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// 身份在认证后 `expires_in` 过期
+    pub(crate) fn with_expires_in(mut self, expires_in: Duration) -> Self {
+        self.expires_in = Some(expires_in);
+        self
+    }
+
+    pub(crate) fn session_id(&self) -> &SessionId {
+        &self.session_id
+    }
+
+    pub(crate) fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait::async_trait]
+impl Authenticator<String> for Counting {
+    type Claims = ();
+
+    async fn authenticate(&self, token: String) -> Result<Identity<Self::Claims>, AuthError> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        if token != Self::TOKEN {
+            return Err(AuthError::Unauthorized);
+        }
+        let identity = Identity::new(self.session_id.clone(), PlayerId::generate(), ());
+        Ok(match self.expires_in {
+            Some(expires_in) => identity.with_expires_at(SystemTime::now() + expires_in),
+            None => identity,
+        })
+    }
+}
@@ -0,0 +1,238 @@
+use std::{error::Error, fmt, io};
+
+use vela_protobuf::{
+    FrameError,
+    common::{Code, Status},
+};
+
+use crate::{authenticate::AuthError, game::GameError};
+
+/// 携带结果码的错误
+///
+/// 统一各类错误到 [`Code`] 的映射，处理器中可以直接使用 `?` 转换，
+/// 最终通过 `From<StatusError> for Status` 返回给客户端。
+#[derive(Debug)]
+pub struct StatusError {
+    status: Status,
+    source: Option<Box<dyn Error + Send + Sync + 'static>>,
+}
+
+impl StatusError {
+    pub fn new(code: Code, message: impl Into<String>) -> Self {
+        Self {
+            status: Status::new(code, message),
+            source: None,
+        }
+    }
+
+    /// 记录原始错误，仅用于日志，不会发送给客户端
+    pub fn with_source<E>(mut self, source: E) -> Self
+    where
+        E: Error + Send + Sync + 'static,
+    {
+        self.source = Some(Box::new(source));
+        self
+    }
+
+    pub fn code(&self) -> Code {
+        self.status.code_enum()
+    }
+
+    pub fn message(&self) -> &str {
+        &self.status.message
+    }
+
+    pub fn status(&self) -> &Status {
+        &self.status
+    }
+
+    pub fn into_status(self) -> Status {
+        self.status
+    }
+}
+
+impl fmt::Display for StatusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.status.fmt(f)
+    }
+}
+
+impl Error for StatusError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_deref().map(|e| e as &(dyn Error + 'static))
+    }
+}
+
+impl From<StatusError> for Status {
+    fn from(error: StatusError) -> Self {
+        error.status
+    }
+}
+
+impl From<Status> for StatusError {
+    fn from(status: Status) -> Self {
+        Self {
+            status,
+            source: None,
+        }
+    }
+}
+
+impl From<Code> for StatusError {
+    fn from(code: Code) -> Self {
+        Status::from(code).into()
+    }
+}
+
+impl AuthError {
+    /// 错误对应的结果码
+    pub fn code(&self) -> Code {
+        match self {
+            AuthError::EmptyToken
+            | AuthError::InvalidToken(_)
+            | AuthError::ExpiredToken
+            | AuthError::Unauthorized => Code::Unauthenticated,
+            AuthError::Io(_) => Code::Internal,
+            AuthError::Timeout => Code::Unavailable,
+            AuthError::SessionExists => Code::AlreadyExists,
+        }
+    }
+
+    /// 返回给客户端的状态，不包含令牌解析或内部错误的细节
+    pub fn status(&self) -> Status {
+        let message = match self {
+            AuthError::InvalidToken(_) => "Invalid token".to_string(),
+            AuthError::Io(_) => "Internal error".to_string(),
+            error => error.to_string(),
+        };
+        Status::new(self.code(), message)
+    }
+}
+
+impl From<AuthError> for StatusError {
+    fn from(error: AuthError) -> Self {
+        StatusError::from(error.status()).with_source(error)
+    }
+}
+
+impl GameError {
+    /// 错误对应的结果码
+    pub fn code(&self) -> Code {
+        match self {
+            GameError::InvalidAction(_) | GameError::Decode(_) => Code::InvalidArgument,
+            GameError::OutOfTurn | GameError::NotInGame => Code::FailedPrecondition,
+        }
+    }
+}
+
+impl From<GameError> for StatusError {
+    fn from(error: GameError) -> Self {
+        StatusError::new(error.code(), error.to_string()).with_source(error)
+    }
+}
+
+impl From<io::Error> for StatusError {
+    fn from(error: io::Error) -> Self {
+        let code = io_code(&error);
+        // 内部错误不向客户端暴露细节
+        let message = match code {
+            Code::Internal => "Internal error".to_string(),
+            _ => error.to_string(),
+        };
+        StatusError::new(code, message).with_source(error)
+    }
+}
+
+impl From<FrameError> for StatusError {
+    fn from(error: FrameError) -> Self {
+        match error {
+            FrameError::Io(e) => e.into(),
+            FrameError::Decode(e) => e.into(),
+            FrameError::Encode(_) => {
+                StatusError::new(Code::Internal, "Internal error").with_source(error)
+            }
+            FrameError::Closed => {
+                StatusError::new(Code::Unavailable, error.to_string()).with_source(error)
+            }
+        }
+    }
+}
+
+impl From<prost::DecodeError> for StatusError {
+    fn from(error: prost::DecodeError) -> Self {
+        StatusError::new(Code::InvalidArgument, error.to_string()).with_source(error)
+    }
+}
+
+fn io_code(error: &io::Error) -> Code {
+    match error.kind() {
+        io::ErrorKind::NotFound => Code::NotFound,
+        io::ErrorKind::PermissionDenied => Code::PermissionDenied,
+        io::ErrorKind::AlreadyExists => Code::AlreadyExists,
+        io::ErrorKind::InvalidInput | io::ErrorKind::InvalidData => Code::InvalidArgument,
+        io::ErrorKind::TimedOut => Code::DeadlineExceeded,
+        io::ErrorKind::Interrupted => Code::Cancelled,
+        io::ErrorKind::Unsupported => Code::Unimplemented,
+        io::ErrorKind::OutOfMemory => Code::ResourceExhausted,
+        io::ErrorKind::ConnectionRefused
+        | io::ErrorKind::ConnectionReset
+        | io::ErrorKind::ConnectionAborted
+        | io::ErrorKind::NotConnected
+        | io::ErrorKind::BrokenPipe
+        | io::ErrorKind::UnexpectedEof
+        | io::ErrorKind::WouldBlock => Code::Unavailable,
+        _ => Code::Internal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auth_error_status_hides_internal_details() {
+        let error = AuthError::InvalidToken("InvalidSignature at byte 42".to_string());
+        let status = StatusError::from(error).into_status();
+        assert_eq!(status.code_enum(), Code::Unauthenticated);
+        assert_eq!(status.message, "Invalid token");
+
+        let error = AuthError::Io(io::Error::other("redis://10.0.0.1 refused"));
+        let status = error.status();
+        assert_eq!(status.code_enum(), Code::Internal);
+        assert_eq!(status.message, "Internal error");
+
+        let status = AuthError::ExpiredToken.status();
+        assert_eq!(status.code_enum(), Code::Unauthenticated);
+        assert_eq!(status.message, AuthError::ExpiredToken.to_string());
+    }
+
+    #[test]
+    fn status_error_keeps_source_for_logging() {
+        let error = StatusError::from(AuthError::InvalidToken("detail".to_string()));
+        let source = error.source().expect("source should be kept");
+        assert!(source.to_string().contains("detail"));
+    }
+
+    #[test]
+    fn game_error_code_matches_status_error() {
+        for error in [
+            GameError::InvalidAction("x".to_string()),
+            GameError::OutOfTurn,
+            GameError::NotInGame,
+        ] {
+            let code = error.code();
+            assert_eq!(StatusError::from(error).code(), code);
+        }
+    }
+
+    #[test]
+    fn internal_io_errors_are_hidden() {
+        let error = StatusError::from(io::Error::other("disk /var/lib/vela full"));
+        assert_eq!(error.code(), Code::Internal);
+        assert_eq!(error.message(), "Internal error");
+
+        let error = StatusError::from(io::Error::new(io::ErrorKind::NotFound, "missing"));
+        assert_eq!(error.code(), Code::NotFound);
+        assert_eq!(error.message(), "missing");
+    }
+}
@@ -0,0 +1,101 @@
+use std::{collections::VecDeque, time::Duration};
+
+use crate::ids::PlayerId;
+
+/// 桌子类游戏逻辑
+///
+/// 游戏只处理类型化的动作与状态消息，网络、坐位与准备流程由桌子运行时负责。
+pub trait Game: Send + 'static {
+    /// 玩家发送的动作消息
+    type Action: prost::Message + Default;
+    /// 下发给玩家的状态消息
+    type State: prost::Message + Default;
+    /// 游戏结束后的结果
+    type Result: Send + 'static;
+
+    /// 游戏开始，`ctx.players()` 为参与本局的玩家
+    fn on_start(&mut self, ctx: &mut GameContext<Self::State>);
+
+    /// 处理玩家动作
+    fn on_player_action(
+        &mut self,
+        ctx: &mut GameContext<Self::State>,
+        player_id: &PlayerId,
+        action: Self::Action,
+    ) -> Result<(), GameError>;
+
+    /// 定时驱动，`elapsed` 为距离上次驱动经过的时间
+    fn on_tick(&mut self, ctx: &mut GameContext<Self::State>, elapsed: Duration);
+
+    /// 玩家在游戏中离开桌子
+    fn on_player_leave(&mut self, ctx: &mut GameContext<Self::State>, player_id: &PlayerId);
+
+    fn is_finished(&self) -> bool;
+
+    /// 游戏结果，仅在 `is_finished` 返回 true 后有效
+    fn result(&mut self) -> Option<Self::Result>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum GameError {
+    #[error("Invalid action: {0}")]
+    InvalidAction(String),
+    #[error("Not the player's turn")]
+    OutOfTurn,
+    #[error("Player is not in the game")]
+    NotInGame,
+    #[error("Failed to decode action: {0}")]
+    Decode(#[from] prost::DecodeError),
+}
+
+/// 消息接收者
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Recipient {
+    Player(PlayerId),
+    All,
+}
+
+/// 游戏上下文，提供参与玩家并收集需要下发的状态
+pub struct GameContext<S> {
+    players: Vec<(u32, PlayerId)>,
+    pending_state: VecDeque<(Recipient, S)>,
+}
+
+impl<S> GameContext<S> {
+    pub fn new(players: Vec<(u32, PlayerId)>) -> Self {
+        Self {
+            players,
+            pending_state: VecDeque::new(),
+        }
+    }
+
+    /// 参与游戏的玩家及其坐位序号
+    pub fn players(&self) -> &[(u32, PlayerId)] {
+        &self.players
+    }
+
+    pub fn contains(&self, player_id: &PlayerId) -> bool {
+        self.players.iter().any(|(_, p)| p == player_id)
+    }
+
+    /// 玩家离开后从参与列表移除
+    pub fn remove_player(&mut self, player_id: &PlayerId) {
+        self.players.retain(|(_, p)| p != player_id);
+    }
+
+    /// 向指定玩家发送状态
+    pub fn send(&mut self, player_id: PlayerId, state: S) {
+        self.pending_state
+            .push_back((Recipient::Player(player_id), state));
+    }
+
+    /// 向所有已就座的玩家广播状态
+    pub fn broadcast(&mut self, state: S) {
+        self.pending_state.push_back((Recipient::All, state));
+    }
+
+    /// 取出下一条待下发的状态
+    pub fn next_state(&mut self) -> Option<(Recipient, S)> {
+        self.pending_state.pop_front()
+    }
+}
@@ -128,6 +128,12 @@ macro_rules! def_id {
             pub fn is_valid_prefix(prefix: &str) -> bool {
                 prefix == $prefix $( || prefix == $alt_prefix )*
             }
+
+            /// Generates a new random id with the primary prefix.
+            pub fn generate() -> Self {
+                let bytes: [u8; 16] = rand::random();
+                $struct_name(format!("{}{}", $prefix, bs58::encode(bytes).into_string()).into())
+            }
         }
 
         impl PartialEq<str> for $struct_name {
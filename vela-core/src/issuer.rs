@@ -0,0 +1,318 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use crate::{
+    authenticate::AuthError,
+    ids::{PlayerId, SessionId},
+    jwt,
+};
+
+/// 刷新令牌的 `aud`
+///
+/// 访问令牌的验证配置了其他受众时，刷新令牌无法当作访问令牌使用。
+pub const REFRESH_AUDIENCE: &str = "vela.connect.refresh";
+
+/// 刷新令牌的 `typ` 声明
+///
+/// [`JwtAuthenticator`](crate::authenticate::JwtAuthenticator) 拒绝携带该声明的令牌，
+/// 即使访问令牌的验证没有配置受众。
+pub const REFRESH_TOKEN_TYPE: &str = "refresh";
+
+/// 刷新令牌 ID 的声明，每个刷新令牌各不相同，自定义声明不应使用该名称
+pub const REFRESH_ID_CLAIM: &str = "rid";
+
+/// 刷新令牌的声明，在访问令牌声明之外带有 [`REFRESH_ID_CLAIM`]
+#[derive(Serialize)]
+struct RefreshClaims<'a, T> {
+    #[serde(flatten)]
+    claims: &'a jwt::Claims<T>,
+    rid: String,
+}
+
+/// 一组访问令牌与刷新令牌
+#[derive(Debug, Clone)]
+pub struct TokenPair {
+    pub session_id: SessionId,
+    pub access_token: String,
+    pub refresh_token: String,
+    /// 访问令牌的有效期
+    pub expires_in: Duration,
+}
+
+/// 令牌签发
+///
+/// 访问令牌以新生成的 [`SessionId`] 为 `jti`、[`PlayerId`] 为 `sub`，
+/// 刷新令牌使用相同的会话，刷新后会话保持不变。
+///
+/// 刷新令牌只能使用一次，已使用的记录保存在内存中，克隆后共享。
+#[derive(Clone)]
+pub struct JwtIssuer {
+    encoding: jwt::EncodingKey,
+    decoding: jwt::DecodingKey,
+    header: jwt::Header,
+    issuer: Option<String>,
+    audience: Vec<String>,
+    access_ttl: Duration,
+    refresh_ttl: Duration,
+    /// 已使用的刷新令牌 ID 与其过期时间
+    used: Arc<Mutex<HashMap<String, u64>>>,
+}
+
+impl JwtIssuer {
+    pub fn new(
+        encoding: jwt::EncodingKey,
+        decoding: jwt::DecodingKey,
+        algorithm: jwt::Algorithm,
+    ) -> Self {
+        Self {
+            encoding,
+            decoding,
+            header: jwt::Header::new(algorithm),
+            issuer: None,
+            audience: Vec::new(),
+            access_ttl: Duration::from_secs(15 * 60),
+            refresh_ttl: Duration::from_secs(30 * 24 * 60 * 60),
+            used: Arc::default(),
+        }
+    }
+
+    /// 使用 HS256 共享密钥
+    pub fn from_secret(secret: &[u8]) -> Self {
+        Self::new(
+            jwt::EncodingKey::from_secret(secret),
+            jwt::DecodingKey::from_secret(secret),
+            jwt::Algorithm::HS256,
+        )
+    }
+
+    /// 令牌头部的 `kid`，用于 JWKS 验证
+    pub fn with_key_id(mut self, kid: impl Into<String>) -> Self {
+        self.header.kid = Some(kid.into());
+        self
+    }
+
+    pub fn with_issuer(mut self, issuer: impl Into<String>) -> Self {
+        self.issuer = Some(issuer.into());
+        self
+    }
+
+    /// 访问令牌的 `aud`
+    pub fn with_audience(mut self, audience: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.audience = audience.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// 访问令牌有效期，默认为 15 分钟
+    pub fn with_access_ttl(mut self, ttl: Duration) -> Self {
+        self.access_ttl = ttl;
+        self
+    }
+
+    /// 刷新令牌有效期，默认为 30 天
+    pub fn with_refresh_ttl(mut self, ttl: Duration) -> Self {
+        self.refresh_ttl = ttl;
+        self
+    }
+
+    /// 访问令牌的声明，可在签发前补充角色与自定义声明
+    pub fn claims(&self, session_id: SessionId, player_id: PlayerId) -> jwt::Claims {
+        let now = now();
+        jwt::Claims {
+            jti: session_id,
+            aud: self.audience.clone(),
+            exp: now + self.access_ttl.as_secs(),
+            iat: now,
+            iss: self.issuer.clone(),
+            sub: player_id,
+            roles: Vec::new(),
+            scope: None,
+            typ: None,
+            payload: (),
+        }
+    }
+
+    /// 签发任意声明
+    pub fn encode<T: Serialize>(&self, claims: &jwt::Claims<T>) -> jwt::errors::Result<String> {
+        jwt::encode(&self.header, claims, &self.encoding)
+    }
+
+    /// 为玩家创建新会话并签发令牌
+    pub fn issue(&self, player_id: PlayerId) -> jwt::errors::Result<TokenPair> {
+        self.issue_claims(self.claims(SessionId::generate(), player_id))
+    }
+
+    /// 以指定的访问令牌声明签发令牌，刷新令牌携带相同的角色与自定义声明
+    pub fn issue_claims<T: Serialize>(
+        &self,
+        claims: jwt::Claims<T>,
+    ) -> jwt::errors::Result<TokenPair> {
+        let access_token = self.encode(&claims)?;
+        let mut refresh = claims;
+        refresh.aud = vec![REFRESH_AUDIENCE.to_string()];
+        refresh.typ = Some(REFRESH_TOKEN_TYPE.to_string());
+        refresh.exp = refresh.iat + self.refresh_ttl.as_secs();
+        let refresh_claims = RefreshClaims {
+            claims: &refresh,
+            rid: bs58::encode(rand::random::<[u8; 16]>()).into_string(),
+        };
+        let refresh_token = jwt::encode(&self.header, &refresh_claims, &self.encoding)?;
+        Ok(TokenPair {
+            session_id: refresh.jti,
+            access_token,
+            refresh_token,
+            expires_in: self.access_ttl,
+        })
+    }
+
+    /// 使用刷新令牌换取新的令牌
+    ///
+    /// 同时签发新的刷新令牌，旧的刷新令牌随即失效。记录只保存在本实例中，
+    /// 多实例部署时需要使会话的令牌失效请撤销会话，如 [`DenyList`](crate::authenticate::DenyList)。
+    pub fn refresh(&self, refresh_token: &str) -> Result<TokenPair, AuthError> {
+        let mut validation = jwt::Validation::new(self.header.alg);
+        validation.set_audience(&[REFRESH_AUDIENCE]);
+        if let Some(issuer) = &self.issuer {
+            validation.set_issuer(&[issuer]);
+        }
+        let mut claims = jwt::decode::<jwt::Claims<serde_json::Map<String, serde_json::Value>>>(
+            refresh_token,
+            &self.decoding,
+            &validation,
+        )
+        .map_err(|e| match e.kind() {
+            jwt::errors::ErrorKind::ExpiredSignature => AuthError::ExpiredToken,
+            _ => AuthError::InvalidToken(e.to_string()),
+        })?
+        .claims;
+        if claims.typ.as_deref() != Some(REFRESH_TOKEN_TYPE) {
+            return Err(AuthError::InvalidToken("Not a refresh token".to_string()));
+        }
+        let Some(serde_json::Value::String(rid)) = claims.payload.remove(REFRESH_ID_CLAIM) else {
+            return Err(AuthError::InvalidToken(
+                "Missing refresh token id".to_string(),
+            ));
+        };
+        if self.used.lock().insert(rid, claims.exp).is_some() {
+            return Err(AuthError::InvalidToken(
+                "Refresh token has already been used".to_string(),
+            ));
+        }
+        let now = now();
+        claims.typ = None;
+        claims.aud = self.audience.clone();
+        claims.iat = now;
+        claims.exp = now + self.access_ttl.as_secs();
+        claims.iss = self.issuer.clone();
+        self.issue_claims(claims)
+            .map_err(|e| AuthError::Io(std::io::Error::other(e)))
+    }
+
+    /// 清理已过期的刷新令牌记录，返回清理的数量
+    pub fn prune(&self) -> usize {
+        let now = now();
+        let mut used = self.used.lock();
+        let len = used.len();
+        used.retain(|_, exp| *exp > now);
+        len - used.len()
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+
+    use super::*;
+    use crate::authenticate::{Authenticator, JwtAuthenticator};
+
+    const SECRET: &[u8] = b"secret";
+
+    // 不校验受众，只依靠 `typ` 区分刷新令牌
+    fn authenticator() -> JwtAuthenticator {
+        let mut authenticator = JwtAuthenticator::new(jwt::DecodingKey::from_secret(SECRET));
+        authenticator.validation_mut().validate_aud = false;
+        authenticator
+    }
+
+    #[test]
+    fn refresh_token_is_not_an_access_token() {
+        let pair = JwtIssuer::from_secret(SECRET)
+            .issue(PlayerId::generate())
+            .unwrap();
+
+        assert!(block_on(authenticator().authenticate(pair.access_token)).is_ok());
+        assert!(matches!(
+            block_on(authenticator().authenticate(pair.refresh_token)),
+            Err(AuthError::InvalidToken(_))
+        ));
+    }
+
+    #[test]
+    fn access_token_cannot_refresh() {
+        let issuer = JwtIssuer::from_secret(SECRET).with_audience([REFRESH_AUDIENCE]);
+        let pair = issuer.issue(PlayerId::generate()).unwrap();
+
+        assert!(matches!(
+            issuer.refresh(&pair.access_token),
+            Err(AuthError::InvalidToken(_))
+        ));
+    }
+
+    #[test]
+    fn refresh_keeps_the_session() {
+        let issuer = JwtIssuer::from_secret(SECRET);
+        let pair = issuer.issue(PlayerId::generate()).unwrap();
+        let refreshed = issuer.refresh(&pair.refresh_token).unwrap();
+
+        assert_eq!(refreshed.session_id, pair.session_id);
+        let identity = block_on(authenticator().authenticate(refreshed.access_token)).unwrap();
+        assert_eq!(identity.session_id(), &pair.session_id);
+        assert!(identity.claims().typ.is_none());
+    }
+
+    #[test]
+    fn refresh_token_can_only_be_used_once() {
+        let issuer = JwtIssuer::from_secret(SECRET);
+        let pair = issuer.issue(PlayerId::generate()).unwrap();
+        let refreshed = issuer.refresh(&pair.refresh_token).unwrap();
+
+        assert_ne!(refreshed.refresh_token, pair.refresh_token);
+        // 克隆共享已使用的记录
+        assert!(matches!(
+            issuer.clone().refresh(&pair.refresh_token),
+            Err(AuthError::InvalidToken(_))
+        ));
+        assert!(issuer.refresh(&refreshed.refresh_token).is_ok());
+        assert_eq!(issuer.prune(), 0);
+    }
+
+    #[test]
+    fn refresh_id_is_not_copied_to_the_access_token() {
+        let issuer = JwtIssuer::from_secret(SECRET);
+        let pair = issuer.issue(PlayerId::generate()).unwrap();
+        let refreshed = issuer.refresh(&pair.refresh_token).unwrap();
+
+        let mut validation = jwt::Validation::new(jwt::Algorithm::HS256);
+        validation.validate_aud = false;
+        let claims = jwt::decode::<jwt::Claims<serde_json::Map<String, serde_json::Value>>>(
+            &refreshed.access_token,
+            &jwt::DecodingKey::from_secret(SECRET),
+            &validation,
+        )
+        .unwrap()
+        .claims;
+        assert!(!claims.payload.contains_key(REFRESH_ID_CLAIM));
+    }
+}
@@ -0,0 +1,344 @@
+//! 按 `kid` 选择验证密钥的密钥集
+//!
+//! 密钥集从 JWKS 文档加载，可定期刷新。刷新时整体替换密钥集，
+//! 正在进行的认证继续使用旧的密钥集。通过 [`Jwks::with_key`] 配置的密钥在刷新后保留。
+
+use std::{collections::HashMap, io, path::PathBuf, sync::Arc, time::Duration};
+
+use parking_lot::RwLock;
+
+use crate::jwt::{
+    Algorithm, DecodingKey,
+    jwk::{AlgorithmParameters, EllipticCurve, JwkSet, KeyAlgorithm},
+};
+
+#[derive(Clone)]
+pub(crate) struct Key {
+    pub(crate) key: DecodingKey,
+    pub(crate) algorithms: Vec<Algorithm>,
+}
+
+/// 以 `kid` 为索引的验证密钥
+#[derive(Clone, Default)]
+pub struct KeySet {
+    keys: HashMap<String, Key>,
+}
+
+impl KeySet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 解析 JWKS 文档，没有 `kid` 或不支持的密钥会被忽略
+    pub fn from_json(json: &[u8]) -> io::Result<Self> {
+        let jwks: JwkSet = serde_json::from_slice(json)?;
+        Ok(Self::from_jwks(&jwks))
+    }
+
+    pub fn from_jwks(jwks: &JwkSet) -> Self {
+        let mut set = Self::new();
+        for jwk in &jwks.keys {
+            let Some(kid) = &jwk.common.key_id else {
+                tracing::warn!("Skipping JWK without kid");
+                continue;
+            };
+            let key = match DecodingKey::from_jwk(jwk) {
+                Ok(key) => key,
+                Err(e) => {
+                    tracing::warn!("Skipping JWK {}: {}", kid, e);
+                    continue;
+                }
+            };
+            let algorithms = match jwk.common.key_algorithm {
+                Some(algorithm) => match algorithm_of(algorithm) {
+                    Some(algorithm) => vec![algorithm],
+                    None => {
+                        tracing::warn!("Skipping JWK {} with unsupported algorithm", kid);
+                        continue;
+                    }
+                },
+                None => default_algorithms(&jwk.algorithm),
+            };
+            set.keys.insert(kid.clone(), Key { key, algorithms });
+        }
+        set
+    }
+
+    /// 添加密钥，如配置中的 HMAC 密钥
+    ///
+    /// 需要在刷新后保留的密钥请使用 [`Jwks::with_key`]。
+    pub fn insert(&mut self, kid: impl Into<String>, key: DecodingKey, algorithm: Algorithm) {
+        self.keys.insert(
+            kid.into(),
+            Key {
+                key,
+                algorithms: vec![algorithm],
+            },
+        );
+    }
+
+    /// 合并另一个密钥集，`kid` 相同时以 `other` 为准
+    fn extend(&mut self, other: &KeySet) {
+        self.keys.extend(
+            other
+                .keys
+                .iter()
+                .map(|(kid, key)| (kid.clone(), key.clone())),
+        );
+    }
+
+    pub fn contains(&self, kid: &str) -> bool {
+        self.keys.contains_key(kid)
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub(crate) fn get(&self, kid: &str) -> Option<&Key> {
+        self.keys.get(kid)
+    }
+}
+
+fn algorithm_of(algorithm: KeyAlgorithm) -> Option<Algorithm> {
+    Some(match algorithm {
+        KeyAlgorithm::HS256 => Algorithm::HS256,
+        KeyAlgorithm::HS384 => Algorithm::HS384,
+        KeyAlgorithm::HS512 => Algorithm::HS512,
+        KeyAlgorithm::ES256 => Algorithm::ES256,
+        KeyAlgorithm::ES384 => Algorithm::ES384,
+        KeyAlgorithm::RS256 => Algorithm::RS256,
+        KeyAlgorithm::RS384 => Algorithm::RS384,
+        KeyAlgorithm::RS512 => Algorithm::RS512,
+        KeyAlgorithm::PS256 => Algorithm::PS256,
+        KeyAlgorithm::PS384 => Algorithm::PS384,
+        KeyAlgorithm::PS512 => Algorithm::PS512,
+        KeyAlgorithm::EdDSA => Algorithm::EdDSA,
+        _ => return None,
+    })
+}
+
+// 未指定 `alg` 时按密钥类型允许的算法
+fn default_algorithms(parameters: &AlgorithmParameters) -> Vec<Algorithm> {
+    match parameters {
+        AlgorithmParameters::RSA(_) => vec![
+            Algorithm::RS256,
+            Algorithm::RS384,
+            Algorithm::RS512,
+            Algorithm::PS256,
+            Algorithm::PS384,
+            Algorithm::PS512,
+        ],
+        AlgorithmParameters::EllipticCurve(params) => match params.curve {
+            EllipticCurve::P384 => vec![Algorithm::ES384],
+            _ => vec![Algorithm::ES256],
+        },
+        AlgorithmParameters::OctetKeyPair(_) => vec![Algorithm::EdDSA],
+        AlgorithmParameters::OctetKey(_) => {
+            vec![Algorithm::HS256, Algorithm::HS384, Algorithm::HS512]
+        }
+    }
+}
+
+/// JWKS 文档来源
+#[async_trait::async_trait]
+pub trait JwksSource: Send + Sync + 'static {
+    async fn fetch(&self) -> io::Result<Vec<u8>>;
+}
+
+/// 从本地文件加载
+///
+/// 文件在独立线程中读取，不会阻塞异步任务。
+#[derive(Debug, Clone)]
+pub struct FileSource {
+    path: PathBuf,
+}
+
+impl FileSource {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[async_trait::async_trait]
+impl JwksSource for FileSource {
+    async fn fetch(&self) -> io::Result<Vec<u8>> {
+        let (sender, receiver) = futures::channel::oneshot::channel();
+        let path = self.path.clone();
+        std::thread::spawn(move || {
+            let _ = sender.send(std::fs::read(path));
+        });
+        receiver
+            .await
+            .map_err(|_| io::Error::other("JWKS read thread exited"))?
+    }
+}
+
+/// 通过 HTTP 加载
+///
+/// 请求在独立线程中执行，不会阻塞异步任务。
+#[cfg(feature = "jwks-http")]
+#[derive(Debug, Clone)]
+pub struct HttpSource {
+    url: String,
+    timeout: Duration,
+}
+
+#[cfg(feature = "jwks-http")]
+impl HttpSource {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+#[cfg(feature = "jwks-http")]
+#[async_trait::async_trait]
+impl JwksSource for HttpSource {
+    async fn fetch(&self) -> io::Result<Vec<u8>> {
+        let (sender, receiver) = futures::channel::oneshot::channel();
+        let url = self.url.clone();
+        let timeout = self.timeout;
+        std::thread::spawn(move || {
+            let result = ureq::get(&url)
+                .timeout(timeout)
+                .call()
+                .map_err(io::Error::other)
+                .and_then(|response| {
+                    let mut body = Vec::new();
+                    std::io::Read::read_to_end(&mut response.into_reader(), &mut body)?;
+                    Ok(body)
+                });
+            let _ = sender.send(result);
+        });
+        receiver
+            .await
+            .map_err(|_| io::Error::other("JWKS fetch thread exited"))?
+    }
+}
+
+/// 可刷新的密钥集
+///
+/// 克隆后共享同一份密钥集。
+#[derive(Clone)]
+pub struct Jwks {
+    source: Arc<dyn JwksSource>,
+    /// 配置的静态密钥，每次刷新后合并到密钥集中
+    static_keys: Arc<RwLock<KeySet>>,
+    keys: Arc<RwLock<Arc<KeySet>>>,
+}
+
+impl Jwks {
+    /// 从来源加载密钥集
+    pub async fn load<S: JwksSource>(source: S) -> io::Result<Self> {
+        let keys = KeySet::from_json(&source.fetch().await?)?;
+        Ok(Self {
+            source: Arc::new(source),
+            static_keys: Arc::default(),
+            keys: Arc::new(RwLock::new(Arc::new(keys))),
+        })
+    }
+
+    /// 添加不随刷新变化的密钥，如配置中的 HMAC 密钥
+    ///
+    /// `kid` 与 JWKS 文档中的密钥相同时以此处的密钥为准。
+    pub fn with_key(self, kid: impl Into<String>, key: DecodingKey, algorithm: Algorithm) -> Self {
+        self.static_keys.write().insert(kid, key, algorithm);
+        let mut keys = KeySet::clone(&self.keys());
+        keys.extend(&self.static_keys.read());
+        *self.keys.write() = Arc::new(keys);
+        self
+    }
+
+    /// 当前密钥集的快照
+    pub fn keys(&self) -> Arc<KeySet> {
+        self.keys.read().clone()
+    }
+
+    /// 重新加载密钥集，失败时保留原有密钥
+    pub async fn refresh(&self) -> io::Result<()> {
+        let mut keys = KeySet::from_json(&self.source.fetch().await?)?;
+        if keys.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "JWKS contains no usable keys",
+            ));
+        }
+        keys.extend(&self.static_keys.read());
+        *self.keys.write() = Arc::new(keys);
+        Ok(())
+    }
+
+    /// 定期刷新密钥集
+    ///
+    /// 返回的 future 不会结束，需要在后台任务中运行。
+    pub async fn refresher(self, interval: Duration) {
+        loop {
+            futures_timer::Delay::new(interval).await;
+            if let Err(e) = self.refresh().await {
+                tracing::warn!("Failed to refresh JWKS: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+
+    use super::*;
+
+    const JWKS: &str = r#"{"keys":[{"kty":"oct","kid":"remote","k":"c2VjcmV0"}]}"#;
+    const ROTATED: &str = r#"{"keys":[{"kty":"oct","kid":"rotated","k":"c2VjcmV0"}]}"#;
+
+    #[test]
+    fn static_keys_survive_refresh() {
+        let path = std::env::temp_dir().join(format!("vela-jwks-{}.json", std::process::id()));
+        std::fs::write(&path, JWKS).unwrap();
+
+        let jwks = block_on(Jwks::load(FileSource::new(&path)))
+            .unwrap()
+            .with_key(
+                "local",
+                DecodingKey::from_secret(b"local"),
+                Algorithm::HS256,
+            );
+        assert!(jwks.keys().contains("remote"));
+        assert!(jwks.keys().contains("local"));
+
+        std::fs::write(&path, ROTATED).unwrap();
+        block_on(jwks.refresh()).unwrap();
+        let keys = jwks.keys();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(!keys.contains("remote"));
+        assert!(keys.contains("rotated"));
+        assert!(keys.contains("local"));
+    }
+
+    #[test]
+    fn refresh_keeps_keys_when_document_is_empty() {
+        let path =
+            std::env::temp_dir().join(format!("vela-jwks-empty-{}.json", std::process::id()));
+        std::fs::write(&path, JWKS).unwrap();
+
+        let jwks = block_on(Jwks::load(FileSource::new(&path))).unwrap();
+        std::fs::write(&path, r#"{"keys":[]}"#).unwrap();
+        let result = block_on(jwks.refresh());
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(result.is_err());
+        assert!(jwks.keys().contains("remote"));
+    }
+}
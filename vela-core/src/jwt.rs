@@ -1,14 +1,58 @@
 pub use jsonwebtoken::*;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
+use crate::ids::{PlayerId, SessionId};
+
+/// 令牌声明
+///
+/// `payload` 为自定义声明，会展开到顶层字段中。
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Claims<T> {
-    pub jti: String,
-    pub aud: String,
+pub struct Claims<T = ()> {
+    pub jti: SessionId, // Session ID
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        deserialize_with = "one_or_many"
+    )]
+    pub aud: Vec<String>, // 授权的范围
     pub exp: u64,
+    #[serde(default)]
     pub iat: u64,
-    pub iss: String,
-    pub sub: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>, // 颁发令牌的服务的名称
+    pub sub: PlayerId, // 用户Id
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,
+    /// 以空格分隔的权限范围
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    /// 令牌用途，刷新令牌为 [`REFRESH_TOKEN_TYPE`](crate::issuer::REFRESH_TOKEN_TYPE)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub typ: Option<String>,
     #[serde(flatten)]
     pub payload: T,
 }
+
+impl<T> Claims<T> {
+    pub fn scopes(&self) -> impl Iterator<Item = &str> {
+        self.scope.iter().flat_map(|scope| scope.split_whitespace())
+    }
+}
+
+// `aud` 可以是字符串或字符串数组
+fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(aud) => vec![aud],
+        OneOrMany::Many(aud) => aud,
+    })
+}
@@ -1,4 +1,8 @@
 pub mod authenticate;
+pub mod error;
+pub mod game;
 pub mod ids;
+pub mod issuer;
+pub mod jwks;
 pub mod jwt;
 pub mod session;
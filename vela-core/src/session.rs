@@ -1,6 +1,14 @@
 mod local_registry;
+#[cfg(feature = "redis")]
+mod redis_registry;
 
+use std::time::{Duration, SystemTime};
+
+use futures::{StreamExt, channel::mpsc, stream::BoxStream};
 pub use local_registry::LocalSessionRegistry;
+#[cfg(feature = "redis")]
+pub use redis_registry::RedisSessionRegistry;
+use volans::core::PeerId;
 
 use crate::ids::{PlayerId, SessionId};
 
@@ -8,19 +16,142 @@ use crate::ids::{PlayerId, SessionId};
 pub trait SessionRegistry {
     async fn lookup(&mut self, id: SessionId) -> Option<Session>;
     async fn insert(&mut self, session: Session) -> Result<(), Session>;
+
+    /// 玩家没有其他未过期的会话时插入会话，检查与插入原子执行
+    ///
+    /// 会话已存在或玩家已有其他会话时返回 `Err`。
+    async fn insert_if_absent(&mut self, session: Session) -> Result<(), Session>;
     async fn remove(&mut self, id: SessionId) -> bool;
     async fn single_session(&mut self, player_id: PlayerId, session_id: SessionId) -> Vec<Session>;
+
+    /// 玩家当前的所有会话
+    async fn sessions_of(&mut self, player_id: PlayerId) -> Vec<Session>;
+
+    /// 刷新会话的最后活跃时间，会话不存在时返回 `false`
+    ///
+    /// vela-connect 不会在连接活跃时自动调用，使用 [`reaper`] 时需要在收到心跳或请求时
+    /// 调用，否则保持连接但没有操作的会话也会被清理。
+    async fn touch(&mut self, id: SessionId) -> bool;
+
+    /// 移除空闲超过 `idle_timeout` 或已到期的会话
+    async fn reap(&mut self, idle_timeout: Duration) -> Vec<Session>;
+
+    /// 订阅会话事件
+    ///
+    /// 仅包含当前注册表实例上发生的变更。订阅者积压超过 [`SUBSCRIBER_CAPACITY`]
+    /// 个事件时被断开，返回的 stream 结束，需要及时消费。
+    fn subscribe(&self) -> BoxStream<'static, SessionEvent>;
+}
+
+/// 定期清理空闲会话
+///
+/// 返回的 future 不会结束，需要在后台任务中运行。会话只在
+/// [`SessionRegistry::touch`] 时刷新活跃时间，调用方需要在心跳时刷新仍然连接的会话。
+pub async fn reaper<R>(mut registry: R, interval: Duration, idle_timeout: Duration)
+where
+    R: SessionRegistry + Send,
+{
+    loop {
+        futures_timer::Delay::new(interval).await;
+        let expired = registry.reap(idle_timeout).await;
+        if !expired.is_empty() {
+            tracing::debug!("Reaped {} idle sessions", expired.len());
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum SessionEvent {
+    Inserted(Session),
+    Removed(Session),
+    /// 会话空闲超时或到期
+    Expired(Session),
+    /// 同一玩家建立了新会话，旧会话被踢出
+    Kicked(Session),
+}
+
+impl SessionEvent {
+    pub fn session(&self) -> &Session {
+        match self {
+            SessionEvent::Inserted(session)
+            | SessionEvent::Removed(session)
+            | SessionEvent::Expired(session)
+            | SessionEvent::Kicked(session) => session,
+        }
+    }
+}
+
+/// 每个订阅者最多积压的事件数量
+pub const SUBSCRIBER_CAPACITY: usize = 1024;
+
+/// 会话事件的订阅者列表
+#[derive(Default)]
+pub(crate) struct Subscribers {
+    senders: Vec<mpsc::Sender<SessionEvent>>,
+}
+
+impl Subscribers {
+    pub(crate) fn subscribe(&mut self) -> BoxStream<'static, SessionEvent> {
+        let (sender, receiver) = mpsc::channel(SUBSCRIBER_CAPACITY);
+        self.senders.push(sender);
+        receiver.boxed()
+    }
+
+    /// 积压已满的订阅者被断开，而不是无限占用内存
+    pub(crate) fn emit(&mut self, event: SessionEvent) {
+        self.senders
+            .retain_mut(|sender| match sender.try_send(event.clone()) {
+                Ok(()) => true,
+                Err(e) if e.is_full() => {
+                    tracing::warn!("Session event subscriber lagged behind, disconnecting");
+                    false
+                }
+                Err(_) => false,
+            });
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Session {
     id: SessionId,
     player_id: PlayerId,
+    created_at: SystemTime,
+    last_active: SystemTime,
+    expires_at: Option<SystemTime>,
+    gateway: Option<String>,
+    peer_id: Option<PeerId>,
 }
 
 impl Session {
     pub fn new(id: SessionId, player_id: PlayerId) -> Self {
-        Self { id, player_id }
+        let now = SystemTime::now();
+        Self {
+            id,
+            player_id,
+            created_at: now,
+            last_active: now,
+            expires_at: None,
+            gateway: None,
+            peer_id: None,
+        }
+    }
+
+    /// 会话到期时间，到期后无论是否活跃都会被清理
+    pub fn with_expires_at(mut self, expires_at: SystemTime) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+
+    /// 持有会话的网关实例
+    pub fn with_gateway(mut self, gateway: impl Into<String>) -> Self {
+        self.gateway = Some(gateway.into());
+        self
+    }
+
+    /// 会话所在的连接
+    pub fn with_peer_id(mut self, peer_id: PeerId) -> Self {
+        self.peer_id = Some(peer_id);
+        self
     }
 
     pub fn id(&self) -> &SessionId {
@@ -30,4 +161,116 @@ impl Session {
     pub fn player_id(&self) -> &PlayerId {
         &self.player_id
     }
+
+    pub fn created_at(&self) -> SystemTime {
+        self.created_at
+    }
+
+    pub fn last_active(&self) -> SystemTime {
+        self.last_active
+    }
+
+    pub fn expires_at(&self) -> Option<SystemTime> {
+        self.expires_at
+    }
+
+    pub fn gateway(&self) -> Option<&str> {
+        self.gateway.as_deref()
+    }
+
+    pub fn peer_id(&self) -> Option<PeerId> {
+        self.peer_id
+    }
+
+    pub fn touch(&mut self) {
+        self.last_active = SystemTime::now();
+    }
+
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+
+    /// 是否已空闲超过 `idle_timeout` 或已到期
+    pub fn is_idle(&self, now: SystemTime, idle_timeout: Duration) -> bool {
+        self.is_expired(now)
+            || now
+                .duration_since(self.last_active)
+                .is_ok_and(|idle| idle >= idle_timeout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+
+    use super::*;
+
+    fn session() -> Session {
+        Session::new(SessionId::generate(), PlayerId::generate())
+    }
+
+    #[test]
+    fn session_expires_at_deadline() {
+        let now = SystemTime::now();
+        let session = session().with_expires_at(now + Duration::from_secs(10));
+
+        assert!(!session.is_expired(now));
+        assert!(session.is_expired(now + Duration::from_secs(10)));
+        // 到期的会话无论是否活跃都视为空闲
+        assert!(session.is_idle(now + Duration::from_secs(10), Duration::from_secs(3600)));
+    }
+
+    #[test]
+    fn session_is_idle_after_timeout() {
+        let mut session = session();
+        let later = session.last_active() + Duration::from_secs(30);
+
+        assert!(!session.is_idle(later, Duration::from_secs(60)));
+        assert!(session.is_idle(later, Duration::from_secs(30)));
+
+        session.touch();
+        assert!(!session.is_idle(session.last_active(), Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn lagging_subscriber_is_disconnected() {
+        let mut subscribers = Subscribers::default();
+        let events = subscribers.subscribe();
+        let total = SUBSCRIBER_CAPACITY * 2;
+        for _ in 0..total {
+            subscribers.emit(SessionEvent::Inserted(session()));
+        }
+
+        assert!(subscribers.senders.is_empty());
+        // 已积压的事件仍可读取，之后 stream 结束
+        let received = block_on(events.count());
+        assert!(received >= SUBSCRIBER_CAPACITY && received < total);
+    }
+
+    #[tokio::test]
+    async fn reaper_removes_idle_sessions() {
+        let mut registry = LocalSessionRegistry::new();
+        let mut events = registry.subscribe();
+        let session = session();
+        registry.insert(session.clone()).await.unwrap();
+        assert!(matches!(
+            events.next().await,
+            Some(SessionEvent::Inserted(_))
+        ));
+
+        tokio::spawn(reaper(
+            registry.clone(),
+            Duration::from_millis(10),
+            Duration::from_millis(20),
+        ));
+        let event = tokio::time::timeout(Duration::from_secs(5), events.next())
+            .await
+            .unwrap();
+
+        match event {
+            Some(SessionEvent::Expired(expired)) => assert_eq!(expired.id(), session.id()),
+            event => panic!("unexpected event: {event:?}"),
+        }
+        assert!(registry.lookup(session.id().clone()).await.is_none());
+    }
 }
@@ -1,18 +1,24 @@
 use std::{
     collections::{HashMap, HashSet},
     sync::Arc,
+    time::{Duration, SystemTime},
 };
 
+use futures::stream::BoxStream;
 use parking_lot::Mutex;
 
 use crate::{
     ids::{PlayerId, SessionId},
-    session::{Session, SessionRegistry},
+    session::{Session, SessionEvent, SessionRegistry, Subscribers},
 };
 
+/// 会话表与玩家索引
+///
+/// 两个索引只通过 `insert` 与 `take` 修改，保证始终一致。
 struct Shared {
     sessions: HashMap<SessionId, Session>,
     player_sessions: HashMap<PlayerId, HashSet<SessionId>>,
+    subscribers: Subscribers,
 }
 
 impl Shared {
@@ -20,13 +26,59 @@ impl Shared {
         Self {
             sessions: HashMap::new(),
             player_sessions: HashMap::new(),
+            subscribers: Subscribers::default(),
         }
     }
 
     fn lookup(&self, id: SessionId) -> Option<Session> {
-        self.sessions.get(&id).cloned()
+        self.sessions
+            .get(&id)
+            .filter(|session| !session.is_expired(SystemTime::now()))
+            .cloned()
     }
 
+    fn sessions_of(&self, player_id: &PlayerId) -> Vec<Session> {
+        let now = SystemTime::now();
+        self.player_sessions
+            .get(player_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.sessions.get(id))
+            .filter(|session| !session.is_expired(now))
+            .cloned()
+            .collect()
+    }
+
+    fn touch(&mut self, id: SessionId) -> bool {
+        match self.sessions.get_mut(&id) {
+            Some(session) if !session.is_expired(SystemTime::now()) => {
+                session.touch();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn reap(&mut self, idle_timeout: Duration) -> Vec<Session> {
+        let now = SystemTime::now();
+        let ids: Vec<SessionId> = self
+            .sessions
+            .values()
+            .filter(|session| session.is_idle(now, idle_timeout))
+            .map(|session| session.id.clone())
+            .collect();
+        let mut expired = Vec::with_capacity(ids.len());
+        for id in ids {
+            if let Some(session) = self.take(id) {
+                self.subscribers
+                    .emit(SessionEvent::Expired(session.clone()));
+                expired.push(session);
+            }
+        }
+        expired
+    }
+
+    #[allow(clippy::result_large_err)]
     fn insert(&mut self, session: Session) -> Result<(), Session> {
         if self.sessions.contains_key(&session.id) {
             return Err(session);
@@ -35,52 +87,122 @@ impl Shared {
             .entry(session.player_id.clone())
             .or_default()
             .insert(session.id.clone());
-        let _ = self.sessions.insert(session.id.clone(), session);
+        let _ = self.sessions.insert(session.id.clone(), session.clone());
+        self.subscribers.emit(SessionEvent::Inserted(session));
         Ok(())
     }
 
+    #[allow(clippy::result_large_err)]
+    fn insert_if_absent(&mut self, session: Session) -> Result<(), Session> {
+        let now = SystemTime::now();
+        let exists = self
+            .player_sessions
+            .get(&session.player_id)
+            .into_iter()
+            .flatten()
+            .filter(|id| **id != session.id)
+            .filter_map(|id| self.sessions.get(id))
+            .any(|other| !other.is_expired(now));
+        if exists {
+            return Err(session);
+        }
+        self.insert(session)
+    }
+
     fn remove(&mut self, id: SessionId) -> Option<Session> {
-        if let Some(session) = self.sessions.remove(&id) {
-            self.player_sessions
-                .entry(session.player_id.clone())
-                .or_default()
-                .remove(&id);
-            if let Some(sessions) = self.player_sessions.get_mut(&session.player_id) {
-                if sessions.is_empty() {
-                    self.player_sessions.remove(&session.player_id);
-                }
+        let session = self.take(id)?;
+        self.subscribers
+            .emit(SessionEvent::Removed(session.clone()));
+        Some(session)
+    }
+
+    fn take(&mut self, id: SessionId) -> Option<Session> {
+        let session = self.sessions.remove(&id)?;
+        if let Some(ids) = self.player_sessions.get_mut(&session.player_id) {
+            ids.remove(&id);
+            if ids.is_empty() {
+                self.player_sessions.remove(&session.player_id);
             }
-            Some(session)
-        } else {
-            None
         }
+        Some(session)
     }
 
-    fn single_session(&mut self, player_id: PlayerId, session_id: SessionId) -> Vec<Session> {
-        let mut sessions = Vec::new();
-        if let Some(ids) = self.player_sessions.get(&player_id) {
-            for id in ids {
-                if *id != session_id {
-                    if let Some(session) = self.sessions.remove(id) {
-                        sessions.push(session.clone());
-                    }
-                }
+    /// 移除玩家满足条件的会话，并发送 `Kicked` 事件
+    fn kick(&mut self, player_id: &PlayerId, filter: impl Fn(&SessionId) -> bool) -> Vec<Session> {
+        let ids: Vec<SessionId> = self
+            .player_sessions
+            .get(player_id)
+            .into_iter()
+            .flatten()
+            .filter(|id| filter(id))
+            .cloned()
+            .collect();
+        let mut sessions = Vec::with_capacity(ids.len());
+        for id in ids {
+            if let Some(session) = self.take(id) {
+                self.subscribers.emit(SessionEvent::Kicked(session.clone()));
+                sessions.push(session);
             }
         }
         sessions
     }
+
+    fn single_session(&mut self, player_id: PlayerId, session_id: SessionId) -> Vec<Session> {
+        self.kick(&player_id, |id| *id != session_id)
+    }
+
+    fn remove_player(&mut self, player_id: &PlayerId) -> Vec<Session> {
+        self.kick(player_id, |_| true)
+    }
 }
 
+#[derive(Clone)]
 pub struct LocalSessionRegistry {
     shared: Arc<Mutex<Shared>>,
 }
 
+impl Default for LocalSessionRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl LocalSessionRegistry {
     pub fn new() -> Self {
         Self {
             shared: Arc::new(Mutex::new(Shared::new())),
         }
     }
+
+    /// 未过期的会话数量
+    pub fn count(&self) -> usize {
+        let now = SystemTime::now();
+        let shared = self.shared.lock();
+        shared
+            .sessions
+            .values()
+            .filter(|session| !session.is_expired(now))
+            .count()
+    }
+
+    /// 所有未过期会话的快照
+    pub fn iter(&self) -> impl Iterator<Item = Session> + use<> {
+        let now = SystemTime::now();
+        let sessions: Vec<Session> = self
+            .shared
+            .lock()
+            .sessions
+            .values()
+            .filter(|session| !session.is_expired(now))
+            .cloned()
+            .collect();
+        sessions.into_iter()
+    }
+
+    /// 移除玩家的所有会话，用于管理后台踢人
+    pub fn remove_player(&self, player_id: &PlayerId) -> Vec<Session> {
+        self.shared.lock().remove_player(player_id)
+    }
 }
 
 #[async_trait::async_trait]
@@ -93,6 +215,10 @@ impl SessionRegistry for LocalSessionRegistry {
         let mut shared = self.shared.lock();
         shared.insert(session)
     }
+    async fn insert_if_absent(&mut self, session: Session) -> Result<(), Session> {
+        let mut shared = self.shared.lock();
+        shared.insert_if_absent(session)
+    }
     async fn remove(&mut self, id: SessionId) -> bool {
         let mut shared = self.shared.lock();
         shared.remove(id).is_some()
@@ -101,4 +227,315 @@ impl SessionRegistry for LocalSessionRegistry {
         let mut shared = self.shared.lock();
         shared.single_session(player_id, session_id)
     }
+    async fn sessions_of(&mut self, player_id: PlayerId) -> Vec<Session> {
+        let shared = self.shared.lock();
+        shared.sessions_of(&player_id)
+    }
+    async fn touch(&mut self, id: SessionId) -> bool {
+        let mut shared = self.shared.lock();
+        shared.touch(id)
+    }
+    async fn reap(&mut self, idle_timeout: Duration) -> Vec<Session> {
+        let mut shared = self.shared.lock();
+        shared.reap(idle_timeout)
+    }
+    fn subscribe(&self) -> BoxStream<'static, SessionEvent> {
+        let mut shared = self.shared.lock();
+        shared.subscribers.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{BTreeSet, HashMap},
+        time::UNIX_EPOCH,
+    };
+
+    use futures::{StreamExt, executor::block_on};
+    use proptest::prelude::*;
+
+    use super::*;
+
+    const PLAYERS: usize = 3;
+    const SESSIONS: usize = 6;
+
+    #[derive(Debug, Clone)]
+    enum Op {
+        Insert {
+            session: usize,
+            player: usize,
+            expired: bool,
+        },
+        InsertIfAbsent {
+            session: usize,
+            player: usize,
+            expired: bool,
+        },
+        Remove(usize),
+        SingleSession {
+            player: usize,
+            session: usize,
+        },
+        RemovePlayer(usize),
+        Touch(usize),
+        Reap,
+    }
+
+    fn op() -> impl Strategy<Value = Op> {
+        prop_oneof![
+            (0..SESSIONS, 0..PLAYERS, any::<bool>()).prop_map(|(session, player, expired)| {
+                Op::Insert {
+                    session,
+                    player,
+                    expired,
+                }
+            }),
+            (0..SESSIONS, 0..PLAYERS, any::<bool>()).prop_map(|(session, player, expired)| {
+                Op::InsertIfAbsent {
+                    session,
+                    player,
+                    expired,
+                }
+            }),
+            (0..SESSIONS).prop_map(Op::Remove),
+            (0..PLAYERS, 0..SESSIONS)
+                .prop_map(|(player, session)| Op::SingleSession { player, session }),
+            (0..PLAYERS).prop_map(Op::RemovePlayer),
+            (0..SESSIONS).prop_map(Op::Touch),
+            Just(Op::Reap),
+        ]
+    }
+
+    fn session_id(i: usize) -> SessionId {
+        format!("ss_{i}").parse().unwrap()
+    }
+
+    fn player_id(i: usize) -> PlayerId {
+        format!("py_{i}").parse().unwrap()
+    }
+
+    fn session(session: usize, player: usize, expired: bool) -> Session {
+        let session = Session::new(session_id(session), player_id(player));
+        if expired {
+            session.with_expires_at(UNIX_EPOCH + Duration::from_secs(1))
+        } else {
+            session
+        }
+    }
+
+    /// 会话 -> (玩家, 是否已过期)，过期会话在清理前仍然存在
+    type Model = HashMap<usize, (usize, bool)>;
+
+    fn live(model: &Model, player: usize) -> BTreeSet<SessionId> {
+        model
+            .iter()
+            .filter(|(_, (p, expired))| *p == player && !expired)
+            .map(|(s, _)| session_id(*s))
+            .collect()
+    }
+
+    fn ids(sessions: Vec<Session>) -> BTreeSet<SessionId> {
+        sessions.into_iter().map(|s| s.id).collect()
+    }
+
+    /// 移除模型中满足条件的会话，返回被移除的会话 ID
+    fn drain(
+        model: &mut Model,
+        filter: impl Fn(usize, usize, bool) -> bool,
+    ) -> BTreeSet<SessionId> {
+        let removed: Vec<usize> = model
+            .iter()
+            .filter(|(s, (p, expired))| filter(**s, *p, *expired))
+            .map(|(s, _)| *s)
+            .collect();
+        removed
+            .into_iter()
+            .map(|s| {
+                model.remove(&s);
+                session_id(s)
+            })
+            .collect()
+    }
+
+    fn apply(registry: &mut LocalSessionRegistry, model: &mut Model, op: Op) {
+        match op {
+            Op::Insert {
+                session: s,
+                player,
+                expired,
+            } => {
+                let result = block_on(registry.insert(session(s, player, expired)));
+                assert_eq!(result.is_ok(), !model.contains_key(&s));
+                model.entry(s).or_insert((player, expired));
+            }
+            Op::InsertIfAbsent {
+                session: s,
+                player,
+                expired,
+            } => {
+                let result = block_on(registry.insert_if_absent(session(s, player, expired)));
+                let others = live(model, player)
+                    .into_iter()
+                    .any(|id| id != session_id(s));
+                assert_eq!(result.is_ok(), !model.contains_key(&s) && !others);
+                if result.is_ok() {
+                    model.insert(s, (player, expired));
+                }
+            }
+            Op::Remove(s) => {
+                assert_eq!(
+                    block_on(registry.remove(session_id(s))),
+                    model.remove(&s).is_some()
+                );
+            }
+            Op::SingleSession { player, session } => {
+                let removed =
+                    block_on(registry.single_session(player_id(player), session_id(session)));
+                assert_eq!(
+                    ids(removed),
+                    drain(model, |s, p, _| p == player && s != session)
+                );
+            }
+            Op::RemovePlayer(player) => {
+                let removed = registry.remove_player(&player_id(player));
+                assert_eq!(ids(removed), drain(model, |_, p, _| p == player));
+            }
+            Op::Touch(s) => {
+                assert_eq!(
+                    block_on(registry.touch(session_id(s))),
+                    model.get(&s).is_some_and(|(_, expired)| !expired)
+                );
+            }
+            Op::Reap => {
+                let expired = block_on(registry.reap(Duration::from_secs(3600)));
+                assert_eq!(ids(expired), drain(model, |_, _, expired| expired));
+            }
+        }
+    }
+
+    /// 会话表与玩家索引互相一致，且与模型一致
+    fn check(registry: &mut LocalSessionRegistry, model: &Model) {
+        {
+            let shared = registry.shared.lock();
+            for (id, session) in &shared.sessions {
+                assert_eq!(&session.id, id);
+                assert!(shared.player_sessions[&session.player_id].contains(id));
+            }
+            for (player_id, ids) in &shared.player_sessions {
+                assert!(!ids.is_empty());
+                for id in ids {
+                    assert_eq!(&shared.sessions[id].player_id, player_id);
+                }
+            }
+            assert_eq!(shared.sessions.len(), model.len());
+        }
+        let live_count = model.values().filter(|(_, expired)| !expired).count();
+        assert_eq!(registry.count(), live_count);
+        assert_eq!(registry.iter().count(), live_count);
+        for player in 0..PLAYERS {
+            assert_eq!(
+                ids(block_on(registry.sessions_of(player_id(player)))),
+                live(model, player)
+            );
+        }
+        for s in 0..SESSIONS {
+            assert_eq!(
+                block_on(registry.lookup(session_id(s))).is_some(),
+                model.get(&s).is_some_and(|(_, expired)| !expired)
+            );
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn indexes_stay_consistent(ops in proptest::collection::vec(op(), 1..64)) {
+            let mut registry = LocalSessionRegistry::new();
+            let mut model = Model::new();
+            for op in ops {
+                apply(&mut registry, &mut model, op);
+                check(&mut registry, &model);
+            }
+        }
+    }
+
+    #[test]
+    fn count_skips_expired_sessions() {
+        let mut registry = LocalSessionRegistry::new();
+        block_on(registry.insert(session(0, 0, false))).unwrap();
+        block_on(registry.insert(session(1, 0, true))).unwrap();
+
+        assert_eq!(registry.count(), 1);
+        assert_eq!(block_on(registry.reap(Duration::from_secs(3600))).len(), 1);
+        assert_eq!(registry.count(), 1);
+    }
+
+    #[test]
+    fn reap_keeps_touched_sessions() {
+        let mut registry = LocalSessionRegistry::new();
+        block_on(registry.insert(session(0, 0, false))).unwrap();
+        block_on(registry.insert(session(1, 1, false))).unwrap();
+        let idle_timeout = Duration::from_millis(50);
+
+        assert!(block_on(registry.reap(idle_timeout)).is_empty());
+        std::thread::sleep(idle_timeout);
+        assert!(block_on(registry.touch(session_id(1))));
+
+        let reaped = block_on(registry.reap(idle_timeout));
+        assert_eq!(ids(reaped), BTreeSet::from([session_id(0)]));
+        assert!(block_on(registry.lookup(session_id(1))).is_some());
+        assert!(!block_on(registry.touch(session_id(0))));
+    }
+
+    #[test]
+    fn reap_removes_sessions_past_expires_at() {
+        let mut registry = LocalSessionRegistry::new();
+        let expires_at = SystemTime::now() + Duration::from_millis(20);
+        block_on(registry.insert(session(0, 0, false).with_expires_at(expires_at))).unwrap();
+        assert!(block_on(registry.lookup(session_id(0))).is_some());
+
+        std::thread::sleep(Duration::from_millis(30));
+        // 到期的会话不能再被刷新，即使仍在空闲时间内也会被清理
+        assert!(!block_on(registry.touch(session_id(0))));
+        assert_eq!(
+            ids(block_on(registry.reap(Duration::from_secs(3600)))),
+            BTreeSet::from([session_id(0)])
+        );
+    }
+
+    #[test]
+    fn subscribers_receive_events_in_order() {
+        let mut registry = LocalSessionRegistry::new();
+        let mut events = registry.subscribe();
+        block_on(registry.insert(session(0, 0, false))).unwrap();
+        block_on(registry.insert(session(1, 0, false))).unwrap();
+        block_on(registry.insert(session(2, 1, true))).unwrap();
+        block_on(registry.single_session(player_id(0), session_id(1)));
+        block_on(registry.remove(session_id(1)));
+        block_on(registry.reap(Duration::from_secs(3600)));
+
+        let received: Vec<(&str, SessionId)> = (0..6)
+            .map(|_| {
+                let event = block_on(events.next()).unwrap();
+                let kind = match &event {
+                    SessionEvent::Inserted(_) => "inserted",
+                    SessionEvent::Removed(_) => "removed",
+                    SessionEvent::Expired(_) => "expired",
+                    SessionEvent::Kicked(_) => "kicked",
+                };
+                (kind, event.session().id.clone())
+            })
+            .collect();
+        assert_eq!(
+            received,
+            [
+                ("inserted", session_id(0)),
+                ("inserted", session_id(1)),
+                ("inserted", session_id(2)),
+                ("kicked", session_id(0)),
+                ("removed", session_id(1)),
+                ("expired", session_id(2)),
+            ]
+        );
+    }
 }
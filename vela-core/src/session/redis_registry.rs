@@ -0,0 +1,510 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use futures::stream::BoxStream;
+use parking_lot::Mutex;
+use redis::{AsyncCommands, RedisResult, Script, aio::ConnectionManager};
+use volans::core::PeerId;
+
+use crate::{
+    ids::{PlayerId, SessionId},
+    session::{Session, SessionEvent, SessionRegistry, Subscribers},
+};
+
+// 所有脚本访问的键都通过 KEYS 传入，玩家会话集合的过期时间只延长不缩短
+
+// KEYS[1] 会话键 KEYS[2] 玩家会话集合
+// ARGV[1] 会话 ID ARGV[2] 过期时间（毫秒） ARGV[3..] 会话字段
+static INSERT: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r#"
+if redis.call('EXISTS', KEYS[1]) == 1 then
+    return 0
+end
+redis.call('HSET', KEYS[1], unpack(ARGV, 3))
+redis.call('PEXPIRE', KEYS[1], ARGV[2])
+local ttl = redis.call('PTTL', KEYS[2])
+redis.call('SADD', KEYS[2], ARGV[1])
+if ttl ~= -1 and ttl < tonumber(ARGV[2]) then
+    redis.call('PEXPIRE', KEYS[2], ARGV[2])
+end
+return 1
+"#,
+    )
+});
+
+// KEYS[1] 会话键 KEYS[2] 玩家会话集合
+// ARGV[1] 会话 ID ARGV[2] 玩家 ID
+// 返回被移除会话的字段，会话不属于该玩家时返回错误
+static REMOVE: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r#"
+local player_id = redis.call('HGET', KEYS[1], 'player_id')
+if not player_id then
+    return {}
+end
+if player_id ~= ARGV[2] then
+    return redis.error_reply('session owner changed')
+end
+local fields = redis.call('HGETALL', KEYS[1])
+redis.call('DEL', KEYS[1])
+redis.call('SREM', KEYS[2], ARGV[1])
+if redis.call('SCARD', KEYS[2]) == 0 then
+    redis.call('DEL', KEYS[2])
+end
+return fields
+"#,
+    )
+});
+
+// KEYS[1] 会话键 KEYS[2] 玩家会话集合
+// ARGV[1] 当前时间（毫秒） ARGV[2] 过期时间（毫秒） ARGV[3] 玩家 ID
+static TOUCH: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r#"
+local player_id = redis.call('HGET', KEYS[1], 'player_id')
+if not player_id then
+    return 0
+end
+if player_id ~= ARGV[3] then
+    return redis.error_reply('session owner changed')
+end
+local ttl = tonumber(ARGV[2])
+local expires_at = redis.call('HGET', KEYS[1], 'expires_at')
+if expires_at then
+    ttl = math.max(math.min(ttl, tonumber(expires_at) - tonumber(ARGV[1])), 1)
+end
+redis.call('HSET', KEYS[1], 'last_active', ARGV[1])
+redis.call('PEXPIRE', KEYS[1], ttl)
+local player_ttl = redis.call('PTTL', KEYS[2])
+if player_ttl ~= -1 and player_ttl < ttl then
+    redis.call('PEXPIRE', KEYS[2], ttl)
+end
+return 1
+"#,
+    )
+});
+
+// KEYS[1] 玩家会话集合 KEYS[2..] 待移除的会话键
+// ARGV[1] 保留的会话 ID ARGV[2..] 与 KEYS[2..] 对应的会话 ID
+// 返回 {是否完成, 被移除会话的字段列表}，集合中出现未传入的会话时不做修改并返回未完成
+static SINGLE_SESSION: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r#"
+local declared = {}
+for i = 2, #ARGV do
+    declared[ARGV[i]] = true
+end
+for _, id in ipairs(redis.call('SMEMBERS', KEYS[1])) do
+    if id ~= ARGV[1] and not declared[id] then
+        return {0, {}}
+    end
+end
+local removed = {}
+for i = 2, #ARGV do
+    if ARGV[i] ~= ARGV[1] then
+        local fields = redis.call('HGETALL', KEYS[i])
+        if #fields > 0 then
+            redis.call('DEL', KEYS[i])
+            table.insert(removed, fields)
+        end
+        redis.call('SREM', KEYS[1], ARGV[i])
+    end
+end
+return {1, removed}
+"#,
+    )
+});
+
+// KEYS[1] 会话键 KEYS[2] 玩家会话集合 KEYS[3..] 玩家其他会话的键
+// ARGV[1] 会话 ID ARGV[2] 过期时间（毫秒） ARGV[3] 其他会话数量 n
+// ARGV[4..3+n] 与 KEYS[3..] 对应的会话 ID ARGV[4+n..] 会话字段
+// 返回 1 已插入，0 会话已存在或玩家有其他会话，-1 集合中出现未传入的会话
+static INSERT_IF_ABSENT: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r#"
+if redis.call('EXISTS', KEYS[1]) == 1 then
+    return 0
+end
+local count = tonumber(ARGV[3])
+local declared = {}
+for i = 1, count do
+    declared[ARGV[3 + i]] = true
+end
+for _, id in ipairs(redis.call('SMEMBERS', KEYS[2])) do
+    if id ~= ARGV[1] and not declared[id] then
+        return -1
+    end
+end
+for i = 3, count + 2 do
+    if redis.call('EXISTS', KEYS[i]) == 1 then
+        return 0
+    end
+end
+redis.call('HSET', KEYS[1], unpack(ARGV, 4 + count))
+redis.call('PEXPIRE', KEYS[1], ARGV[2])
+local ttl = redis.call('PTTL', KEYS[2])
+redis.call('SADD', KEYS[2], ARGV[1])
+if ttl ~= -1 and ttl < tonumber(ARGV[2]) then
+    redis.call('PEXPIRE', KEYS[2], ARGV[2])
+end
+return 1
+"#,
+    )
+});
+
+/// 玩家会话集合在读取后被修改时脚本的最大重试次数
+const SCRIPT_ATTEMPTS: usize = 3;
+
+/// 基于 Redis 的会话注册表
+///
+/// 多个网关实例共享同一份会话数据，插入、移除与单会话清理均通过 Lua 脚本原子执行。
+/// 会话在 `ttl` 内没有 [`touch`](SessionRegistry::touch) 即由 Redis 过期，
+/// 因此 [`reap`](SessionRegistry::reap) 不做处理，也不会产生 `Expired` 事件。
+///
+/// 脚本访问的键均通过 `KEYS` 声明。使用 Redis Cluster 时请通过
+/// [`with_prefix`](Self::with_prefix) 设置带哈希标签的前缀（如 `{vela}:`），
+/// 使所有键落在同一个槽位。
+///
+/// 注册表接口不返回错误，Redis 错误会记录日志并按操作失败处理。
+#[derive(Clone)]
+pub struct RedisSessionRegistry {
+    conn: ConnectionManager,
+    prefix: String,
+    ttl: Duration,
+    subscribers: Arc<Mutex<Subscribers>>,
+}
+
+impl RedisSessionRegistry {
+    pub fn new(conn: ConnectionManager) -> Self {
+        Self {
+            conn,
+            prefix: "vela:".to_string(),
+            ttl: Duration::from_secs(24 * 60 * 60),
+            subscribers: Arc::new(Mutex::new(Subscribers::default())),
+        }
+    }
+
+    /// 连接到 Redis
+    pub async fn connect(url: &str) -> RedisResult<Self> {
+        let client = redis::Client::open(url)?;
+        let conn = ConnectionManager::new(client).await?;
+        Ok(Self::new(conn))
+    }
+
+    /// 键前缀，默认为 `vela:`
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// 会话空闲过期时间，默认为 24 小时
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    fn session_prefix(&self) -> String {
+        format!("{}session:", self.prefix)
+    }
+
+    fn player_prefix(&self) -> String {
+        format!("{}player:", self.prefix)
+    }
+
+    fn session_key(&self, id: &SessionId) -> String {
+        format!("{}{}", self.session_prefix(), id)
+    }
+
+    fn player_key(&self, id: &PlayerId) -> String {
+        format!("{}{}", self.player_prefix(), id)
+    }
+
+    fn emit(&self, event: SessionEvent) {
+        self.subscribers.lock().emit(event);
+    }
+
+    async fn try_lookup(&mut self, id: &SessionId) -> RedisResult<Option<Session>> {
+        let key = self.session_key(id);
+        let fields: HashMap<String, String> = self.conn.hgetall(key).await?;
+        Ok(from_fields(id.clone(), fields))
+    }
+
+    /// 会话在 Redis 中的过期时间（毫秒）
+    fn session_ttl(&self, session: &Session) -> u64 {
+        let mut ttl = self.ttl;
+        if let Some(expires_at) = session.expires_at {
+            ttl = ttl.min(
+                expires_at
+                    .duration_since(SystemTime::now())
+                    .unwrap_or_default(),
+            );
+        }
+        (ttl.as_millis() as u64).max(1)
+    }
+
+    async fn try_insert(&mut self, session: &Session) -> RedisResult<bool> {
+        INSERT
+            .key(self.session_key(&session.id))
+            .key(self.player_key(&session.player_id))
+            .arg(session.id.as_str())
+            .arg(self.session_ttl(session))
+            .arg(to_fields(session))
+            .invoke_async(&mut self.conn)
+            .await
+    }
+
+    async fn try_insert_if_absent(&mut self, session: &Session) -> RedisResult<bool> {
+        let player_key = self.player_key(&session.player_id);
+        for _ in 0..SCRIPT_ATTEMPTS {
+            let ids: Vec<String> = self.conn.smembers(&player_key).await?;
+            let others: Vec<&String> = ids
+                .iter()
+                .filter(|id| id.as_str() != session.id.as_str())
+                .collect();
+            let mut script = INSERT_IF_ABSENT.key(self.session_key(&session.id));
+            script
+                .key(&player_key)
+                .arg(session.id.as_str())
+                .arg(self.session_ttl(session))
+                .arg(others.len());
+            for id in &others {
+                script.key(format!("{}{}", self.session_prefix(), id));
+                script.arg(id.as_str());
+            }
+            script.arg(to_fields(session));
+            let inserted: i64 = script.invoke_async(&mut self.conn).await?;
+            if inserted >= 0 {
+                return Ok(inserted == 1);
+            }
+        }
+        Err((redis::ErrorKind::TryAgain, "player sessions kept changing").into())
+    }
+
+    async fn try_remove(&mut self, id: &SessionId) -> RedisResult<Option<Session>> {
+        let key = self.session_key(id);
+        let Some(player_id) = self.try_owner(&key).await? else {
+            return Ok(None);
+        };
+        let fields: HashMap<String, String> = REMOVE
+            .key(key)
+            .key(self.player_key(&player_id))
+            .arg(id.as_str())
+            .arg(player_id.as_str())
+            .invoke_async(&mut self.conn)
+            .await?;
+        Ok(from_fields(id.clone(), fields))
+    }
+
+    /// 会话所属的玩家，脚本需要据此声明玩家会话集合的键
+    async fn try_owner(&mut self, key: &str) -> RedisResult<Option<PlayerId>> {
+        let player_id: Option<String> = self.conn.hget(key, "player_id").await?;
+        Ok(player_id.and_then(|id| id.parse().ok()))
+    }
+
+    async fn try_sessions_of(&mut self, player_id: &PlayerId) -> RedisResult<Vec<Session>> {
+        let ids: Vec<String> = self.conn.smembers(self.player_key(player_id)).await?;
+        let mut sessions = Vec::with_capacity(ids.len());
+        for id in ids {
+            let Ok(id) = id.parse() else {
+                continue;
+            };
+            if let Some(session) = self.try_lookup(&id).await? {
+                sessions.push(session);
+            }
+        }
+        Ok(sessions)
+    }
+
+    async fn try_touch(&mut self, id: &SessionId) -> RedisResult<bool> {
+        let key = self.session_key(id);
+        let Some(player_id) = self.try_owner(&key).await? else {
+            return Ok(false);
+        };
+        TOUCH
+            .key(key)
+            .key(self.player_key(&player_id))
+            .arg(to_millis(SystemTime::now()))
+            .arg(self.ttl.as_millis() as u64)
+            .arg(player_id.as_str())
+            .invoke_async(&mut self.conn)
+            .await
+    }
+
+    async fn try_single_session(
+        &mut self,
+        player_id: &PlayerId,
+        session_id: &SessionId,
+    ) -> RedisResult<Vec<Session>> {
+        let player_key = self.player_key(player_id);
+        for _ in 0..SCRIPT_ATTEMPTS {
+            let ids: Vec<String> = self.conn.smembers(&player_key).await?;
+            let mut script = SINGLE_SESSION.key(&player_key);
+            script.arg(session_id.as_str());
+            for id in &ids {
+                script.key(format!("{}{}", self.session_prefix(), id));
+                script.arg(id);
+            }
+            let (complete, removed): (bool, Vec<HashMap<String, String>>) =
+                script.invoke_async(&mut self.conn).await?;
+            if !complete {
+                continue;
+            }
+            return Ok(removed
+                .into_iter()
+                .filter_map(|fields| {
+                    let id = fields.get("id")?.parse().ok()?;
+                    from_fields(id, fields)
+                })
+                .collect());
+        }
+        Err((redis::ErrorKind::TryAgain, "player sessions kept changing").into())
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionRegistry for RedisSessionRegistry {
+    async fn lookup(&mut self, id: SessionId) -> Option<Session> {
+        self.try_lookup(&id).await.unwrap_or_else(|e| {
+            tracing::error!("Failed to lookup session {}: {}", id, e);
+            None
+        })
+    }
+
+    async fn insert(&mut self, session: Session) -> Result<(), Session> {
+        match self.try_insert(&session).await {
+            Ok(true) => {
+                self.emit(SessionEvent::Inserted(session));
+                Ok(())
+            }
+            Ok(false) => Err(session),
+            Err(e) => {
+                tracing::error!("Failed to insert session {}: {}", session.id, e);
+                Err(session)
+            }
+        }
+    }
+
+    async fn insert_if_absent(&mut self, session: Session) -> Result<(), Session> {
+        match self.try_insert_if_absent(&session).await {
+            Ok(true) => {
+                self.emit(SessionEvent::Inserted(session));
+                Ok(())
+            }
+            Ok(false) => Err(session),
+            Err(e) => {
+                tracing::error!("Failed to insert session {}: {}", session.id, e);
+                Err(session)
+            }
+        }
+    }
+
+    async fn remove(&mut self, id: SessionId) -> bool {
+        match self.try_remove(&id).await {
+            Ok(Some(session)) => {
+                self.emit(SessionEvent::Removed(session));
+                true
+            }
+            Ok(None) => false,
+            Err(e) => {
+                tracing::error!("Failed to remove session {}: {}", id, e);
+                false
+            }
+        }
+    }
+
+    async fn single_session(&mut self, player_id: PlayerId, session_id: SessionId) -> Vec<Session> {
+        match self.try_single_session(&player_id, &session_id).await {
+            Ok(sessions) => {
+                for session in &sessions {
+                    self.emit(SessionEvent::Kicked(session.clone()));
+                }
+                sessions
+            }
+            Err(e) => {
+                tracing::error!("Failed to clear sessions of {}: {}", player_id, e);
+                Vec::new()
+            }
+        }
+    }
+
+    async fn sessions_of(&mut self, player_id: PlayerId) -> Vec<Session> {
+        self.try_sessions_of(&player_id).await.unwrap_or_else(|e| {
+            tracing::error!("Failed to list sessions of {}: {}", player_id, e);
+            Vec::new()
+        })
+    }
+
+    async fn touch(&mut self, id: SessionId) -> bool {
+        self.try_touch(&id).await.unwrap_or_else(|e| {
+            tracing::error!("Failed to touch session {}: {}", id, e);
+            false
+        })
+    }
+
+    async fn reap(&mut self, _idle_timeout: Duration) -> Vec<Session> {
+        Vec::new()
+    }
+
+    fn subscribe(&self) -> BoxStream<'static, SessionEvent> {
+        self.subscribers.lock().subscribe()
+    }
+}
+
+fn to_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+fn from_millis(millis: &str) -> Option<SystemTime> {
+    millis
+        .parse()
+        .ok()
+        .map(|millis| UNIX_EPOCH + Duration::from_millis(millis))
+}
+
+fn to_fields(session: &Session) -> Vec<(&'static str, String)> {
+    let mut fields = vec![
+        ("id", session.id.to_string()),
+        ("player_id", session.player_id.to_string()),
+        ("created_at", to_millis(session.created_at).to_string()),
+        ("last_active", to_millis(session.last_active).to_string()),
+    ];
+    if let Some(expires_at) = session.expires_at {
+        fields.push(("expires_at", to_millis(expires_at).to_string()));
+    }
+    if let Some(gateway) = &session.gateway {
+        fields.push(("gateway", gateway.clone()));
+    }
+    if let Some(peer_id) = session.peer_id {
+        fields.push(("peer_id", peer_id.to_base58()));
+    }
+    fields
+}
+
+fn from_fields(id: SessionId, fields: HashMap<String, String>) -> Option<Session> {
+    let player_id = fields.get("player_id")?.parse().ok()?;
+    let mut session = Session::new(id, player_id);
+    if let Some(created_at) = fields.get("created_at").and_then(|v| from_millis(v)) {
+        session.created_at = created_at;
+    }
+    if let Some(last_active) = fields.get("last_active").and_then(|v| from_millis(v)) {
+        session.last_active = last_active;
+    }
+    session.expires_at = fields.get("expires_at").and_then(|v| from_millis(v));
+    session.gateway = fields.get("gateway").cloned();
+    session.peer_id = fields
+        .get("peer_id")
+        .and_then(|v| bs58::decode(v).into_vec().ok()?.try_into().ok())
+        .map(PeerId::from_bytes);
+    Some(session)
+}
+
+#[cfg(test)]
+mod memory_server;
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,515 @@
+//! 测试用的内存 Redis 服务
+//!
+//! 只实现注册表用到的命令，脚本由 Lua 5.1 执行。脚本中 `redis.call`
+//! 访问未在 `KEYS` 中声明的键时报错，与 Redis Cluster 的要求一致。
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    io,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use mlua::{Lua, MultiValue, Value};
+use parking_lot::Mutex;
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Reply {
+    Status(String),
+    Error(String),
+    Integer(i64),
+    Bulk(Option<String>),
+    Array(Vec<Reply>),
+}
+
+impl Reply {
+    fn ok() -> Self {
+        Reply::Status("OK".to_string())
+    }
+
+    fn error(message: impl Into<String>) -> Self {
+        Reply::Error(message.into())
+    }
+
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            Reply::Status(status) => buf.extend_from_slice(format!("+{status}\r\n").as_bytes()),
+            Reply::Error(error) => buf.extend_from_slice(format!("-{error}\r\n").as_bytes()),
+            Reply::Integer(value) => buf.extend_from_slice(format!(":{value}\r\n").as_bytes()),
+            Reply::Bulk(None) => buf.extend_from_slice(b"$-1\r\n"),
+            Reply::Bulk(Some(value)) => {
+                buf.extend_from_slice(format!("${}\r\n{value}\r\n", value.len()).as_bytes())
+            }
+            Reply::Array(items) => {
+                buf.extend_from_slice(format!("*{}\r\n", items.len()).as_bytes());
+                for item in items {
+                    item.encode(buf);
+                }
+            }
+        }
+    }
+}
+
+enum Data {
+    Hash(HashMap<String, String>),
+    Set(BTreeSet<String>),
+}
+
+struct Entry {
+    data: Data,
+    expires_at: Option<Instant>,
+}
+
+#[derive(Default)]
+struct Store {
+    entries: HashMap<String, Entry>,
+    scripts: HashMap<String, String>,
+    /// 模拟的时间偏移，用于测试过期
+    elapsed: Duration,
+}
+
+const WRONG_TYPE: &str = "WRONGTYPE Operation against a key holding the wrong kind of value";
+
+impl Store {
+    fn now(&self) -> Instant {
+        Instant::now() + self.elapsed
+    }
+
+    /// 取出未过期的键，已过期的键被惰性删除
+    fn entry(&mut self, key: &str) -> Option<&mut Entry> {
+        let now = self.now();
+        if self
+            .entries
+            .get(key)
+            .and_then(|entry| entry.expires_at)
+            .is_some_and(|expires_at| expires_at <= now)
+        {
+            self.entries.remove(key);
+        }
+        self.entries.get_mut(key)
+    }
+
+    fn hash(&mut self, key: &str) -> Result<Option<&mut HashMap<String, String>>, Reply> {
+        match self.entry(key) {
+            None => Ok(None),
+            Some(Entry {
+                data: Data::Hash(hash),
+                ..
+            }) => Ok(Some(hash)),
+            Some(_) => Err(Reply::error(WRONG_TYPE)),
+        }
+    }
+
+    fn set(&mut self, key: &str) -> Result<Option<&mut BTreeSet<String>>, Reply> {
+        match self.entry(key) {
+            None => Ok(None),
+            Some(Entry {
+                data: Data::Set(set),
+                ..
+            }) => Ok(Some(set)),
+            Some(_) => Err(Reply::error(WRONG_TYPE)),
+        }
+    }
+
+    fn insert(&mut self, key: &str, data: Data) -> &mut Data {
+        &mut self
+            .entries
+            .entry(key.to_string())
+            .or_insert(Entry {
+                data,
+                expires_at: None,
+            })
+            .data
+    }
+
+    fn execute(&mut self, args: &[String]) -> Reply {
+        self.try_execute(args, None).unwrap_or_else(|error| error)
+    }
+
+    /// 执行命令，`declared` 为脚本声明的键
+    fn try_execute(
+        &mut self,
+        args: &[String],
+        declared: Option<&[String]>,
+    ) -> Result<Reply, Reply> {
+        let Some((command, args)) = args.split_first() else {
+            return Err(Reply::error("ERR empty command"));
+        };
+        let command = command.to_ascii_uppercase();
+        if let Some(declared) = declared {
+            let keys = match command.as_str() {
+                "DEL" | "EXISTS" => args,
+                _ => &args[..args.len().min(1)],
+            };
+            if let Some(key) = keys.iter().find(|key| !declared.contains(key)) {
+                return Err(Reply::error(format!(
+                    "ERR script accessed undeclared key '{key}'"
+                )));
+            }
+        }
+        let arity = |min: usize| {
+            if args.len() < min {
+                Err(Reply::error(format!(
+                    "ERR wrong number of arguments for '{command}' command"
+                )))
+            } else {
+                Ok(())
+            }
+        };
+        match command.as_str() {
+            "PING" => Ok(Reply::Status("PONG".to_string())),
+            "CLIENT" | "SELECT" => Ok(Reply::ok()),
+            "EXISTS" => {
+                arity(1)?;
+                let count = args.iter().filter(|key| self.entry(key).is_some()).count();
+                Ok(Reply::Integer(count as i64))
+            }
+            "DEL" => {
+                arity(1)?;
+                let count = args
+                    .iter()
+                    .filter(|key| self.entry(key).is_some() && self.entries.remove(*key).is_some())
+                    .count();
+                Ok(Reply::Integer(count as i64))
+            }
+            "PEXPIRE" => {
+                arity(2)?;
+                let millis: u64 = args[1]
+                    .parse()
+                    .map_err(|_| Reply::error("ERR value is not an integer or out of range"))?;
+                let expires_at = self.now() + Duration::from_millis(millis);
+                Ok(match self.entry(&args[0]) {
+                    Some(entry) => {
+                        entry.expires_at = Some(expires_at);
+                        Reply::Integer(1)
+                    }
+                    None => Reply::Integer(0),
+                })
+            }
+            "PTTL" => {
+                arity(1)?;
+                let now = self.now();
+                Ok(Reply::Integer(match self.entry(&args[0]) {
+                    None => -2,
+                    Some(Entry {
+                        expires_at: None, ..
+                    }) => -1,
+                    Some(Entry {
+                        expires_at: Some(expires_at),
+                        ..
+                    }) => expires_at.saturating_duration_since(now).as_millis() as i64,
+                }))
+            }
+            "HSET" => {
+                arity(3)?;
+                if args.len() % 2 == 0 {
+                    return Err(Reply::error(
+                        "ERR wrong number of arguments for 'HSET' command",
+                    ));
+                }
+                self.hash(&args[0])?;
+                let Data::Hash(hash) = self.insert(&args[0], Data::Hash(HashMap::new())) else {
+                    unreachable!("type checked above");
+                };
+                let added = args[1..]
+                    .chunks(2)
+                    .filter(|pair| hash.insert(pair[0].clone(), pair[1].clone()).is_none())
+                    .count();
+                Ok(Reply::Integer(added as i64))
+            }
+            "HGET" => {
+                arity(2)?;
+                let value = self
+                    .hash(&args[0])?
+                    .and_then(|hash| hash.get(&args[1]).cloned());
+                Ok(Reply::Bulk(value))
+            }
+            "HGETALL" => {
+                arity(1)?;
+                let fields = self
+                    .hash(&args[0])?
+                    .map(|hash| {
+                        hash.iter()
+                            .flat_map(|(field, value)| [field.clone(), value.clone()])
+                            .map(|value| Reply::Bulk(Some(value)))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                Ok(Reply::Array(fields))
+            }
+            "SADD" => {
+                arity(2)?;
+                self.set(&args[0])?;
+                let Data::Set(set) = self.insert(&args[0], Data::Set(BTreeSet::new())) else {
+                    unreachable!("type checked above");
+                };
+                let added = args[1..]
+                    .iter()
+                    .filter(|member| set.insert(member.to_string()))
+                    .count();
+                Ok(Reply::Integer(added as i64))
+            }
+            "SREM" => {
+                arity(2)?;
+                let Some(set) = self.set(&args[0])? else {
+                    return Ok(Reply::Integer(0));
+                };
+                let removed = args[1..]
+                    .iter()
+                    .filter(|member| set.remove(*member))
+                    .count();
+                if set.is_empty() {
+                    self.entries.remove(&args[0]);
+                }
+                Ok(Reply::Integer(removed as i64))
+            }
+            "SCARD" => {
+                arity(1)?;
+                let count = self.set(&args[0])?.map_or(0, |set| set.len());
+                Ok(Reply::Integer(count as i64))
+            }
+            "SMEMBERS" => {
+                arity(1)?;
+                let members = self
+                    .set(&args[0])?
+                    .map(|set| set.iter().map(|m| Reply::Bulk(Some(m.clone()))).collect())
+                    .unwrap_or_default();
+                Ok(Reply::Array(members))
+            }
+            "SCRIPT" if declared.is_none() => {
+                arity(2)?;
+                if !args[0].eq_ignore_ascii_case("LOAD") {
+                    return Err(Reply::error("ERR unknown SCRIPT subcommand"));
+                }
+                let sha = sha1_smol::Sha1::from(&args[1]).digest().to_string();
+                self.scripts.insert(sha.clone(), args[1].clone());
+                Ok(Reply::Bulk(Some(sha)))
+            }
+            "EVALSHA" if declared.is_none() => {
+                arity(2)?;
+                let Some(script) = self.scripts.get(&args[0].to_ascii_lowercase()).cloned() else {
+                    return Err(Reply::error(
+                        "NOSCRIPT No matching script. Please use EVAL.",
+                    ));
+                };
+                self.eval(&script, &args[1..])
+            }
+            "EVAL" if declared.is_none() => {
+                arity(2)?;
+                let sha = sha1_smol::Sha1::from(&args[0]).digest().to_string();
+                self.scripts.insert(sha, args[0].clone());
+                self.eval(&args[0], &args[1..])
+            }
+            _ => Err(Reply::error(format!("ERR unknown command '{command}'"))),
+        }
+    }
+
+    fn eval(&mut self, script: &str, args: &[String]) -> Result<Reply, Reply> {
+        let count: usize = args[0]
+            .parse()
+            .map_err(|_| Reply::error("ERR value is not an integer or out of range"))?;
+        if count > args.len() - 1 {
+            return Err(Reply::error(
+                "ERR Number of keys can't be greater than number of args",
+            ));
+        }
+        let (keys, argv) = args[1..].split_at(count);
+        let lua = Lua::new();
+        let result = lua.scope(|scope| {
+            let redis = lua.create_table()?;
+            let call = scope.create_function_mut(|lua, args: MultiValue| {
+                let args = args
+                    .into_iter()
+                    .map(|arg| match arg {
+                        Value::String(s) => Ok(s.to_string_lossy()),
+                        Value::Integer(i) => Ok(i.to_string()),
+                        Value::Number(n) => Ok((n as i64).to_string()),
+                        _ => Err(mlua::Error::runtime(
+                            "Lua redis() command arguments must be strings or integers",
+                        )),
+                    })
+                    .collect::<mlua::Result<Vec<_>>>()?;
+                match self.try_execute(&args, Some(keys)) {
+                    Ok(reply) => to_lua(lua, reply),
+                    Err(Reply::Error(error)) => Err(mlua::Error::runtime(error)),
+                    Err(reply) => to_lua(lua, reply),
+                }
+            })?;
+            redis.set("call", call)?;
+            redis.set(
+                "error_reply",
+                lua.create_function(|lua, message: String| {
+                    let table = lua.create_table()?;
+                    table.set("err", message)?;
+                    Ok(table)
+                })?,
+            )?;
+            redis.set(
+                "status_reply",
+                lua.create_function(|lua, message: String| {
+                    let table = lua.create_table()?;
+                    table.set("ok", message)?;
+                    Ok(table)
+                })?,
+            )?;
+            let globals = lua.globals();
+            globals.set("redis", redis)?;
+            globals.set("KEYS", keys.to_vec())?;
+            globals.set("ARGV", argv.to_vec())?;
+            let value: Value = lua.load(script).eval()?;
+            Ok(from_lua(value))
+        });
+        result.map_err(|e| Reply::error(format!("ERR Error running script: {e}")))
+    }
+}
+
+fn to_lua(lua: &Lua, reply: Reply) -> mlua::Result<Value> {
+    Ok(match reply {
+        Reply::Status(status) => {
+            let table = lua.create_table()?;
+            table.set("ok", status)?;
+            Value::Table(table)
+        }
+        Reply::Error(error) => {
+            let table = lua.create_table()?;
+            table.set("err", error)?;
+            Value::Table(table)
+        }
+        Reply::Integer(value) => Value::Integer(value),
+        Reply::Bulk(None) => Value::Boolean(false),
+        Reply::Bulk(Some(value)) => Value::String(lua.create_string(value)?),
+        Reply::Array(items) => {
+            let table = lua.create_table()?;
+            for (i, item) in items.into_iter().enumerate() {
+                table.set(i + 1, to_lua(lua, item)?)?;
+            }
+            Value::Table(table)
+        }
+    })
+}
+
+fn from_lua(value: Value) -> Reply {
+    match value {
+        Value::Integer(value) => Reply::Integer(value),
+        Value::Number(value) => Reply::Integer(value as i64),
+        Value::Boolean(true) => Reply::Integer(1),
+        Value::String(s) => Reply::Bulk(Some(s.to_string_lossy())),
+        Value::Table(table) => {
+            if let Ok(Some(error)) = table.get::<Option<String>>("err") {
+                return Reply::Error(error);
+            }
+            if let Ok(Some(status)) = table.get::<Option<String>>("ok") {
+                return Reply::Status(status);
+            }
+            let mut items = Vec::new();
+            for i in 1.. {
+                match table.get::<Value>(i) {
+                    Ok(Value::Nil) | Err(_) => break,
+                    Ok(value) => items.push(from_lua(value)),
+                }
+            }
+            Reply::Array(items)
+        }
+        _ => Reply::Bulk(None),
+    }
+}
+
+/// 在本地端口上运行的内存 Redis 服务
+#[derive(Clone)]
+pub(super) struct MemoryServer {
+    addr: SocketAddr,
+    store: Arc<Mutex<Store>>,
+}
+
+impl MemoryServer {
+    pub(super) async fn start() -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let server = Self {
+            addr: listener.local_addr()?,
+            store: Arc::default(),
+        };
+        let store = server.store.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, store.clone()));
+            }
+        });
+        Ok(server)
+    }
+
+    pub(super) fn url(&self) -> String {
+        format!("redis://{}", self.addr)
+    }
+
+    /// 将服务端时间向前推进
+    pub(super) fn advance(&self, duration: Duration) {
+        self.store.lock().elapsed += duration;
+    }
+
+    pub(super) fn exists(&self, key: &str) -> bool {
+        self.store.lock().entry(key).is_some()
+    }
+
+    /// 键的剩余过期时间，不存在或未设置过期时间时返回 `None`
+    pub(super) fn pttl(&self, key: &str) -> Option<Duration> {
+        match self
+            .store
+            .lock()
+            .execute(&["PTTL".to_string(), key.to_string()])
+        {
+            Reply::Integer(millis) if millis >= 0 => Some(Duration::from_millis(millis as u64)),
+            _ => None,
+        }
+    }
+}
+
+async fn serve(stream: TcpStream, store: Arc<Mutex<Store>>) -> io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    while let Some(args) = read_command(&mut reader).await? {
+        let reply = store.lock().execute(&args);
+        let mut buf = Vec::new();
+        reply.encode(&mut buf);
+        writer.write_all(&buf).await?;
+    }
+    Ok(())
+}
+
+async fn read_line<R: AsyncBufRead + Unpin>(reader: &mut R) -> io::Result<Option<String>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line).await? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(line.trim_end_matches("\r\n").to_string()))
+}
+
+async fn read_command<R: AsyncBufRead + Unpin>(reader: &mut R) -> io::Result<Option<Vec<String>>> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+    let Some(header) = read_line(reader).await? else {
+        return Ok(None);
+    };
+    let count: usize = header
+        .strip_prefix('*')
+        .and_then(|count| count.parse().ok())
+        .ok_or_else(|| invalid("expected array"))?;
+    let mut args = Vec::with_capacity(count);
+    for _ in 0..count {
+        let line = read_line(reader)
+            .await?
+            .ok_or_else(|| invalid("unexpected end of stream"))?;
+        let len: usize = line
+            .strip_prefix('$')
+            .and_then(|len| len.parse().ok())
+            .ok_or_else(|| invalid("expected bulk string"))?;
+        let mut buf = vec![0; len + 2];
+        reader.read_exact(&mut buf).await?;
+        buf.truncate(len);
+        args.push(String::from_utf8_lossy(&buf).into_owned());
+    }
+    Ok(Some(args))
+}
@@ -0,0 +1,186 @@
+use std::time::{Duration, SystemTime};
+
+use volans::core::PeerId;
+
+use super::{RedisSessionRegistry, memory_server::MemoryServer};
+use crate::{
+    ids::{PlayerId, SessionId},
+    session::{Session, SessionRegistry},
+};
+
+async fn registry() -> (MemoryServer, RedisSessionRegistry) {
+    let server = MemoryServer::start().await.unwrap();
+    let registry = RedisSessionRegistry::connect(&server.url()).await.unwrap();
+    (server, registry)
+}
+
+fn session(player_id: &PlayerId) -> Session {
+    Session::new(SessionId::generate(), player_id.clone())
+}
+
+fn ids(mut sessions: Vec<Session>) -> Vec<SessionId> {
+    sessions.sort_by(|a, b| a.id().cmp(b.id()));
+    sessions.into_iter().map(|s| s.id().clone()).collect()
+}
+
+fn sorted(mut ids: Vec<SessionId>) -> Vec<SessionId> {
+    ids.sort();
+    ids
+}
+
+#[tokio::test]
+async fn insert_and_lookup() {
+    let (_server, mut registry) = registry().await;
+    let player_id = PlayerId::generate();
+    let peer_id = PeerId::from_bytes([7; 32]);
+    let expires_at = SystemTime::now() + Duration::from_secs(3600);
+    let inserted = session(&player_id)
+        .with_gateway("gateway-1")
+        .with_peer_id(peer_id)
+        .with_expires_at(expires_at);
+
+    registry.insert(inserted.clone()).await.unwrap();
+    assert!(registry.insert(inserted.clone()).await.is_err());
+
+    let found = registry.lookup(inserted.id().clone()).await.unwrap();
+    assert_eq!(found.player_id(), &player_id);
+    assert_eq!(found.gateway(), Some("gateway-1"));
+    assert_eq!(found.peer_id(), Some(peer_id));
+    // 时间以毫秒精度存储
+    let stored = found.expires_at().unwrap();
+    assert!(expires_at.duration_since(stored).unwrap() < Duration::from_millis(1));
+    assert!(registry.lookup(SessionId::generate()).await.is_none());
+}
+
+#[tokio::test]
+async fn sessions_of_follows_insert_and_remove() {
+    let (server, mut registry) = registry().await;
+    let player_id = PlayerId::generate();
+    let first = session(&player_id);
+    let second = session(&player_id);
+    let other = session(&PlayerId::generate());
+    for session in [&first, &second, &other] {
+        registry.insert(session.clone()).await.unwrap();
+    }
+
+    assert_eq!(
+        ids(registry.sessions_of(player_id.clone()).await),
+        sorted(vec![first.id().clone(), second.id().clone()])
+    );
+
+    assert!(registry.remove(first.id().clone()).await);
+    assert!(!registry.remove(first.id().clone()).await);
+    assert_eq!(
+        ids(registry.sessions_of(player_id.clone()).await),
+        vec![second.id().clone()]
+    );
+
+    assert!(registry.remove(second.id().clone()).await);
+    assert!(registry.sessions_of(player_id.clone()).await.is_empty());
+    assert!(!server.exists(&format!("vela:player:{player_id}")));
+    assert_eq!(
+        registry.sessions_of(other.player_id().clone()).await.len(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn single_session_removes_other_sessions() {
+    let (_server, mut registry) = registry().await;
+    let player_id = PlayerId::generate();
+    let kept = session(&player_id);
+    let others = [session(&player_id), session(&player_id)];
+    registry.insert(kept.clone()).await.unwrap();
+    for session in &others {
+        registry.insert(session.clone()).await.unwrap();
+    }
+
+    let removed = registry
+        .single_session(player_id.clone(), kept.id().clone())
+        .await;
+    assert_eq!(
+        ids(removed),
+        sorted(others.iter().map(|s| s.id().clone()).collect())
+    );
+    assert_eq!(
+        ids(registry.sessions_of(player_id.clone()).await),
+        vec![kept.id().clone()]
+    );
+    for session in &others {
+        assert!(registry.lookup(session.id().clone()).await.is_none());
+    }
+}
+
+#[tokio::test]
+async fn idle_sessions_expire() {
+    let (server, registry) = registry().await;
+    let mut registry = registry.with_ttl(Duration::from_secs(60));
+    let player_id = PlayerId::generate();
+    let active = session(&player_id);
+    let idle = session(&player_id);
+    registry.insert(active.clone()).await.unwrap();
+    registry.insert(idle.clone()).await.unwrap();
+
+    server.advance(Duration::from_secs(40));
+    assert!(registry.touch(active.id().clone()).await);
+    server.advance(Duration::from_secs(40));
+
+    assert!(registry.lookup(idle.id().clone()).await.is_none());
+    assert!(!registry.touch(idle.id().clone()).await);
+    assert_eq!(
+        ids(registry.sessions_of(player_id.clone()).await),
+        vec![active.id().clone()]
+    );
+
+    server.advance(Duration::from_secs(61));
+    assert!(registry.sessions_of(player_id).await.is_empty());
+}
+
+#[tokio::test]
+async fn short_lived_session_keeps_player_index() {
+    let (server, mut registry) = registry().await;
+    let player_id = PlayerId::generate();
+    let player_key = format!("vela:player:{player_id}");
+    let long_lived = session(&player_id);
+    let short_lived =
+        session(&player_id).with_expires_at(SystemTime::now() + Duration::from_secs(10));
+    registry.insert(long_lived.clone()).await.unwrap();
+    registry.insert(short_lived.clone()).await.unwrap();
+    assert!(registry.touch(short_lived.id().clone()).await);
+
+    assert!(server.pttl(&player_key).unwrap() > Duration::from_secs(60 * 60));
+
+    server.advance(Duration::from_secs(11));
+    assert!(registry.lookup(short_lived.id().clone()).await.is_none());
+    assert_eq!(
+        ids(registry.sessions_of(player_id).await),
+        vec![long_lived.id().clone()]
+    );
+}
+
+#[tokio::test]
+async fn insert_if_absent_rejects_other_live_sessions() {
+    let (server, mut registry) = registry().await;
+    let player_id = PlayerId::generate();
+    let first = session(&player_id).with_expires_at(SystemTime::now() + Duration::from_secs(10));
+    registry.insert_if_absent(first.clone()).await.unwrap();
+    assert!(registry.insert_if_absent(first.clone()).await.is_err());
+    assert!(
+        registry
+            .insert_if_absent(session(&player_id))
+            .await
+            .is_err()
+    );
+    registry
+        .insert(session(&PlayerId::generate()))
+        .await
+        .unwrap();
+
+    server.advance(Duration::from_secs(11));
+    let second = session(&player_id);
+    registry.insert_if_absent(second.clone()).await.unwrap();
+    assert_eq!(
+        ids(registry.sessions_of(player_id).await),
+        vec![second.id().clone()]
+    );
+}
@@ -11,6 +11,8 @@ fn main() -> Result<()> {
     // 构建 prost 配置
     let mut config = prost_build::Config::new();
     config.out_dir(&out_dir);
+    // 生成 prost::Name，用于将错误详情打包为 Any
+    config.enable_type_names();
 
     // 收集需要编译的 proto 文件
     let mut proto_files = Vec::new();
@@ -21,6 +23,7 @@ fn main() -> Result<()> {
         proto_files.push("../apis/vela/common/status.proto");
         proto_files.push("../apis/vela/common/api.proto");
         proto_files.push("../apis/vela/common/metadata.proto");
+        proto_files.push("../apis/vela/common/error_details.proto");
         println!("cargo:rustc-cfg=feature=\"common\"");
     }
 
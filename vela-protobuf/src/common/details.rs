@@ -0,0 +1,171 @@
+use std::time::Duration;
+
+use prost::Name;
+use prost_types::Any;
+
+use super::{
+    BadRequest, Code, ErrorInfo, QuotaFailure, RetryInfo, Status, bad_request, quota_failure,
+};
+
+impl Status {
+    pub fn new(code: Code, message: impl Into<String>) -> Self {
+        Status {
+            code: code as i32,
+            message: message.into(),
+            details: Vec::new(),
+        }
+    }
+
+    pub fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = message.into();
+        self
+    }
+
+    /// 附加错误详情，如 [`BadRequest`]、[`RetryInfo`]
+    pub fn with_detail<M: Name>(mut self, detail: &M) -> Self {
+        self.details
+            .push(Any::from_msg(detail).expect("encoding to Vec never fails"));
+        self
+    }
+
+    /// 取出第一个指定类型的错误详情
+    pub fn detail<M: Name + Default>(&self) -> Option<M> {
+        self.details.iter().find_map(|any| any.to_msg().ok())
+    }
+
+    /// 取出所有指定类型的错误详情
+    pub fn details<M: Name + Default>(&self) -> Vec<M> {
+        self.details
+            .iter()
+            .filter_map(|any| any.to_msg().ok())
+            .collect()
+    }
+}
+
+impl BadRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_violation(
+        mut self,
+        field: impl Into<String>,
+        description: impl Into<String>,
+    ) -> Self {
+        self.field_violations.push(bad_request::FieldViolation {
+            field: field.into(),
+            description: description.into(),
+        });
+        self
+    }
+}
+
+impl QuotaFailure {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_violation(
+        mut self,
+        subject: impl Into<String>,
+        description: impl Into<String>,
+    ) -> Self {
+        self.violations.push(quota_failure::Violation {
+            subject: subject.into(),
+            description: description.into(),
+        });
+        self
+    }
+}
+
+impl RetryInfo {
+    pub fn new(retry_delay: Duration) -> Self {
+        Self {
+            retry_delay: prost_types::Duration::try_from(retry_delay).ok(),
+        }
+    }
+
+    /// 建议的重试间隔，无效或为负数时返回 `None`
+    pub fn retry_delay(&self) -> Option<Duration> {
+        self.retry_delay
+            .and_then(|delay| Duration::try_from(delay).ok())
+    }
+}
+
+impl ErrorInfo {
+    pub fn new(reason: impl Into<String>, domain: impl Into<String>) -> Self {
+        Self {
+            reason: reason.into(),
+            domain: domain.into(),
+            metadata: Default::default(),
+        }
+    }
+
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 经过编码与解码后取出详情
+    fn round_trip(status: Status) -> Status {
+        use prost::Message;
+
+        Status::decode(status.encode_to_vec().as_slice()).unwrap()
+    }
+
+    #[test]
+    fn bad_request_round_trips() {
+        let detail = BadRequest::new()
+            .with_violation("name", "too long")
+            .with_violation("seat", "out of range");
+        let status = round_trip(Status::new(Code::InvalidArgument, "bad").with_detail(&detail));
+
+        assert_eq!(status.detail::<BadRequest>(), Some(detail));
+        assert_eq!(status.detail::<QuotaFailure>(), None);
+    }
+
+    #[test]
+    fn quota_failure_round_trips() {
+        let detail = QuotaFailure::new().with_violation("py_1", "too many tables");
+        let status = round_trip(Status::from(Code::ResourceExhausted).with_detail(&detail));
+
+        assert_eq!(status.detail::<QuotaFailure>(), Some(detail));
+    }
+
+    #[test]
+    fn retry_info_round_trips() {
+        let detail = RetryInfo::new(Duration::from_millis(1500));
+        let status = round_trip(Status::from(Code::Unavailable).with_detail(&detail));
+
+        let retry = status.detail::<RetryInfo>().unwrap();
+        assert_eq!(retry.retry_delay(), Some(Duration::from_millis(1500)));
+    }
+
+    #[test]
+    fn error_info_round_trips() {
+        let detail = ErrorInfo::new("SEAT_TAKEN", "vela.table").with_metadata("seat", "3");
+        let status = round_trip(Status::from(Code::FailedPrecondition).with_detail(&detail));
+
+        assert_eq!(status.detail::<ErrorInfo>(), Some(detail));
+    }
+
+    #[test]
+    fn details_returns_every_detail_of_a_type() {
+        let first = ErrorInfo::new("A", "vela");
+        let second = ErrorInfo::new("B", "vela");
+        let status = round_trip(
+            Status::from(Code::Aborted)
+                .with_detail(&first)
+                .with_detail(&RetryInfo::new(Duration::from_secs(1)))
+                .with_detail(&second),
+        );
+
+        assert_eq!(status.details::<ErrorInfo>(), [first, second]);
+        assert_eq!(status.details::<RetryInfo>().len(), 1);
+    }
+}
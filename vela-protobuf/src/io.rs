@@ -82,7 +82,7 @@ where
 
     fn start_send(self: Pin<&mut Self>, item: O) -> Result<(), Self::Error> {
         let this = self.project();
-        item.encode(&mut this.write_buffer.as_mut())?;
+        item.encode(this.write_buffer)?;
         let buffer = this.write_buffer.split().freeze();
         this.io.start_send(buffer)?;
         Ok(())
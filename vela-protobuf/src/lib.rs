@@ -12,6 +12,8 @@ pub use io::{FrameError, Framed};
 pub mod common {
     include!(concat!(env!("OUT_DIR"), "/vela.common.rs"));
 
+    mod details;
+
     // OK = 0; // 成功 HTTP 200
     // CANCELLED = 1; // 操作被取消 HTTP 499
     // UNKNOWN = 2; // 未知错误 HTTP 500
@@ -30,36 +32,146 @@ pub mod common {
     // UNAVAILABLE = 14; // 服务不可用 HTTP 503
     // DATA_LOSS = 15; // 数据丢失 HTTP 500
 
+    impl Code {
+        /// 对应的 HTTP 状态码
+        pub fn http_status(self) -> u16 {
+            match self {
+                Code::Ok => 200,
+                Code::Cancelled => 499,
+                Code::Unknown => 500,
+                Code::InvalidArgument => 400,
+                Code::DeadlineExceeded => 504,
+                Code::NotFound => 404,
+                Code::AlreadyExists => 409,
+                Code::PermissionDenied => 403,
+                Code::Unauthenticated => 401,
+                Code::ResourceExhausted => 429,
+                Code::FailedPrecondition => 412,
+                Code::Aborted => 409,
+                Code::OutOfRange => 416,
+                Code::Unimplemented => 501,
+                Code::Internal => 500,
+                Code::Unavailable => 503,
+                Code::DataLoss => 500,
+            }
+        }
+
+        /// 由 HTTP 状态码推断结果码
+        ///
+        /// 多个结果码共用同一状态码时取最通用的一个：409 为 `Aborted`，500 为 `Internal`。
+        /// 未列出的 2xx 视为 `Ok`，4xx 视为 `FailedPrecondition`，其余视为 `Unknown`。
+        pub fn from_http_status(status: u16) -> Self {
+            match status {
+                400 => Code::InvalidArgument,
+                401 => Code::Unauthenticated,
+                403 => Code::PermissionDenied,
+                404 => Code::NotFound,
+                409 => Code::Aborted,
+                412 => Code::FailedPrecondition,
+                416 => Code::OutOfRange,
+                429 => Code::ResourceExhausted,
+                499 => Code::Cancelled,
+                500 => Code::Internal,
+                501 => Code::Unimplemented,
+                503 => Code::Unavailable,
+                504 => Code::DeadlineExceeded,
+                s if (200..300).contains(&s) => Code::Ok,
+                s if (400..500).contains(&s) => Code::FailedPrecondition,
+                _ => Code::Unknown,
+            }
+        }
+    }
+
     impl Status {
+        /// 结果码，未知的值视为 `Unknown`
+        pub fn code_enum(&self) -> Code {
+            Code::try_from(self.code).unwrap_or(Code::Unknown)
+        }
+
         pub fn http_code(&self) -> i32 {
-            match self.code {
-                0 => 200,
-                1 => 404,
-                2 => 500,
-                3 => 400,
-                4 => 504,
-                5 => 404,
-                6 => 409,
-                7 => 403,
-                8 => 429,
-                9 => 412,
-                10 => 409,
-                11 => 416,
-                12 => 501,
-                13 => 500,
-                14 => 503,
-                15 => 500,
-                _ => 500,
+            self.code_enum().http_status() as i32
+        }
+    }
+
+    impl std::fmt::Display for Status {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            let name = self.code_enum().as_str_name();
+            if self.message.is_empty() {
+                write!(f, "{}", name)
+            } else {
+                write!(f, "{}: {}", name, self.message)
             }
         }
     }
 
+    impl std::error::Error for Status {}
+
+    /// 消息为空，显示时只有结果码名称
     impl From<Code> for Status {
         fn from(code: Code) -> Self {
-            Status {
-                code: code as i32,
-                ..Default::default()
+            Status::new(code, String::new())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        /// 所有结果码，按 proto 中的数值遍历
+        fn all_codes() -> Vec<Code> {
+            (0..=16).map(|v| Code::try_from(v).unwrap()).collect()
+        }
+
+        #[test]
+        fn every_code_round_trips_through_http_status() {
+            let codes = all_codes();
+            assert_eq!(codes.len(), 17);
+            for code in codes {
+                let status = code.http_status();
+                let back = Code::from_http_status(status);
+                // 共用状态码的结果码映射回最通用的一个
+                let expected = match code {
+                    Code::AlreadyExists => Code::Aborted,
+                    Code::Unknown | Code::DataLoss => Code::Internal,
+                    code => code,
+                };
+                assert_eq!(back, expected, "{:?} -> {}", code, status);
+                assert_eq!(back.http_status(), status);
+            }
+        }
+
+        #[test]
+        fn unmapped_http_statuses_fall_back_by_class() {
+            for status in [201, 204, 299] {
+                assert_eq!(Code::from_http_status(status), Code::Ok);
             }
+            for status in [402, 405, 418, 422, 451] {
+                assert_eq!(Code::from_http_status(status), Code::FailedPrecondition);
+            }
+            for status in [0, 100, 301, 302, 502, 505, 599, 999] {
+                assert_eq!(Code::from_http_status(status), Code::Unknown);
+            }
+        }
+
+        #[test]
+        fn unknown_status_code_maps_to_internal_server_error() {
+            let status = Status {
+                code: 99,
+                ..Default::default()
+            };
+            assert_eq!(status.code_enum(), Code::Unknown);
+            assert_eq!(status.http_code(), 500);
+        }
+
+        #[test]
+        fn status_from_code_displays_the_name_once() {
+            let status = Status::from(Code::NotFound);
+            assert!(status.message.is_empty());
+            assert_eq!(status.to_string(), "NOT_FOUND");
+            assert_eq!(
+                status.with_message("missing").to_string(),
+                "NOT_FOUND: missing"
+            );
         }
     }
 }
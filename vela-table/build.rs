@@ -0,0 +1,8 @@
+use std::io::Result;
+
+fn main() -> Result<()> {
+    // 生成 vela.table 服务代码，消息类型引用 vela_protobuf::table
+    vela_build::compile_protos(&["../apis/vela/table/table.proto"], &["../apis"])?;
+    println!("cargo:rerun-if-changed=../apis/vela/table/table.proto");
+    Ok(())
+}
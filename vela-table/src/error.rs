@@ -0,0 +1,53 @@
+use vela_core::{error::StatusError, game::GameError};
+use vela_protobuf::common::Code;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TableError {
+    #[error("Table {0} not found")]
+    NotFound(String),
+    #[error("Player already joined the table")]
+    AlreadyJoined,
+    #[error("Player has not joined the table")]
+    NotJoined,
+    #[error("Seat {0} does not exist")]
+    InvalidSeat(u32),
+    #[error("Seat {0} is already taken")]
+    SeatTaken(u32),
+    #[error("Player is already seated")]
+    AlreadySeated,
+    #[error("Player is not seated")]
+    NotSeated,
+    #[error("Game is in progress")]
+    Playing,
+    #[error("Game is not in progress")]
+    NotPlaying,
+    #[error("Not all seated players are ready")]
+    NotReady,
+    #[error("Game error: {0}")]
+    Game(#[from] GameError),
+}
+
+impl TableError {
+    /// 错误对应的结果码
+    pub fn code(&self) -> Code {
+        match self {
+            TableError::NotFound(_) => Code::NotFound,
+            TableError::AlreadyJoined | TableError::SeatTaken(_) | TableError::AlreadySeated => {
+                Code::AlreadyExists
+            }
+            TableError::InvalidSeat(_) => Code::OutOfRange,
+            TableError::NotJoined
+            | TableError::NotSeated
+            | TableError::Playing
+            | TableError::NotPlaying
+            | TableError::NotReady => Code::FailedPrecondition,
+            TableError::Game(e) => e.code(),
+        }
+    }
+}
+
+impl From<TableError> for StatusError {
+    fn from(error: TableError) -> Self {
+        StatusError::new(error.code(), error.to_string()).with_source(error)
+    }
+}
@@ -0,0 +1,17 @@
+//! Vela 桌子引擎
+//!
+//! 实现 `vela.table` 协议的桌子状态机，负责坐位管理、玩家加入离开、
+//! 准备以及游戏的开始与结束；[`Runtime`] 在桌子之上驱动 `vela_core::game::Game`。
+
+mod error;
+mod runtime;
+mod table;
+
+/// `vela.table.TableService` 服务端 trait 与客户端
+pub mod service {
+    include!(concat!(env!("OUT_DIR"), "/vela.table.rs"));
+}
+
+pub use error::TableError;
+pub use runtime::Runtime;
+pub use table::Table;
@@ -0,0 +1,432 @@
+use std::{collections::VecDeque, time::Duration};
+
+use prost::Message;
+use vela_core::{
+    game::{Game, GameContext, GameError, Recipient},
+    ids::PlayerId,
+};
+use vela_protobuf::table::{
+    GameActionResp, GameStateNtf, IncomingMessage, OutgoingMessage, SeatStatus, TableStatus,
+    incoming_message, outgoing_message,
+};
+
+use crate::{Table, TableError, table::result_code};
+
+/// 桌子运行时
+///
+/// 在 [`Table`] 之上驱动 [`Game`]：所有就座玩家准备后自动开始游戏，
+/// 将 `GameActionReq` 解码为游戏动作，并将游戏状态编码为 `GameStateNtf` 下发。
+pub struct Runtime<G: Game> {
+    table: Table,
+    factory: Box<dyn FnMut() -> G + Send>,
+    game: Option<(G, GameContext<G::State>)>,
+    auto_start: bool,
+    pending_result: VecDeque<G::Result>,
+}
+
+impl<G: Game> Runtime<G> {
+    /// 创建运行时，每局游戏开始时调用 `factory` 创建新的游戏实例
+    pub fn new<F>(table: Table, factory: F) -> Self
+    where
+        F: FnMut() -> G + Send + 'static,
+    {
+        Self {
+            table,
+            factory: Box::new(factory),
+            game: None,
+            auto_start: true,
+            pending_result: VecDeque::new(),
+        }
+    }
+
+    /// 是否在所有就座玩家准备后自动开始，默认开启
+    pub fn with_auto_start(mut self, auto_start: bool) -> Self {
+        self.auto_start = auto_start;
+        self
+    }
+
+    pub fn table(&self) -> &Table {
+        &self.table
+    }
+
+    pub fn game(&self) -> Option<&G> {
+        self.game.as_ref().map(|(game, _)| game)
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.game.is_some()
+    }
+
+    /// 处理玩家发来的消息
+    pub fn handle(
+        &mut self,
+        player_id: &PlayerId,
+        message: IncomingMessage,
+    ) -> Result<(), TableError> {
+        let result = match message.message {
+            Some(incoming_message::Message::GameActionReq(req)) if self.game.is_some() => {
+                let result = self.on_action(player_id, &req.payload);
+                self.table.reply(
+                    player_id,
+                    outgoing_message::Message::GameActionResp(GameActionResp {
+                        code: result_code(&result),
+                    }),
+                );
+                result
+            }
+            message => {
+                let gaming = self.is_gaming(player_id);
+                let result = self.table.handle(player_id, IncomingMessage { message });
+                if gaming && self.table.seat_of(player_id).is_none() {
+                    self.on_leave(player_id);
+                }
+                result
+            }
+        };
+        self.update();
+        result
+    }
+
+    /// 玩家离开桌子，如玩家断线等
+    pub fn leave(&mut self, player_id: &PlayerId) -> Result<(), TableError> {
+        let gaming = self.is_gaming(player_id);
+        self.table.leave(player_id)?;
+        if gaming {
+            self.on_leave(player_id);
+        }
+        self.update();
+        Ok(())
+    }
+
+    /// 手动开始游戏
+    pub fn start(&mut self) -> Result<(), TableError> {
+        self.table.start()?;
+        let players = self
+            .table
+            .seated_players()
+            .map(|(index, player_id)| (index, player_id.clone()))
+            .collect();
+        let mut game = (self.factory)();
+        let mut ctx = GameContext::new(players);
+        game.on_start(&mut ctx);
+        self.game = Some((game, ctx));
+        self.update();
+        Ok(())
+    }
+
+    /// 定时驱动游戏
+    pub fn tick(&mut self, elapsed: Duration) {
+        if let Some((game, ctx)) = self.game.as_mut() {
+            game.on_tick(ctx, elapsed);
+        }
+        self.update();
+    }
+
+    /// 取出下一条待发送的消息
+    pub fn next_message(&mut self) -> Option<(PlayerId, OutgoingMessage)> {
+        self.table.next_message()
+    }
+
+    /// 取出已结束游戏的结果
+    pub fn next_result(&mut self) -> Option<G::Result> {
+        self.pending_result.pop_front()
+    }
+
+    fn is_gaming(&self, player_id: &PlayerId) -> bool {
+        self.table
+            .seat_of(player_id)
+            .is_some_and(|seat| seat.status() == SeatStatus::Gaming)
+    }
+
+    fn on_action(&mut self, player_id: &PlayerId, payload: &[u8]) -> Result<(), TableError> {
+        let Some((game, ctx)) = self.game.as_mut() else {
+            return Err(TableError::NotPlaying);
+        };
+        if !ctx.contains(player_id) {
+            return Err(GameError::NotInGame.into());
+        }
+        let action = G::Action::decode(payload).map_err(GameError::from)?;
+        game.on_player_action(ctx, player_id, action)?;
+        Ok(())
+    }
+
+    fn on_leave(&mut self, player_id: &PlayerId) {
+        if let Some((game, ctx)) = self.game.as_mut() {
+            ctx.remove_player(player_id);
+            game.on_player_leave(ctx, player_id);
+        }
+    }
+
+    // 下发游戏状态，检查游戏是否结束以及是否可以开始新的一局
+    fn update(&mut self) {
+        if let Some((game, ctx)) = self.game.as_mut() {
+            while let Some((recipient, state)) = ctx.next_state() {
+                let message = OutgoingMessage {
+                    message: Some(outgoing_message::Message::GameStateNtf(GameStateNtf {
+                        payload: state.encode_to_vec(),
+                    })),
+                };
+                match recipient {
+                    Recipient::Player(player_id) => self.table.send(&player_id, message),
+                    Recipient::All => self.table.broadcast(message),
+                }
+            }
+            if game.is_finished() {
+                if let Some(result) = game.result() {
+                    self.pending_result.push_back(result);
+                }
+                self.game = None;
+                if self.table.status() == TableStatus::Playing {
+                    let _ = self.table.end();
+                }
+            }
+        }
+        if self.auto_start
+            && self.game.is_none()
+            && self.table.can_start()
+            && let Err(e) = self.start()
+        {
+            tracing::warn!("Failed to start game on table {}: {}", self.table.id(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vela_protobuf::{
+        common::Code,
+        table::{GameActionReq, ReadyReq, SitDownReq, TableInfo},
+    };
+
+    use super::*;
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct Add {
+        #[prost(uint32, tag = "1")]
+        value: u32,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct Total {
+        #[prost(uint32, tag = "1")]
+        total: u32,
+    }
+
+    /// 累加到 10 结束，只剩一名玩家时提前结束
+    #[derive(Default)]
+    struct Counter {
+        total: u32,
+        left: Vec<PlayerId>,
+        finished: bool,
+    }
+
+    impl Game for Counter {
+        type Action = Add;
+        type State = Total;
+        type Result = (u32, Vec<PlayerId>);
+
+        fn on_start(&mut self, ctx: &mut GameContext<Total>) {
+            ctx.broadcast(Total { total: 0 });
+        }
+
+        fn on_player_action(
+            &mut self,
+            ctx: &mut GameContext<Total>,
+            _player_id: &PlayerId,
+            action: Add,
+        ) -> Result<(), GameError> {
+            if action.value == 0 {
+                return Err(GameError::InvalidAction("zero".to_string()));
+            }
+            self.total += action.value;
+            self.finished = self.total >= 10;
+            ctx.broadcast(Total { total: self.total });
+            Ok(())
+        }
+
+        fn on_tick(&mut self, _ctx: &mut GameContext<Total>, _elapsed: Duration) {}
+
+        fn on_player_leave(&mut self, ctx: &mut GameContext<Total>, player_id: &PlayerId) {
+            self.left.push(player_id.clone());
+            self.finished = ctx.players().len() < 2;
+        }
+
+        fn is_finished(&self) -> bool {
+            self.finished
+        }
+
+        fn result(&mut self) -> Option<Self::Result> {
+            Some((self.total, std::mem::take(&mut self.left)))
+        }
+    }
+
+    fn message(message: incoming_message::Message) -> IncomingMessage {
+        IncomingMessage {
+            message: Some(message),
+        }
+    }
+
+    fn action(payload: Vec<u8>) -> IncomingMessage {
+        message(incoming_message::Message::GameActionReq(GameActionReq {
+            payload,
+        }))
+    }
+
+    fn add(value: u32) -> IncomingMessage {
+        action(Add { value }.encode_to_vec())
+    }
+
+    /// 两名玩家就座，`ready` 为 true 时同时准备
+    fn runtime(auto_start: bool, ready: bool) -> (Runtime<Counter>, [PlayerId; 2]) {
+        let table = Table::new(TableInfo::default(), ["east", "west"]);
+        let mut runtime = Runtime::new(table, Counter::default).with_auto_start(auto_start);
+        let players = [PlayerId::generate(), PlayerId::generate()];
+        for (index, player_id) in players.iter().enumerate() {
+            runtime.table.join(player_id.clone()).unwrap();
+            let req = SitDownReq {
+                index: index as u32,
+            };
+            runtime
+                .handle(
+                    player_id,
+                    message(incoming_message::Message::SitDownReq(req)),
+                )
+                .unwrap();
+            if ready {
+                let req = ReadyReq { ready: true };
+                runtime
+                    .handle(player_id, message(incoming_message::Message::ReadyReq(req)))
+                    .unwrap();
+            }
+        }
+        (runtime, players)
+    }
+
+    fn drain(runtime: &mut Runtime<Counter>) -> Vec<(PlayerId, outgoing_message::Message)> {
+        std::iter::from_fn(|| runtime.next_message())
+            .filter_map(|(player_id, message)| Some((player_id, message.message?)))
+            .collect()
+    }
+
+    fn states(messages: &[(PlayerId, outgoing_message::Message)]) -> Vec<(PlayerId, u32)> {
+        messages
+            .iter()
+            .filter_map(|(player_id, message)| match message {
+                outgoing_message::Message::GameStateNtf(ntf) => Some((
+                    player_id.clone(),
+                    Total::decode(&*ntf.payload).unwrap().total,
+                )),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn action_codes(messages: &[(PlayerId, outgoing_message::Message)]) -> Vec<u32> {
+        messages
+            .iter()
+            .filter_map(|(_, message)| match message {
+                outgoing_message::Message::GameActionResp(resp) => Some(resp.code),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn starts_when_all_seated_players_are_ready() {
+        let (mut runtime, players) = runtime(true, true);
+
+        assert!(runtime.is_playing());
+        assert_eq!(runtime.table().status(), TableStatus::Playing);
+        let messages = drain(&mut runtime);
+        assert_eq!(
+            states(&messages),
+            vec![(players[0].clone(), 0), (players[1].clone(), 0)]
+        );
+    }
+
+    #[test]
+    fn waits_for_start_without_auto_start() {
+        let (mut runtime, _) = runtime(false, true);
+        assert!(!runtime.is_playing());
+
+        runtime.start().unwrap();
+        assert!(runtime.is_playing());
+    }
+
+    #[test]
+    fn does_not_start_until_everyone_is_ready() {
+        let (mut runtime, players) = runtime(true, false);
+        let req = ReadyReq { ready: true };
+        runtime
+            .handle(
+                &players[0],
+                message(incoming_message::Message::ReadyReq(req)),
+            )
+            .unwrap();
+        assert!(!runtime.is_playing());
+    }
+
+    #[test]
+    fn rejects_actions_that_fail_to_decode() {
+        let (mut runtime, players) = runtime(true, true);
+        drain(&mut runtime);
+
+        let result = runtime.handle(&players[0], action(vec![0xff]));
+        assert!(matches!(
+            result,
+            Err(TableError::Game(GameError::Decode(_)))
+        ));
+        let messages = drain(&mut runtime);
+        assert_eq!(action_codes(&messages), vec![Code::InvalidArgument as u32]);
+        assert!(states(&messages).is_empty());
+        assert!(runtime.is_playing());
+    }
+
+    #[test]
+    fn rejects_actions_from_players_not_in_the_game() {
+        let (mut runtime, _) = runtime(true, true);
+        let watcher = PlayerId::generate();
+        runtime.table.join(watcher.clone()).unwrap();
+        drain(&mut runtime);
+
+        let result = runtime.handle(&watcher, add(1));
+        assert_eq!(result, Err(TableError::Game(GameError::NotInGame)));
+        assert_eq!(
+            action_codes(&drain(&mut runtime)),
+            vec![Code::FailedPrecondition as u32]
+        );
+    }
+
+    #[test]
+    fn leaving_mid_game_notifies_the_game() {
+        let (mut runtime, players) = runtime(true, true);
+        runtime.handle(&players[0], add(3)).unwrap();
+
+        runtime.leave(&players[1]).unwrap();
+
+        assert!(!runtime.is_playing());
+        assert_eq!(runtime.table().status(), TableStatus::Idle);
+        assert_eq!(runtime.next_result(), Some((3, vec![players[1].clone()])));
+        assert!(runtime.next_result().is_none());
+    }
+
+    #[test]
+    fn reports_the_result_when_the_game_finishes() {
+        let (mut runtime, players) = runtime(true, true);
+        runtime.handle(&players[0], add(4)).unwrap();
+        assert!(runtime.next_result().is_none());
+
+        runtime.handle(&players[1], add(6)).unwrap();
+
+        assert!(!runtime.is_playing());
+        assert_eq!(runtime.next_result(), Some((10, Vec::new())));
+        // 结束后坐位回到已就座状态，不会自动开始新的一局
+        assert!(
+            runtime
+                .table()
+                .seats()
+                .all(|seat| seat.status() == SeatStatus::Seated)
+        );
+        assert!(!runtime.is_playing());
+    }
+}
@@ -0,0 +1,456 @@
+use std::collections::VecDeque;
+
+use vela_core::ids::PlayerId;
+use vela_protobuf::{
+    common::Code,
+    table::{
+        GameActionResp, IncomingMessage, JoinTableResp, LeaveTableResp, OutgoingMessage, ReadyResp,
+        Seat, SeatStatus, SeatStatusNtf, SitDownResp, StandUpResp, TableInfo, TableInfoNtf,
+        TableStatus, TableStatusNtf, incoming_message, outgoing_message,
+    },
+};
+
+use crate::TableError;
+
+/// 桌子状态机
+///
+/// 处理玩家的 `IncomingMessage`，并将需要下发的 `OutgoingMessage`
+/// 放入待发送队列，由调用方通过 [`Table::next_message`] 取出后发送。
+pub struct Table {
+    info: TableInfo,
+    seats: Vec<SeatSlot>,
+    players: Vec<PlayerId>,
+    status: TableStatus,
+    min_players: usize,
+    pending_message: VecDeque<(PlayerId, OutgoingMessage)>,
+}
+
+struct SeatSlot {
+    seat: Seat,
+    player_id: Option<PlayerId>,
+}
+
+impl Table {
+    /// 创建桌子，每个坐位名称对应一个坐位
+    pub fn new<I>(info: TableInfo, seats: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let seats: Vec<SeatSlot> = seats
+            .into_iter()
+            .enumerate()
+            .map(|(index, name)| SeatSlot {
+                seat: Seat {
+                    index: index as u32,
+                    name: name.into(),
+                    status: SeatStatus::Empty as i32,
+                    player_id: String::new(),
+                },
+                player_id: None,
+            })
+            .collect();
+        let min_players = seats.len();
+        Self {
+            info,
+            seats,
+            players: Vec::new(),
+            status: TableStatus::Idle,
+            min_players,
+            pending_message: VecDeque::new(),
+        }
+    }
+
+    /// 开始游戏所需的最少就座人数，默认为坐位数
+    pub fn with_min_players(mut self, min_players: usize) -> Self {
+        self.min_players = min_players;
+        self
+    }
+
+    pub fn id(&self) -> &str {
+        &self.info.id
+    }
+
+    pub fn info(&self) -> &TableInfo {
+        &self.info
+    }
+
+    pub fn status(&self) -> TableStatus {
+        self.status
+    }
+
+    pub fn seats(&self) -> impl Iterator<Item = &Seat> {
+        self.seats.iter().map(|slot| &slot.seat)
+    }
+
+    /// 桌上所有玩家，包括未就座的玩家
+    pub fn players(&self) -> &[PlayerId] {
+        &self.players
+    }
+
+    /// 已就座的玩家，按坐位序号排列
+    pub fn seated_players(&self) -> impl Iterator<Item = (u32, &PlayerId)> {
+        self.seats
+            .iter()
+            .filter_map(|slot| slot.player_id.as_ref().map(|p| (slot.seat.index, p)))
+    }
+
+    pub fn contains(&self, player_id: &PlayerId) -> bool {
+        self.players.contains(player_id)
+    }
+
+    pub fn seat_of(&self, player_id: &PlayerId) -> Option<&Seat> {
+        self.seat_index(player_id).map(|i| &self.seats[i].seat)
+    }
+
+    /// 取出下一条待发送的消息
+    pub fn next_message(&mut self) -> Option<(PlayerId, OutgoingMessage)> {
+        self.pending_message.pop_front()
+    }
+
+    /// 处理玩家发来的消息，并向该玩家回复对应的响应
+    pub fn handle(
+        &mut self,
+        player_id: &PlayerId,
+        message: IncomingMessage,
+    ) -> Result<(), TableError> {
+        let Some(message) = message.message else {
+            tracing::debug!("Empty table message from {}", player_id);
+            return Ok(());
+        };
+        match message {
+            incoming_message::Message::JoinTableReq(req) => {
+                let result = if req.table_id.is_empty() || req.table_id == self.info.id {
+                    self.join(player_id.clone())
+                } else {
+                    Err(TableError::NotFound(req.table_id))
+                };
+                self.reply(
+                    player_id,
+                    outgoing_message::Message::JoinTableResp(JoinTableResp {
+                        code: result_code(&result),
+                    }),
+                );
+                result
+            }
+            incoming_message::Message::LeaveTableReq(_) => {
+                let result = self.leave(player_id);
+                self.reply(
+                    player_id,
+                    outgoing_message::Message::LeaveTableResp(LeaveTableResp {
+                        code: result_code(&result),
+                    }),
+                );
+                result
+            }
+            incoming_message::Message::SitDownReq(req) => {
+                let result = self.sit_down(player_id, req.index);
+                self.reply(
+                    player_id,
+                    outgoing_message::Message::SitDownResp(SitDownResp {
+                        code: result_code(&result),
+                    }),
+                );
+                result
+            }
+            incoming_message::Message::StandUpReq(_) => {
+                let result = self.stand_up(player_id);
+                self.reply(
+                    player_id,
+                    outgoing_message::Message::StandUpResp(StandUpResp {
+                        code: result_code(&result),
+                    }),
+                );
+                result
+            }
+            incoming_message::Message::ReadyReq(req) => {
+                let result = self.ready(player_id, req.ready);
+                self.reply(
+                    player_id,
+                    outgoing_message::Message::ReadyResp(ReadyResp {
+                        code: result_code(&result),
+                    }),
+                );
+                result
+            }
+            incoming_message::Message::GameActionReq(_) => {
+                let result = Err(TableError::NotPlaying);
+                self.reply(
+                    player_id,
+                    outgoing_message::Message::GameActionResp(GameActionResp {
+                        code: result_code(&result),
+                    }),
+                );
+                result
+            }
+        }
+    }
+
+    /// 玩家加入桌子，成功后向其发送 `TableInfoNtf`
+    pub fn join(&mut self, player_id: PlayerId) -> Result<(), TableError> {
+        if self.contains(&player_id) {
+            return Err(TableError::AlreadyJoined);
+        }
+        self.players.push(player_id.clone());
+        let ntf = self.table_info_ntf();
+        self.reply(&player_id, outgoing_message::Message::TableInfoNtf(ntf));
+        Ok(())
+    }
+
+    /// 玩家离开桌子，如已就座则同时让出坐位
+    ///
+    /// 游戏进行中也允许离开，坐位会被清空。
+    pub fn leave(&mut self, player_id: &PlayerId) -> Result<(), TableError> {
+        let Some(position) = self.players.iter().position(|p| p == player_id) else {
+            return Err(TableError::NotJoined);
+        };
+        // 先让出坐位，离开的玩家也能收到坐位通知
+        if let Some(index) = self.seat_index(player_id) {
+            self.set_seat(index, SeatStatus::Empty, None);
+        }
+        self.players.remove(position);
+        Ok(())
+    }
+
+    pub fn sit_down(&mut self, player_id: &PlayerId, index: u32) -> Result<(), TableError> {
+        if !self.contains(player_id) {
+            return Err(TableError::NotJoined);
+        }
+        if self.status == TableStatus::Playing {
+            return Err(TableError::Playing);
+        }
+        if self.seat_index(player_id).is_some() {
+            return Err(TableError::AlreadySeated);
+        }
+        let slot = self
+            .seats
+            .get(index as usize)
+            .ok_or(TableError::InvalidSeat(index))?;
+        if slot.player_id.is_some() {
+            return Err(TableError::SeatTaken(index));
+        }
+        self.set_seat(index as usize, SeatStatus::Seated, Some(player_id.clone()));
+        Ok(())
+    }
+
+    pub fn stand_up(&mut self, player_id: &PlayerId) -> Result<(), TableError> {
+        let index = self.seat_index(player_id).ok_or(TableError::NotSeated)?;
+        if self.seats[index].seat.status() == SeatStatus::Gaming {
+            return Err(TableError::Playing);
+        }
+        self.set_seat(index, SeatStatus::Empty, None);
+        Ok(())
+    }
+
+    pub fn ready(&mut self, player_id: &PlayerId, ready: bool) -> Result<(), TableError> {
+        let index = self.seat_index(player_id).ok_or(TableError::NotSeated)?;
+        if self.status == TableStatus::Playing {
+            return Err(TableError::Playing);
+        }
+        let status = if ready {
+            SeatStatus::Ready
+        } else {
+            SeatStatus::Seated
+        };
+        if self.seats[index].seat.status() != status {
+            self.set_seat(index, status, Some(player_id.clone()));
+        }
+        Ok(())
+    }
+
+    /// 是否满足开始条件：桌子空闲、就座人数足够且全部已准备
+    pub fn can_start(&self) -> bool {
+        let mut seated = 0;
+        for slot in self.seats.iter().filter(|s| s.player_id.is_some()) {
+            if slot.seat.status() != SeatStatus::Ready {
+                return false;
+            }
+            seated += 1;
+        }
+        self.status == TableStatus::Idle && seated > 0 && seated >= self.min_players
+    }
+
+    /// 开始游戏，已准备的坐位进入游戏状态
+    pub fn start(&mut self) -> Result<(), TableError> {
+        if self.status == TableStatus::Playing {
+            return Err(TableError::Playing);
+        }
+        if !self.can_start() {
+            return Err(TableError::NotReady);
+        }
+        for index in 0..self.seats.len() {
+            if self.seats[index].seat.status() == SeatStatus::Ready {
+                let player_id = self.seats[index].player_id.clone();
+                self.set_seat(index, SeatStatus::Gaming, player_id);
+            }
+        }
+        self.set_status(TableStatus::Playing);
+        Ok(())
+    }
+
+    /// 结束游戏，游戏中的坐位回到已就座状态
+    pub fn end(&mut self) -> Result<(), TableError> {
+        if self.status != TableStatus::Playing {
+            return Err(TableError::NotPlaying);
+        }
+        for index in 0..self.seats.len() {
+            if self.seats[index].seat.status() == SeatStatus::Gaming {
+                let player_id = self.seats[index].player_id.clone();
+                self.set_seat(index, SeatStatus::Seated, player_id);
+            }
+        }
+        self.set_status(TableStatus::Idle);
+        Ok(())
+    }
+
+    pub fn table_info_ntf(&self) -> TableInfoNtf {
+        TableInfoNtf {
+            table: Some(self.info.clone()),
+            seats: self.seats().cloned().collect(),
+            status: self.status as i32,
+        }
+    }
+
+    /// 向指定玩家发送消息
+    pub fn send(&mut self, player_id: &PlayerId, message: OutgoingMessage) {
+        self.pending_message.push_back((player_id.clone(), message));
+    }
+
+    /// 向所有已就座的玩家广播消息
+    pub fn broadcast(&mut self, message: OutgoingMessage) {
+        for slot in &self.seats {
+            if let Some(player_id) = &slot.player_id {
+                self.pending_message
+                    .push_back((player_id.clone(), message.clone()));
+            }
+        }
+    }
+
+    pub(crate) fn reply(&mut self, player_id: &PlayerId, message: outgoing_message::Message) {
+        self.send(
+            player_id,
+            OutgoingMessage {
+                message: Some(message),
+            },
+        );
+    }
+
+    fn seat_index(&self, player_id: &PlayerId) -> Option<usize> {
+        self.seats
+            .iter()
+            .position(|slot| slot.player_id.as_ref() == Some(player_id))
+    }
+
+    fn set_seat(&mut self, index: usize, status: SeatStatus, player_id: Option<PlayerId>) {
+        let slot = &mut self.seats[index];
+        // 让出坐位的玩家不在广播范围内，单独通知
+        let vacated = slot
+            .player_id
+            .take()
+            .filter(|old| player_id.as_ref() != Some(old));
+        slot.seat.status = status as i32;
+        slot.seat.player_id = player_id
+            .as_ref()
+            .map(|p| p.to_string())
+            .unwrap_or_default();
+        slot.player_id = player_id;
+        let ntf = SeatStatusNtf {
+            index: slot.seat.index,
+            status: slot.seat.status,
+            player_id: slot.seat.player_id.clone(),
+        };
+        let message = OutgoingMessage {
+            message: Some(outgoing_message::Message::SeatStatusNtf(ntf)),
+        };
+        if let Some(vacated) = vacated {
+            self.send(&vacated, message.clone());
+        }
+        self.broadcast(message);
+    }
+
+    fn set_status(&mut self, status: TableStatus) {
+        self.status = status;
+        self.broadcast(OutgoingMessage {
+            message: Some(outgoing_message::Message::TableStatusNtf(TableStatusNtf {
+                status: status as i32,
+            })),
+        });
+    }
+}
+
+pub(crate) fn result_code(result: &Result<(), TableError>) -> u32 {
+    match result {
+        Ok(()) => Code::Ok as u32,
+        Err(e) => e.code() as u32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> Table {
+        Table::new(TableInfo::default(), ["east", "west"])
+    }
+
+    fn seat_ntfs(table: &mut Table) -> Vec<(PlayerId, SeatStatusNtf)> {
+        std::iter::from_fn(|| table.next_message())
+            .filter_map(|(player_id, message)| match message.message {
+                Some(outgoing_message::Message::SeatStatusNtf(ntf)) => Some((player_id, ntf)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn broadcast_only_reaches_seated_players() {
+        let mut table = table();
+        let seated = PlayerId::generate();
+        let watching = PlayerId::generate();
+        table.join(seated.clone()).unwrap();
+        table.join(watching.clone()).unwrap();
+        table.sit_down(&seated, 0).unwrap();
+
+        let ntfs = seat_ntfs(&mut table);
+        assert_eq!(ntfs.len(), 1);
+        assert_eq!(ntfs[0].0, seated);
+    }
+
+    #[test]
+    fn leaving_player_receives_own_seat_status() {
+        let mut table = table();
+        let leaving = PlayerId::generate();
+        let staying = PlayerId::generate();
+        for (index, player_id) in [&leaving, &staying].into_iter().enumerate() {
+            table.join(player_id.clone()).unwrap();
+            table.sit_down(player_id, index as u32).unwrap();
+        }
+        seat_ntfs(&mut table);
+
+        table.leave(&leaving).unwrap();
+
+        let ntfs = seat_ntfs(&mut table);
+        let recipients: Vec<&PlayerId> = ntfs.iter().map(|(player_id, _)| player_id).collect();
+        assert_eq!(recipients, [&leaving, &staying]);
+        assert!(
+            ntfs.iter()
+                .all(|(_, ntf)| ntf.index == 0 && ntf.status == SeatStatus::Empty as i32)
+        );
+        assert!(!table.contains(&leaving));
+    }
+
+    #[test]
+    fn stand_up_notifies_player_who_stood_up() {
+        let mut table = table();
+        let player_id = PlayerId::generate();
+        table.join(player_id.clone()).unwrap();
+        table.sit_down(&player_id, 1).unwrap();
+        seat_ntfs(&mut table);
+
+        table.stand_up(&player_id).unwrap();
+
+        let ntfs = seat_ntfs(&mut table);
+        assert_eq!(ntfs.len(), 1);
+        assert_eq!(ntfs[0].0, player_id);
+    }
+}
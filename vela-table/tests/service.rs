@@ -0,0 +1,228 @@
+//! 生成的 `table_service` 经路由与调用句柄往返
+
+use std::{pin::Pin, sync::Arc, time::Duration};
+
+use futures::StreamExt;
+use vela_protobuf::{
+    common::{Code, Status},
+    table::{
+        GameActionReq, GameActionResp, JoinTableReq, JoinTableResp, LeaveTableReq, LeaveTableResp,
+        ReadyReq, ReadyResp, SitDownReq, SitDownResp, StandUpReq, StandUpResp,
+    },
+};
+use vela_request::{
+    CallError, Request, caller,
+    router::{self, RequestContext, Router},
+};
+use vela_table::service::table_service::{self, TableService, TableServiceClient};
+use volans::{
+    Transport,
+    core::{PeerId, Url, muxing::StreamMuxerBox, transport::Boxed},
+    muxing, ping, plaintext,
+    request::Config,
+    swarm::{self, DialOpts, NetworkIncomingBehavior, NetworkOutgoingBehavior, StreamProtocol},
+    tcp,
+};
+
+const PROTOCOL: StreamProtocol = StreamProtocol::new("/v1/table");
+
+/// 坐下返回坐位序号，加入桌子总是失败，其余方法未实现
+struct Table;
+
+#[vela_request::async_trait]
+impl TableService for Table {
+    async fn join_table(
+        &self,
+        _context: RequestContext,
+        request: Request<JoinTableReq>,
+    ) -> Result<JoinTableResp, Status> {
+        Err(Status::new(
+            Code::NotFound,
+            format!("Table {} not found", request.payload().table_id),
+        ))
+    }
+
+    async fn leave_table(
+        &self,
+        _context: RequestContext,
+        _request: Request<LeaveTableReq>,
+    ) -> Result<LeaveTableResp, Status> {
+        Err(Code::Unimplemented.into())
+    }
+
+    async fn sit_down(
+        &self,
+        _context: RequestContext,
+        request: Request<SitDownReq>,
+    ) -> Result<SitDownResp, Status> {
+        Ok(SitDownResp {
+            code: request.payload().index,
+        })
+    }
+
+    async fn stand_up(
+        &self,
+        _context: RequestContext,
+        _request: Request<StandUpReq>,
+    ) -> Result<StandUpResp, Status> {
+        Err(Code::Unimplemented.into())
+    }
+
+    async fn ready(
+        &self,
+        _context: RequestContext,
+        _request: Request<ReadyReq>,
+    ) -> Result<ReadyResp, Status> {
+        Err(Code::Unimplemented.into())
+    }
+
+    async fn game_action(
+        &self,
+        _context: RequestContext,
+        _request: Request<GameActionReq>,
+    ) -> Result<GameActionResp, Status> {
+        Err(Code::Unimplemented.into())
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy)]
+struct TokioExecutor;
+
+impl swarm::Executor for TokioExecutor {
+    fn exec(&self, future: Pin<Box<dyn Future<Output = ()> + Send>>) {
+        tokio::spawn(future);
+    }
+}
+
+fn build_transport() -> (PeerId, Boxed<(PeerId, StreamMuxerBox)>) {
+    let key: [u8; 32] = rand::random();
+    let signing_key = plaintext::ed25519::SigningKey::from_bytes(&key);
+    let transport = tcp::Config::new()
+        .upgrade()
+        .authenticate(plaintext::Config::new(signing_key.verifying_key()))
+        .multiplex(muxing::Config::new())
+        .boxed();
+    (PeerId::from_bytes(key), transport)
+}
+
+#[derive(NetworkIncomingBehavior)]
+struct ServerBehavior {
+    ping: ping::inbound::Behavior,
+    router: router::Behavior,
+}
+
+#[derive(NetworkOutgoingBehavior)]
+struct ClientBehavior {
+    ping: ping::outbound::Behavior,
+    caller: caller::Behavior,
+}
+
+/// 在后台运行挂载桌子服务的路由服务端
+fn spawn_server(router: Router) -> Url {
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .unwrap()
+        .port();
+    let addr = Url::parse(&format!("http://127.0.0.1:{port}")).unwrap();
+    let (peer_id, transport) = build_transport();
+    let mut server = swarm::server::Swarm::new(
+        transport,
+        ServerBehavior {
+            ping: ping::inbound::Behavior::default(),
+            router: router::Behavior::new(router, [PROTOCOL], Config::default()),
+        },
+        peer_id,
+        swarm::connection::PoolConfig::new(Box::new(TokioExecutor)),
+    );
+    server.listen_on(addr.clone()).unwrap();
+    tokio::spawn(async move { while server.next().await.is_some() {} });
+    addr
+}
+
+/// 连接服务端，返回在后台驱动的调用句柄对应的客户端
+async fn connect(addr: &Url) -> TableServiceClient<caller::Handle> {
+    let (caller, handle) = caller::Behavior::new(PROTOCOL, Config::default());
+    let (peer_id, transport) = build_transport();
+    let mut client = swarm::client::Swarm::new(
+        transport,
+        ClientBehavior {
+            ping: ping::outbound::Behavior::default(),
+            caller,
+        },
+        peer_id,
+        swarm::connection::PoolConfig::new(Box::new(TokioExecutor)),
+    );
+    client
+        .dial(DialOpts::new(Some(addr.clone()), None))
+        .unwrap();
+    let server = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            if let Some(swarm::client::SwarmEvent::ConnectionEstablished { peer_id, .. }) =
+                client.next().await
+            {
+                return peer_id;
+            }
+        }
+    })
+    .await
+    .expect("connection timed out");
+    tokio::spawn(caller::drive(client));
+    TableServiceClient::new(handle.with_timeout(Duration::from_secs(5)), server)
+}
+
+#[test]
+fn route_registers_every_method() {
+    let router = table_service::route(Router::new(), Arc::new(Table));
+    let mut services: Vec<_> = router.services().collect();
+    services.sort_unstable();
+    assert_eq!(
+        services,
+        [
+            table_service::GAME_ACTION,
+            table_service::JOIN_TABLE,
+            table_service::LEAVE_TABLE,
+            table_service::READY,
+            table_service::SIT_DOWN,
+            table_service::STAND_UP,
+        ]
+    );
+    assert_eq!(table_service::SIT_DOWN, "vela.table.sit_down");
+}
+
+#[tokio::test]
+async fn client_calls_reach_the_service() {
+    let addr = spawn_server(table_service::route(Router::new(), Arc::new(Table)));
+    let client = connect(&addr).await;
+
+    let response = client.sit_down(SitDownReq { index: 3 }).await.unwrap();
+    assert_eq!(response, SitDownResp { code: 3 });
+
+    let error = client
+        .join_table(JoinTableReq {
+            table_id: "t_1".to_string(),
+        })
+        .await
+        .unwrap_err();
+    match error {
+        CallError::Status(status) => {
+            assert_eq!(status.code_enum(), Code::NotFound);
+            assert_eq!(status.message, "Table t_1 not found");
+        }
+        error => panic!("unexpected error: {error:?}"),
+    }
+}
+
+#[tokio::test]
+async fn concurrent_calls_receive_their_own_responses() {
+    let addr = spawn_server(table_service::route(Router::new(), Arc::new(Table)));
+    let client = connect(&addr).await;
+
+    let (first, second, third) = futures::join!(
+        client.sit_down(SitDownReq { index: 1 }),
+        client.sit_down(SitDownReq { index: 2 }),
+        client.sit_down(SitDownReq { index: 3 }),
+    );
+    assert_eq!(first.unwrap().code, 1);
+    assert_eq!(second.unwrap().code, 2);
+    assert_eq!(third.unwrap().code, 3);
+}
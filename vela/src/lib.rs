@@ -1,3 +1,4 @@
+#[allow(dead_code)]
 trait SessionRegistry<S> {
     fn lookup(&self, id: &str) -> Option<&S>;
     fn insert(&mut self, id: String, session: S);